@@ -7,22 +7,43 @@ use std::process;
 
 use crossbeam_channel::{unbounded, select};
 
+use shared_resources::cli::Cli;
 use shared_resources::config::BackupConfig;
-use shared_resources::request::Request;
+use shared_resources::config_watcher;
+use shared_resources::logging;
+use shared_resources::metrics;
+use shared_resources::request::{Request, Stage};
 use network_rust::udpnet;
+use tracing::info;
 
 mod process_pair;
 
 pub fn main() -> Result<()> {
-    let config = BackupConfig::get();
+    let cli = Cli::parse_for("backup", "Safely stores the hall requests received from the master");
+    let config = BackupConfig::get(&cli);
+    logging::init("backup", &config.node)?;
+    if let Some(path) = &cli.record {
+        udpnet::recording::record_to(path)?;
+    }
+    if let Some(path) = &cli.replay {
+        udpnet::recording::replay_from(path)?;
+    }
     let num_floors = config.elevator.num_floors;
     let program_dir = PathBuf::from("./.");
     let program_path: String = fs::canonicalize(&program_dir).unwrap().into_os_string().into_string().unwrap();
     let process_pair_port = config.network.pp_port;
-    let process_pair_handle = thread::spawn(move || process_pair::process_pair(num_floors, process_pair_port));
+    let node = config.node.clone();
+    let process_pair_handle = thread::spawn(move || process_pair::process_pair(&node, num_floors, process_pair_port));
     let mut backup_data = process_pair_handle.join().unwrap();
 
-    process_pair::spawn_process_pair(program_path);
+    if cli.spawns_process_pair() {
+        process_pair::spawn_process_pair(program_path, cli.process_pair_args(), config.node.headless);
+    }
+    metrics::serve(&config.metrics);
+
+    // The backup has no settings that can be changed while running, but watching
+    // the configuration reports changes that require a restart.
+    config_watcher::watch::<BackupConfig>(cli, Vec::new())?;
 
     let (changed_requests_tx, changed_requests_rx) = unbounded::<(Vec<Request>,Vec<Request>)>();
     thread::Builder::new().name("master_to_backup".to_string()).spawn(move || {
@@ -44,17 +65,20 @@ pub fn main() -> Result<()> {
         }
     }).unwrap();
 
-    println!("Backup is running...");
+    info!("Backup is running...");
     loop {
         select!{
             recv(changed_requests_rx) -> msg => {
                 let changed_requests = msg.unwrap();
 
                 for new_request in changed_requests.0 {
-                    backup_data[new_request.floor as usize][new_request.call as usize] = true;
+                    if !backup_data[new_request.floor as usize][new_request.column()] {
+                        new_request.log_stage(Stage::Stored);
+                    }
+                    backup_data[new_request.floor as usize][new_request.column()] = true;
                 }
                 for served_request in changed_requests.1 {
-                    backup_data[served_request.floor as usize][served_request.call as usize] = false;
+                    backup_data[served_request.floor as usize][served_request.column()] = false;
                 }
 
                 broadcast_backup_data_tx.send(backup_data.clone()).unwrap();
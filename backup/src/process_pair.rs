@@ -1,33 +1,39 @@
+use std::env;
 use std::thread;
 use std::process;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::time::Duration;
 
 use crossbeam_channel::{select, unbounded};
+use tracing::info;
 
-use shared_resources::call::Call;
 use network_rust::udpnet;
 use network_rust::udpnet::bcast::BcError;
+use shared_resources::cli::{self, Role};
+use shared_resources::config::NodeConfig;
+use shared_resources::request::Request;
 
-pub fn process_pair(num_floors: u8, process_pair_port: u16) -> Vec<Vec<bool>> {
-    println!("BACKUP process pair running on port: {:#?}\n---------------------", process_pair_port);
+pub fn process_pair(node: &NodeConfig, num_floors: u8, process_pair_port: u16) -> Vec<Vec<bool>> {
+    let mut backup_data = vec![vec![false; Request::hall_columns(num_floors)]; num_floors as usize];
+    if node.role == Role::Primary {
+        return backup_data;
+    }
+    info!(port = process_pair_port, "Backup process pair running, waiting for the primary to go silent");
 
     let (process_pair_tx, process_pair_rx) = unbounded::<Vec<Vec<bool>>>();
     thread::Builder::new().name("process_pair_recieve_from_master".to_string()).spawn(move || {
-        match udpnet::bcast::rx(process_pair_port, process_pair_tx) {
-            Err(BcError::IOError(_e)) => process::exit(1),
-            _ => (),
+        if let Err(BcError::IOError(_e)) = udpnet::bcast::rx(process_pair_port, process_pair_tx) {
+            process::exit(1);
         }
     }).ok();
 
-    let mut backup_data = vec![vec![false; Call::num_hall_calls() as usize]; num_floors as usize];
-
     loop {
         select! {
             recv(process_pair_rx) -> data => {
                 backup_data = data.unwrap_or(backup_data);
             },
             default(Duration::from_secs(2)) => {
+                info!("Primary went silent, taking over");
                 return backup_data;
             }
         }
@@ -35,26 +41,35 @@ pub fn process_pair(num_floors: u8, process_pair_port: u16) -> Vec<Vec<bool>> {
 }
 
 pub fn spawn_process_pair(
-    program_path: String
+    program_path: String,
+    args: Vec<String>,
+    headless: bool,
 ) {
-    if cfg!(target_os = "linux") {
+    if headless {
+        // The process pair is meant to outlive this program, so it is never waited on
+        #[allow(clippy::zombie_processes)]
+        let _process_pair = Command::new(env::current_exe().expect("failed to locate executable"))
+            .args(args)
+            .current_dir(program_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to spawn process pair");
+    } else if cfg!(target_os = "linux") {
+        // The arguments are passed on as they are, without a shell to split them
         Command::new("gnome-terminal")
-            .arg("--")
-            .arg("/bin/sh")
-            .arg("-c")
-            .arg("cd ".to_owned()
-                + &program_path
-                + " && "
-                + "cargo run")
+            .arg(format!("--working-directory={}", program_path))
+            .args(["--", "cargo", "run", "--"])
+            .args(&args)
             .output()
             .expect("failed to spawn process pair");
     } else if cfg!(target_os = "macos") {
+        let args: Vec<String> = args.iter().map(|arg| cli::shell_quote(arg)).collect();
+        let command = format!("cd {} && cargo run -- {}", cli::shell_quote(&program_path), args.join(" "));
         Command::new("osascript")
             .arg("-e")
-            .arg("tell app \"Terminal\" to do script \"cd ".to_owned() 
-                + &program_path
-                + " && "
-                + "cargo run\"")
+            .arg(format!("tell app \"Terminal\" to do script \"{}\"", command.replace('\\', "\\\\").replace('"', "\\\"")))
             .output()
             .expect("failed to spawn process pair");
     }
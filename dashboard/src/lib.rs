@@ -0,0 +1,2 @@
+pub mod utilities;
+pub mod modules;
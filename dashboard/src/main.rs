@@ -0,0 +1,5 @@
+use dashboard::modules;
+
+fn main() -> std::io::Result<()> {
+    modules::run()
+}
@@ -0,0 +1,44 @@
+//! ----- FEEDS MODULE -----
+//! This module listens to the messages broadcast between the nodes, which any
+//! computer on the network can receive without disturbing the nodes. The
+//! process pairs talk over localhost and are not listened to, since only one
+//! listener on a computer receives those packets.
+
+use std::net::SocketAddr;
+use std::thread;
+
+use crossbeam_channel::Sender;
+use network_rust::udpnet::{self, bcast::BcError};
+
+use shared_resources::command_message::CommandMessage;
+use shared_resources::config::DashboardConfig;
+use shared_resources::elevator_message::ElevatorMessage;
+
+pub fn start(
+    config: &DashboardConfig,
+    elevator_message_tx: Sender<(SocketAddr, ElevatorMessage)>,
+    command_tx: Sender<(SocketAddr, CommandMessage)>,
+    backup_tx: Sender<(SocketAddr, Vec<Vec<bool>>)>,
+    error_tx: Sender<String>,
+) {
+    for &port in &config.network.update_ports {
+        listen(port, elevator_message_tx.clone(), error_tx.clone());
+    }
+    // The master sends the same message on every command port
+    if let Some(&port) = config.network.command_ports.first() {
+        listen(port, command_tx, error_tx.clone());
+    }
+    listen(config.network.backup_ack_port, backup_tx, error_tx);
+}
+
+fn listen<T: serde::de::DeserializeOwned + Send + 'static>(
+    port: u16,
+    tx: Sender<(SocketAddr, T)>,
+    error_tx: Sender<String>,
+) {
+    thread::Builder::new().name(format!("feed_{}", port)).spawn(move || {
+        if let Err(BcError::IOError(e)) = udpnet::bcast::rx_from(port, tx) {
+            error_tx.send(format!("Could not listen on port {}: {}", port, e)).ok();
+        }
+    }).unwrap();
+}
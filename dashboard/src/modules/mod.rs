@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::io::Result;
+use std::net::{IpAddr, SocketAddr};
+use std::thread;
+use std::time::Duration;
+
+use clap::Parser;
+use crossbeam_channel::{select, tick, unbounded, Sender};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+
+use shared_resources::cli::ConfigArgs;
+use shared_resources::config::DashboardConfig;
+
+use crate::utilities::cluster::{EventLevel, Node};
+
+mod feeds;
+mod scraper;
+mod ui;
+
+const REDRAW_PERIOD: f64 = 0.1;
+
+#[derive(Parser)]
+#[command(name = "dashboard", about = "Shows the state of every node in the cluster as seen from the network")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+pub fn run() -> Result<()> {
+    let args = Args::parse();
+    let config = DashboardConfig::get(&args.config);
+
+    let (elevator_message_tx, elevator_message_rx) = unbounded();
+    let (command_tx, command_rx) = unbounded();
+    let (backup_tx, backup_rx) = unbounded();
+    let (error_tx, error_rx) = unbounded();
+    feeds::start(&config, elevator_message_tx, command_tx, backup_tx, error_tx);
+
+    let (address_tx, address_rx) = unbounded();
+    let (scrape_tx, scrape_rx) = unbounded();
+    if config.metrics.enabled {
+        let config = config.clone();
+        thread::Builder::new().name("scraper".to_string()).spawn(move || scraper::main(config, address_rx, scrape_tx))?;
+    }
+
+    let (key_tx, key_rx) = unbounded();
+    thread::Builder::new().name("keys".to_string()).spawn(move || {
+        while let Ok(event) = event::read() {
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press && key_tx.send(key).is_err() {
+                    return
+                }
+            }
+        }
+    })?;
+
+    let mut dashboard = ui::Dashboard::new(&config);
+    let mut addresses: HashMap<Node, IpAddr> = HashMap::new();
+    let mut record_address = |node: Node, address: SocketAddr, address_tx: &Sender<(Node, IpAddr)>| {
+        if addresses.insert(node, address.ip()) != Some(address.ip()) {
+            address_tx.send((node, address.ip())).ok();
+        }
+    };
+    let timer = tick(Duration::from_secs_f64(REDRAW_PERIOD));
+
+    let mut terminal = ratatui::init();
+    let result = loop {
+        select! {
+            recv(elevator_message_rx) -> msg => {
+                let (address, message) = msg.unwrap();
+                if let Ok(elevnum) = message.id.parse::<u8>() {
+                    record_address(Node::Slave(elevnum), address, &address_tx);
+                }
+                dashboard.cluster.update_elevator(address.ip(), message);
+            },
+            recv(command_rx) -> msg => {
+                let (address, message) = msg.unwrap();
+                record_address(Node::Master, address, &address_tx);
+                dashboard.cluster.update_master(address.ip(), message);
+            },
+            recv(backup_rx) -> msg => {
+                let (address, hall_requests) = msg.unwrap();
+                record_address(Node::Backup, address, &address_tx);
+                dashboard.cluster.update_backup(address.ip(), hall_requests);
+            },
+            recv(error_rx) -> msg => {
+                dashboard.cluster.log(EventLevel::Warning, msg.unwrap());
+            },
+            recv(scrape_rx) -> msg => {
+                dashboard.packet_stats.update(msg.unwrap());
+            },
+            recv(key_rx) -> msg => {
+                if !dashboard.handle_key(msg.unwrap()) {
+                    break Ok(())
+                }
+                if let Err(e) = terminal.draw(|frame| dashboard.draw(frame)) {
+                    break Err(e)
+                }
+            },
+            recv(timer) -> _ => {
+                dashboard.cluster.check_timeouts();
+                if let Err(e) = terminal.draw(|frame| dashboard.draw(frame)) {
+                    break Err(e)
+                }
+            },
+        }
+    };
+    ratatui::restore();
+    result
+}
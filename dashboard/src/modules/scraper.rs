@@ -0,0 +1,65 @@
+//! ----- SCRAPER MODULE -----
+//! This module periodically reads the metrics of every node over HTTP and
+//! passes their UDP packet counters on to the UI module. Nodes serving metrics
+//! on a loopback address are reached on this computer, and other nodes on the
+//! address their broadcasts are received from.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use shared_resources::config::DashboardConfig;
+
+use crate::utilities::cluster::Node;
+use crate::utilities::packet_stats::{self, Scrape};
+
+const SCRAPE_PERIOD: f64 = 1.0;
+const REQUEST_TIMEOUT: f64 = 0.2;
+
+pub fn main(config: DashboardConfig, address_rx: Receiver<(Node, IpAddr)>, scrape_tx: Sender<Scrape>) {
+    let configured_address = config.metrics.address.parse::<IpAddr>().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    let mut ports = vec![(Node::Master, config.metrics.master_port), (Node::Backup, config.metrics.backup_port)];
+    for (elevnum, &port) in config.metrics.slave_ports.iter().enumerate() {
+        ports.push((Node::Slave(elevnum as u8), port));
+    }
+
+    let mut seen_addresses: HashMap<Node, IpAddr> = HashMap::new();
+    loop {
+        seen_addresses.extend(address_rx.try_iter());
+        let mut nodes = HashMap::new();
+        for &(node, port) in &ports {
+            let address = if configured_address.is_loopback() {
+                configured_address
+            } else {
+                seen_addresses.get(&node).copied().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            };
+            if let Ok(metrics) = get_metrics(SocketAddr::new(address, port)) {
+                nodes.insert(node, packet_stats::parse(&metrics));
+            }
+        }
+        if scrape_tx.send(Scrape { taken: Instant::now(), nodes }).is_err() {
+            return
+        }
+        thread::sleep(Duration::from_secs_f64(SCRAPE_PERIOD));
+    }
+}
+
+fn get_metrics(address: SocketAddr) -> io::Result<String> {
+    let timeout = Duration::from_secs_f64(REQUEST_TIMEOUT);
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(stream, "GET /metrics HTTP/1.0\r\nHost: {}\r\n\r\n", address)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))?;
+    if !head.lines().next().is_some_and(|status_line| status_line.contains(" 200 ")) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "metrics request failed"))
+    }
+    Ok(body.to_string())
+}
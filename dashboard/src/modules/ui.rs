@@ -0,0 +1,306 @@
+//! ----- UI MODULE -----
+//! This module draws the dashboard, with the health of every node, a shaft
+//! diagram of the elevators with their hall and cab lamps, the hall requests
+//! with their priority, who they are assigned to and when it is expected to
+//! arrive, packet loss on each link between the nodes and a scrolling event log.
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::Frame;
+
+use shared_resources::call::Call;
+use shared_resources::config::DashboardConfig;
+use shared_resources::elevator_message::ServiceMode;
+use shared_resources::request::Request;
+
+use crate::utilities::cluster::{hall_call_name, Cluster, EventLevel, Node, NodeHealth};
+use crate::utilities::packet_stats::{self, Link, PacketStats};
+
+/// Number of events scrolled by page up and page down
+const PAGE_SIZE: usize = 10;
+
+pub struct Dashboard {
+    pub cluster: Cluster,
+    pub packet_stats: PacketStats,
+    links: Vec<Link>,
+    metrics_enabled: bool,
+    /// Number of events scrolled back from the newest event
+    scroll: usize,
+}
+
+impl Dashboard {
+    pub fn new(config: &DashboardConfig) -> Self {
+        Dashboard {
+            cluster: Cluster::new(config),
+            packet_stats: PacketStats::new(),
+            links: packet_stats::links(config),
+            metrics_enabled: config.metrics.enabled,
+            scroll: 0,
+        }
+    }
+
+    /// Handles a key press, returning false if the dashboard should quit
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up => self.scroll += 1,
+            KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll += PAGE_SIZE,
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE_SIZE),
+            KeyCode::Home => self.scroll = self.cluster.events.len(),
+            KeyCode::End => self.scroll = 0,
+            _ => (),
+        }
+        self.scroll = self.scroll.min(self.cluster.events.len().saturating_sub(1));
+        true
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let num_slaves = self.cluster.slaves.len() as u16;
+        let shaft_height = self.cluster.num_floors as u16 + 3;
+        let network_height = self.links.len() as u16 + 3;
+        let [top, middle, events] = Layout::vertical([
+            Constraint::Length(num_slaves + 5),
+            Constraint::Length(shaft_height.max(network_height)),
+            Constraint::Min(5),
+        ]).areas(frame.area());
+        let [nodes, hall_requests] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(top);
+        let [shafts, network] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(middle);
+
+        self.draw_nodes(frame, nodes);
+        self.draw_hall_requests(frame, hall_requests);
+        self.draw_shafts(frame, shafts);
+        self.draw_network(frame, network);
+        self.draw_events(frame, events);
+    }
+
+    fn draw_nodes(&self, frame: &mut Frame, area: Rect) {
+        let cluster = &self.cluster;
+        let master_state = match cluster.recall_floor {
+            Some(floor) => format!("recalling to floor {}", floor),
+            None => format!(
+                "{} hall calls assigned",
+                cluster.assignments.values().flatten().flatten().filter(|&&active| active).count()
+            ),
+        };
+        let mut rows = vec![
+            self.node_row(Node::Master, &cluster.master, master_state),
+            self.node_row(Node::Backup, &cluster.backup, format!(
+                "{} hall calls stored",
+                cluster.hall_requests.iter().flatten().filter(|&&active| active).count()
+            )),
+        ];
+        for (&elevnum, slave) in &cluster.slaves {
+            let state = match &slave.state {
+                Some(state) => format!(
+                    "{} at floor {}, going {}, {:.0} kg{}{}",
+                    state.behaviour, state.floor, state.direction, state.load, if state.full { " (full)" } else { "" },
+                    if state.mode == ServiceMode::Normal { String::new() } else { format!(", {}", state.mode.as_str()) },
+                ),
+                None => String::new(),
+            };
+            rows.push(self.node_row(Node::Slave(elevnum), &slave.health, state));
+        }
+        let mut header = vec!["Node", "Address", "Last seen", "Status"];
+        let mut widths = vec![Constraint::Length(8), Constraint::Length(16), Constraint::Length(10), Constraint::Length(9)];
+        if self.metrics_enabled {
+            header.push("Metrics");
+            widths.push(Constraint::Length(12));
+        }
+        header.push("State");
+        widths.push(Constraint::Min(10));
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(Block::bordered().title(" Nodes "));
+        frame.render_widget(table, area);
+    }
+
+    fn node_row(&self, node: Node, health: &NodeHealth, state: String) -> Row<'static> {
+        let (status, color) = if health.alive() {
+            ("up", Color::Green)
+        } else if health.last_seen.is_some() {
+            ("silent", Color::Red)
+        } else {
+            ("not seen", Color::DarkGray)
+        };
+        let last_seen = match health.last_seen {
+            Some(last_seen) => format!("{:.1} s", last_seen.elapsed().as_secs_f64()),
+            None => String::from("-"),
+        };
+        let address = health.address.map(|address| address.to_string()).unwrap_or_default();
+        let mut cells = vec![
+            Span::raw(node.to_string()),
+            Span::raw(address),
+            Span::raw(last_seen),
+            Span::styled(status, Style::new().fg(color)),
+        ];
+        if self.metrics_enabled {
+            cells.push(if self.packet_stats.reachable(node) {
+                Span::styled("reachable", Style::new().fg(Color::Green))
+            } else {
+                Span::styled("unreachable", Style::new().fg(Color::DarkGray))
+            });
+        }
+        cells.push(Span::raw(state));
+        Row::new(cells)
+    }
+
+    fn draw_hall_requests(&self, frame: &mut Frame, area: Rect) {
+        let cluster = &self.cluster;
+        let mut rows = Vec::new();
+        for floor in (0..cluster.num_floors as usize).rev() {
+            for call in 0..Request::hall_columns(cluster.num_floors) {
+                let stored = cluster.hall_requests.get(floor).is_some_and(|calls| calls.get(call).copied().unwrap_or(false));
+                let assigned_to = cluster.assigned_to(floor, call);
+                if !stored && assigned_to.is_empty() {
+                    continue;
+                }
+                let trace_id = cluster.trace_id(floor, call).map(|trace_id| trace_id.to_string()).unwrap_or_default();
+                let arrival = cluster.arrival(floor, call)
+                    .map(|arrival| format!("car {} in ~{:.0} s", arrival.elevator, arrival.seconds))
+                    .unwrap_or_default();
+                // Priority requests stand out, as they are served before the others
+                let priority = cluster.priority(floor, call);
+                let (call_name, priority) = if priority > 0 {
+                    let style = Style::new().fg(Color::Red).add_modifier(Modifier::BOLD);
+                    (Span::styled(hall_call_name(floor, call), style), Span::styled(priority.to_string(), style))
+                } else {
+                    (Span::raw(hall_call_name(floor, call)), Span::raw(""))
+                };
+                rows.push(Row::new(vec![
+                    call_name,
+                    priority,
+                    if stored { Span::styled("yes", Style::new().fg(Color::Green)) } else { Span::styled("no", Style::new().fg(Color::Yellow)) },
+                    Span::raw(assigned_to.join(", ")),
+                    Span::raw(arrival),
+                    Span::raw(trace_id),
+                ]));
+            }
+        }
+        let widths = [Constraint::Length(8), Constraint::Length(9), Constraint::Length(7), Constraint::Length(12), Constraint::Length(15), Constraint::Min(16)];
+        let table = Table::new(rows, widths)
+            .header(Row::new(["Call", "Priority", "Stored", "Assigned to", "Arrival", "Trace id"]).style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(Block::bordered().title(" Hall requests "));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_shafts(&self, frame: &mut Frame, area: Rect) {
+        let cluster = &self.cluster;
+        let mut header = vec![Span::raw("Floor  Hall ")];
+        for elevnum in cluster.slaves.keys() {
+            header.push(Span::raw(format!("  E{:<3}", elevnum)));
+        }
+        let mut lines = vec![Line::from(header).style(Style::new().add_modifier(Modifier::BOLD))];
+
+        let lamp = |lit: bool, symbol: &'static str| if lit {
+            Span::styled(symbol, Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        } else {
+            Span::styled(symbol, Style::new().fg(Color::DarkGray))
+        };
+        for floor in (0..cluster.num_floors as usize).rev() {
+            let calls = cluster.hall_requests.get(floor);
+            let mut spans = vec![Span::raw(format!("{:>5}  ", floor))];
+            spans.push(if floor + 1 < cluster.num_floors as usize {
+                lamp(calls.is_some_and(|calls| calls[Call::HallUp as usize]), "▲")
+            } else {
+                Span::raw(" ")
+            });
+            spans.push(if floor > 0 {
+                lamp(calls.is_some_and(|calls| calls[Call::HallDown as usize]), "▼")
+            } else {
+                Span::raw(" ")
+            });
+            spans.push(Span::raw("  "));
+
+            for slave in cluster.slaves.values() {
+                spans.push(Span::raw("  "));
+                let car = slave.state.as_ref().filter(|state| state.floor as usize == floor);
+                match car {
+                    Some(state) => {
+                        let symbol = match (state.behaviour.as_str(), state.direction.as_str()) {
+                            ("doorOpen", _) => "[ ]",
+                            ("moving", "up") => "[▲]",
+                            ("moving", "down") => "[▼]",
+                            _ => "[■]",
+                        };
+                        let color = if !slave.health.alive() {
+                            Color::DarkGray
+                        } else if state.behaviour == "doorOpen" {
+                            Color::Cyan
+                        } else {
+                            Color::Green
+                        };
+                        spans.push(Span::styled(symbol, Style::new().fg(color).add_modifier(Modifier::BOLD)));
+                    },
+                    None => spans.push(Span::styled("│ │", Style::new().fg(Color::DarkGray))),
+                }
+                let cab_lamp = slave.state.as_ref()
+                    .is_some_and(|state| state.cab_requests.get(floor).copied().unwrap_or(false));
+                spans.push(if cab_lamp { lamp(true, "●") } else { Span::raw(" ") });
+            }
+            lines.push(Line::from(spans));
+        }
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Shafts ")), area);
+    }
+
+    fn draw_network(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Network ");
+        if !self.metrics_enabled {
+            let text = Paragraph::new("Metrics are disabled, enable them to see packet loss").block(block);
+            frame.render_widget(text, area);
+            return
+        }
+        let rows = self.links.iter().map(|link| {
+            let stats = self.packet_stats.link_stats(link);
+            let loss = match stats.loss {
+                Some(loss) => {
+                    let color = if loss > 0.1 { Color::Red } else if loss > 0.01 { Color::Yellow } else { Color::Green };
+                    Span::styled(format!("{:.1} %", loss * 100.0), Style::new().fg(color))
+                },
+                None => Span::raw("-"),
+            };
+            Row::new(vec![
+                Span::raw(link.port.to_string()),
+                Span::raw(format!("{} → {}", link.from, link.to)),
+                Span::raw(stats.rate.map(|rate| format!("{:.1}/s", rate)).unwrap_or_else(|| String::from("-"))),
+                loss,
+                Span::raw(stats.dropped.map(|dropped| format!("{}", dropped)).unwrap_or_else(|| String::from("-"))),
+            ])
+        });
+        let widths = [Constraint::Length(6), Constraint::Length(20), Constraint::Length(9), Constraint::Length(8), Constraint::Min(7)];
+        let table = Table::new(rows, widths)
+            .header(Row::new(["Port", "Link", "Rate", "Loss", "Dropped"]).style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(block);
+        frame.render_widget(table, area);
+    }
+
+    fn draw_events(&self, frame: &mut Frame, area: Rect) {
+        let events = &self.cluster.events;
+        let height = area.height.saturating_sub(2) as usize;
+        let end = events.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+        let items: Vec<ListItem> = events.range(start..end).map(|event| {
+            let style = match event.level {
+                EventLevel::Info => Style::new(),
+                EventLevel::Warning => Style::new().fg(Color::Yellow),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(event.time.format("%H:%M:%S%.3f ").to_string(), Style::new().fg(Color::DarkGray)),
+                Span::styled(event.text.clone(), style),
+            ]))
+        }).collect();
+        let title = if self.scroll > 0 {
+            format!(" Events, {} newer hidden (End to follow) ", self.scroll)
+        } else {
+            String::from(" Events ")
+        };
+        let block = Block::bordered()
+            .title(title)
+            .title_bottom(Line::from(" q quit  ↑/↓ PgUp/PgDn Home/End scroll ").right_aligned());
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
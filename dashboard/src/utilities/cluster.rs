@@ -0,0 +1,308 @@
+//! ----- CLUSTER -----
+//! This module keeps the state of the whole cluster as seen from the messages
+//! broadcast between the nodes, and turns changes to that state into events
+//! for the event log.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use shared_resources::call::Call;
+use shared_resources::command_message::{Arrival, CommandMessage};
+use shared_resources::config::DashboardConfig;
+use shared_resources::elevator_message::ElevatorMessage;
+use shared_resources::request::{Request, TraceId};
+
+/// Number of events kept in the event log
+const EVENT_LOG_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Node {
+    Master,
+    Backup,
+    Slave(u8),
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Master => write!(f, "master"),
+            Node::Backup => write!(f, "backup"),
+            Node::Slave(elevnum) => write!(f, "slave {}", elevnum),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventLevel {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub time: DateTime<Local>,
+    pub level: EventLevel,
+    pub text: String,
+}
+
+/// When a node was last heard from, and from what address
+#[derive(Debug, Clone)]
+pub struct NodeHealth {
+    pub address: Option<IpAddr>,
+    pub last_seen: Option<Instant>,
+    pub timeout: Duration,
+    silent: bool,
+}
+
+impl NodeHealth {
+    fn new(timeout: Duration) -> Self {
+        NodeHealth {
+            address: None,
+            last_seen: None,
+            timeout,
+            silent: true,
+        }
+    }
+
+    /// Records a message from the node, returning true if the node was silent
+    fn seen(&mut self, address: IpAddr) -> bool {
+        self.address = Some(address);
+        self.last_seen = Some(Instant::now());
+        let was_silent = self.silent;
+        self.silent = false;
+        was_silent
+    }
+
+    pub fn alive(&self) -> bool {
+        self.last_seen.is_some_and(|last_seen| last_seen.elapsed() < self.timeout)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlaveView {
+    pub health: NodeHealth,
+    pub state: Option<ElevatorMessage>,
+}
+
+pub struct Cluster {
+    pub num_floors: u8,
+    pub master: NodeHealth,
+    pub backup: NodeHealth,
+    pub slaves: BTreeMap<u8, SlaveView>,
+    /// Hall requests stored by the backup
+    pub hall_requests: Vec<Vec<bool>>,
+    pub assignments: HashMap<String, Vec<Vec<bool>>>,
+    pub trace_ids: Vec<Vec<Option<TraceId>>>,
+    /// Priority of each active hall request, 0 for normal requests
+    pub priorities: Vec<Vec<u8>>,
+    /// Predicted arrival at each assigned hall request
+    pub arrivals: Vec<Vec<Option<Arrival>>>,
+    /// Floor every elevator is recalled to, while an emergency recall is active
+    pub recall_floor: Option<u8>,
+    pub events: VecDeque<Event>,
+    slave_timeout: Duration,
+    /// Trace ids of the hall button presses already logged
+    pressed: HashSet<TraceId>,
+    /// Trace ids of the hall requests already logged as cancelled by their passenger
+    cancelled: HashSet<TraceId>,
+}
+
+impl Cluster {
+    pub fn new(config: &DashboardConfig) -> Self {
+        let num_floors = config.elevator.num_floors;
+        let master_timeout = Duration::from_secs_f64(config.timing.master_timeout);
+        let backup_timeout = Duration::from_secs_f64(config.timing.backup_timeout);
+        let slave_timeout = Duration::from_secs_f64(config.timing.slave_timeout);
+        Cluster {
+            num_floors,
+            master: NodeHealth::new(master_timeout),
+            backup: NodeHealth::new(backup_timeout),
+            slaves: (0..config.elevator.num_elevators)
+                .map(|elevnum| (elevnum, SlaveView { health: NodeHealth::new(slave_timeout), state: None }))
+                .collect(),
+            hall_requests: vec![vec![false; Request::hall_columns(num_floors)]; num_floors as usize],
+            assignments: HashMap::new(),
+            trace_ids: vec![vec![None; Request::hall_columns(num_floors)]; num_floors as usize],
+            priorities: vec![vec![0; Request::hall_columns(num_floors)]; num_floors as usize],
+            arrivals: Vec::new(),
+            recall_floor: None,
+            events: VecDeque::new(),
+            slave_timeout,
+            pressed: HashSet::new(),
+            cancelled: HashSet::new(),
+        }
+    }
+
+    pub fn log(&mut self, level: EventLevel, text: String) {
+        if self.events.len() == EVENT_LOG_SIZE {
+            self.events.pop_front();
+        }
+        self.events.push_back(Event { time: Local::now(), level, text });
+    }
+
+    pub fn update_elevator(&mut self, address: IpAddr, message: ElevatorMessage) {
+        let Ok(elevnum) = message.id.parse::<u8>() else {
+            self.log(EventLevel::Warning, format!("Ignoring update from unknown elevator \"{}\"", message.id));
+            return
+        };
+        let slave_timeout = self.slave_timeout;
+        let slave = self.slaves.entry(elevnum)
+            .or_insert_with(|| SlaveView { health: NodeHealth::new(slave_timeout), state: None });
+        let reconnected = slave.health.seen(address);
+        let previous = slave.state.replace(message.clone());
+
+        if reconnected {
+            self.log(EventLevel::Info, format!("{} is up at {}", Node::Slave(elevnum), address));
+        }
+        if let Some(previous) = previous {
+            if message.floor != previous.floor {
+                self.log(EventLevel::Info, format!("Elevator {} reached floor {}", elevnum, message.floor));
+            }
+            if message.behaviour == "doorOpen" && previous.behaviour != "doorOpen" {
+                self.log(EventLevel::Info, format!("Elevator {} opened its doors at floor {}", elevnum, message.floor));
+            }
+            for (floor, (&active, &previously_active)) in message.cab_requests.iter().zip(&previous.cab_requests).enumerate() {
+                if active && !previously_active {
+                    self.log(EventLevel::Info, format!("Cab call to floor {} in elevator {}", floor, elevnum));
+                }
+            }
+        }
+        for request in message.new_hall_orders {
+            let Some(trace_id) = request.trace_id else {
+                continue
+            };
+            if self.pressed.insert(trace_id) {
+                let priority = if request.priority > 0 { format!(" with priority {}", request.priority) } else { String::new() };
+                self.log(EventLevel::Info, format!(
+                    "Hall call {} pressed at elevator {}{} [{}]",
+                    hall_call_name(request.floor as usize, request.column()), elevnum, priority, trace_id
+                ));
+            }
+        }
+        for request in message.cancelled_hall_orders {
+            let Some(trace_id) = request.trace_id else {
+                continue
+            };
+            if self.cancelled.insert(trace_id) {
+                self.log(EventLevel::Info, format!(
+                    "Hall call {} cancelled by a long press at elevator {} [{}]",
+                    hall_call_name(request.floor as usize, request.column()), elevnum, trace_id
+                ));
+            }
+        }
+    }
+
+    pub fn update_master(&mut self, address: IpAddr, message: CommandMessage) {
+        if self.master.seen(address) {
+            self.log(EventLevel::Info, format!("{} is up at {}", Node::Master, address));
+        }
+        if message.recall_floor != self.recall_floor {
+            match message.recall_floor {
+                Some(floor) => self.log(EventLevel::Warning, format!("Elevators recalled to floor {}", floor)),
+                None => self.log(EventLevel::Info, String::from("Recall reset")),
+            }
+            self.recall_floor = message.recall_floor;
+        }
+        let mut assigned = Vec::new();
+        for (id, requests) in &message.assigned_hall_requests {
+            for (floor, calls) in requests.iter().enumerate() {
+                for (call, &active) in calls.iter().enumerate() {
+                    let previously_assigned = self.assignments.get(id)
+                        .is_some_and(|requests| requests.get(floor).is_some_and(|calls| calls.get(call).copied().unwrap_or(false)));
+                    if active && !previously_assigned {
+                        assigned.push(format!("Hall call {} assigned to elevator {}", hall_call_name(floor, call), id));
+                    }
+                }
+            }
+        }
+        for text in assigned {
+            self.log(EventLevel::Info, text);
+        }
+        self.assignments = message.assigned_hall_requests;
+        self.trace_ids = message.trace_ids;
+        self.priorities = message.priorities;
+        self.arrivals = message.arrivals;
+    }
+
+    pub fn update_backup(&mut self, address: IpAddr, hall_requests: Vec<Vec<bool>>) {
+        if self.backup.seen(address) {
+            self.log(EventLevel::Info, format!("{} is up at {}", Node::Backup, address));
+        }
+        let mut changes = Vec::new();
+        for (floor, calls) in hall_requests.iter().enumerate() {
+            for (call, &active) in calls.iter().enumerate() {
+                let previously_active = self.hall_requests.get(floor).is_some_and(|calls| calls.get(call).copied().unwrap_or(false));
+                if active && !previously_active {
+                    changes.push(format!("Hall call {} stored by the backup", hall_call_name(floor, call)));
+                } else if !active && previously_active {
+                    changes.push(format!("Hall call {} cleared", hall_call_name(floor, call)));
+                }
+            }
+        }
+        for text in changes {
+            self.log(EventLevel::Info, text);
+        }
+        self.hall_requests = hall_requests;
+    }
+
+    /// Logs the nodes that have gone silent since the last check
+    pub fn check_timeouts(&mut self) {
+        let mut silent = Vec::new();
+        for (node, health) in [(Node::Master, &mut self.master), (Node::Backup, &mut self.backup)].into_iter()
+            .chain(self.slaves.iter_mut().map(|(&elevnum, slave)| (Node::Slave(elevnum), &mut slave.health)))
+        {
+            if !health.silent && !health.alive() {
+                health.silent = true;
+                silent.push(node);
+            }
+        }
+        for node in silent {
+            self.log(EventLevel::Warning, format!("{} went silent", node));
+        }
+        // Forgetting old button presses may log a press again, which is harmless
+        if self.pressed.len() > EVENT_LOG_SIZE {
+            self.pressed.clear();
+        }
+        if self.cancelled.len() > EVENT_LOG_SIZE {
+            self.cancelled.clear();
+        }
+    }
+
+    /// Elevators the hall request is assigned to, sorted by id
+    pub fn assigned_to(&self, floor: usize, call: usize) -> Vec<String> {
+        let mut ids: Vec<String> = self.assignments.iter()
+            .filter(|(_, requests)| requests.get(floor).is_some_and(|calls| calls.get(call).copied().unwrap_or(false)))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    pub fn trace_id(&self, floor: usize, call: usize) -> Option<TraceId> {
+        self.trace_ids.get(floor).and_then(|calls| calls.get(call).copied().flatten())
+    }
+
+    /// Predicted arrival of the elevator assigned the hall request
+    pub fn arrival(&self, floor: usize, call: usize) -> Option<&Arrival> {
+        self.arrivals.get(floor).and_then(|calls| calls.get(call)).and_then(Option::as_ref)
+    }
+
+    /// Priority level of the hall request, 0 for normal requests
+    pub fn priority(&self, floor: usize, call: usize) -> u8 {
+        self.priorities.get(floor).and_then(|calls| calls.get(call).copied()).unwrap_or(0)
+    }
+}
+
+/// Name of the hall call at `column` of the hall request table of `floor`
+pub fn hall_call_name(floor: usize, column: usize) -> String {
+    let request = Request::from_column(floor as u8, column, None);
+    match (request.call, request.destination) {
+        (Call::Destination, Some(destination)) => format!("{} to {}", floor, destination),
+        (Call::HallUp, _) => format!("up {}", floor),
+        _ => format!("down {}", floor),
+    }
+}
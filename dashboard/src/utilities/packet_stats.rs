@@ -0,0 +1,145 @@
+//! ----- PACKET STATS -----
+//! This module parses the UDP packet counters served by each node in the
+//! Prometheus text format, and estimates the packet loss on each link between
+//! two nodes from how many packets one node sent and the other received.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use shared_resources::config::DashboardConfig;
+
+use crate::utilities::cluster::Node;
+
+/// Number of scrapes the rates and packet loss are computed over
+const WINDOW: usize = 5;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PortCounters {
+    pub sent: f64,
+    pub received: f64,
+    pub dropped: f64,
+}
+
+/// The packet counters of every node that could be reached at one point in time
+#[derive(Debug, Clone)]
+pub struct Scrape {
+    pub taken: Instant,
+    pub nodes: HashMap<Node, HashMap<u16, PortCounters>>,
+}
+
+/// Packets sent from one node to another over a single port
+#[derive(Debug, Clone, Copy)]
+pub struct Link {
+    pub port: u16,
+    pub from: Node,
+    pub to: Node,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkStats {
+    /// Packets per second sent over the link
+    pub rate: Option<f64>,
+    /// Fraction of the packets sent that were not received
+    pub loss: Option<f64>,
+    /// Packets that could not be sent or parsed since the nodes started
+    pub dropped: Option<f64>,
+}
+
+/// Reads the UDP packet counters of each port from the metrics of a node
+pub fn parse(metrics: &str) -> HashMap<u16, PortCounters> {
+    let mut ports: HashMap<u16, PortCounters> = HashMap::new();
+    for line in metrics.lines().filter(|line| !line.starts_with('#')) {
+        let Some((series, value)) = line.rsplit_once(' ') else {
+            continue
+        };
+        let (Some((name, labels)), Ok(value)) = (series.split_once('{'), value.parse::<f64>()) else {
+            continue
+        };
+        let Some(port) = label(labels, "port").and_then(|port| port.parse::<u16>().ok()) else {
+            continue
+        };
+        let counters = ports.entry(port).or_default();
+        match name {
+            "udp_packets_sent_total" => counters.sent += value,
+            "udp_packets_received_total" => counters.received += value,
+            "udp_packets_dropped_total" => counters.dropped += value,
+            _ => (),
+        }
+    }
+    ports
+}
+
+fn label<'a>(labels: &'a str, name: &str) -> Option<&'a str> {
+    labels.trim_end_matches('}').split(',').find_map(|label| {
+        let (key, value) = label.split_once('=')?;
+        (key == name).then(|| value.trim_matches('"'))
+    })
+}
+
+/// The links between the nodes that carry UDP broadcasts
+pub fn links(config: &DashboardConfig) -> Vec<Link> {
+    let mut links = Vec::new();
+    for (elevnum, &port) in config.network.update_ports.iter().enumerate() {
+        links.push(Link { port, from: Node::Slave(elevnum as u8), to: Node::Master });
+    }
+    for (elevnum, &port) in config.network.command_ports.iter().enumerate() {
+        links.push(Link { port, from: Node::Master, to: Node::Slave(elevnum as u8) });
+    }
+    links.push(Link { port: config.network.backup_update_port, from: Node::Master, to: Node::Backup });
+    links.push(Link { port: config.network.backup_ack_port, from: Node::Backup, to: Node::Master });
+    links
+}
+
+pub struct PacketStats {
+    scrapes: VecDeque<Scrape>,
+}
+
+impl PacketStats {
+    pub fn new() -> Self {
+        PacketStats { scrapes: VecDeque::new() }
+    }
+
+    pub fn update(&mut self, scrape: Scrape) {
+        if self.scrapes.len() == WINDOW {
+            self.scrapes.pop_front();
+        }
+        self.scrapes.push_back(scrape);
+    }
+
+    /// Whether the metrics of the node could be read the last time
+    pub fn reachable(&self, node: Node) -> bool {
+        self.scrapes.back().is_some_and(|scrape| scrape.nodes.contains_key(&node))
+    }
+
+    /// Rate and loss over the last scrapes in which both nodes could be reached,
+    /// unknown if either node restarted in between
+    pub fn link_stats(&self, link: &Link) -> LinkStats {
+        let counters = |scrape: &Scrape, node: Node| scrape.nodes.get(&node)
+            .map(|ports| ports.get(&link.port).copied().unwrap_or_default());
+        let both = |scrape: &&Scrape| counters(scrape, link.from).is_some() && counters(scrape, link.to).is_some();
+        let (Some(first), Some(last)) = (self.scrapes.iter().find(both), self.scrapes.iter().rev().find(both)) else {
+            return LinkStats::default()
+        };
+        let (first_from, first_to) = (counters(first, link.from).unwrap(), counters(first, link.to).unwrap());
+        let (last_from, last_to) = (counters(last, link.from).unwrap(), counters(last, link.to).unwrap());
+
+        let dropped = Some(last_from.dropped + last_to.dropped);
+        let sent = last_from.sent - first_from.sent;
+        let received = last_to.received - first_to.received;
+        let elapsed = last.taken.duration_since(first.taken).as_secs_f64();
+        if elapsed == 0.0 || sent < 0.0 || received < 0.0 {
+            return LinkStats { rate: None, loss: None, dropped }
+        }
+        LinkStats {
+            rate: Some(sent / elapsed),
+            loss: (sent > 0.0).then(|| (1.0 - received / sent).clamp(0.0, 1.0)),
+            dropped,
+        }
+    }
+}
+
+impl Default for PacketStats {
+    fn default() -> Self {
+        PacketStats::new()
+    }
+}
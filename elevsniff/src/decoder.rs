@@ -0,0 +1,171 @@
+//! ----- DECODER -----
+//! This module decodes the packets sent on each kind of port as the message
+//! type the nodes send there, and summarizes them on a single line.
+
+use std::collections::BTreeMap;
+
+use shared_resources::call::Call;
+use shared_resources::command_message::CommandMessage;
+use shared_resources::config::PortKind;
+use shared_resources::elevator_message::{ElevatorMessage, ServiceMode};
+use shared_resources::request::Request;
+use slave::utilities::elevator_status::ElevatorStatus;
+
+/// Short name of a kind of port, as printed and recorded
+pub fn kind_name(kind: PortKind) -> &'static str {
+    match kind {
+        PortKind::Update => "update",
+        PortKind::Command => "command",
+        PortKind::SlavePPUpdate => "slave-pp",
+        PortKind::BackupUpdate => "backup-update",
+        PortKind::BackupAck => "backup-ack",
+        PortKind::MasterPP => "master-pp",
+        PortKind::BackupPP => "backup-pp",
+    }
+}
+
+/// Summarizes a packet as the message type sent on its kind of port, or returns
+/// why the packet is not a valid message of that type.
+pub fn summarize(kind: PortKind, payload: &str, num_floors: u8) -> Result<String, serde_json::Error> {
+    let summary = match kind {
+        PortKind::Update => {
+            let message: ElevatorMessage = serde_json::from_str(payload)?;
+            let mut summary = format!(
+                "ElevatorMessage elevator {}{}, {} at floor {} going {}, load {:.0} kg{}, cab calls {}, new hall calls {}",
+                message.id,
+                if message.mode == ServiceMode::Normal { String::new() } else { format!(" ({})", message.mode.as_str()) },
+                message.behaviour,
+                message.floor,
+                message.direction,
+                message.load,
+                if message.full { " (full)" } else { "" },
+                floors(&message.cab_requests),
+                requests(&message.new_hall_orders),
+            );
+            if !message.cancelled_hall_orders.is_empty() {
+                summary.push_str(&format!(", cancelled hall calls {}", requests(&message.cancelled_hall_orders)));
+            }
+            summary
+        },
+        PortKind::Command => {
+            let message: CommandMessage = serde_json::from_str(payload)?;
+            let assigned: BTreeMap<&String, String> = message.assigned_hall_requests.iter()
+                .map(|(id, hall_requests)| (id, hall_calls(hall_requests)))
+                .collect();
+            let assigned: Vec<String> = assigned.into_iter()
+                .map(|(id, hall_calls)| format!("{}: {}", id, hall_calls))
+                .collect();
+            let parking: BTreeMap<&String, u8> = message.parking_floors.iter()
+                .map(|(id, &floor)| (id, floor))
+                .collect();
+            let mut summary = format!("CommandMessage {{{}}}", assigned.join(", "));
+            if message.hall_requests.iter().flatten().any(|&active| active) {
+                summary.push_str(&format!(", hall calls {}", hall_calls(&message.hall_requests)));
+            }
+            if !message.withdrawn_hall_requests.is_empty() {
+                summary.push_str(&format!(", withdrawn {}", requests(&message.withdrawn_hall_requests)));
+            }
+            if !parking.is_empty() {
+                let parking: Vec<String> = parking.into_iter()
+                    .map(|(id, floor)| format!("{}: floor {}", id, floor))
+                    .collect();
+                summary.push_str(&format!(", parking {{{}}}", parking.join(", ")));
+            }
+            let arrivals: Vec<String> = message.arrivals.iter().enumerate()
+                .flat_map(|(floor, calls)| calls.iter().enumerate().filter_map(move |(column, arrival)| {
+                    arrival.as_ref().map(|arrival| format!(
+                        "{}: {} in {:.0} s",
+                        call_name(&Request::from_column(floor as u8, column, None)), arrival.elevator, arrival.seconds
+                    ))
+                }))
+                .collect();
+            if !arrivals.is_empty() {
+                summary.push_str(&format!(", arrivals {{{}}}", arrivals.join(", ")));
+            }
+            if let Some(floor) = message.recall_floor {
+                summary.push_str(&format!(", recall to floor {}", floor));
+            }
+            if !message.independent.is_empty() {
+                let mut independent: Vec<&String> = message.independent.iter().collect();
+                independent.sort();
+                let independent: Vec<&str> = independent.into_iter().map(String::as_str).collect();
+                summary.push_str(&format!(", independent {{{}}}", independent.join(", ")));
+            }
+            summary
+        },
+        PortKind::SlavePPUpdate => {
+            let status: ElevatorStatus = serde_json::from_str(payload)?;
+            let lit: Vec<Vec<bool>> = (0..num_floors).map(|floor| status.requests.get_requests_at_floor(floor)).collect();
+            let cab_calls: Vec<bool> = lit.iter().map(|calls| calls[Call::Cab as usize]).collect();
+            format!(
+                "ElevatorStatus {} at floor {} going {}, hall calls {}, cab calls {}",
+                status.behaviour.as_string(),
+                status.floor,
+                status.direction.as_string().unwrap_or_else(|| String::from("nowhere")),
+                hall_calls(&lit),
+                floors(&cab_calls),
+            )
+        },
+        PortKind::BackupUpdate => {
+            let (new_requests, served_requests): (Vec<Request>, Vec<Request>) = serde_json::from_str(payload)?;
+            format!("changed requests, new {}, served {}", requests(&new_requests), requests(&served_requests))
+        },
+        PortKind::BackupAck | PortKind::BackupPP => {
+            let hall_requests: Vec<Vec<bool>> = serde_json::from_str(payload)?;
+            format!("stored hall calls {}", hall_calls(&hall_requests))
+        },
+        PortKind::MasterPP => {
+            let alive: bool = serde_json::from_str(payload)?;
+            format!("heartbeat {}", alive)
+        },
+    };
+    Ok(summary)
+}
+
+fn floors(active: &[bool]) -> String {
+    let floors: Vec<String> = active.iter().enumerate()
+        .filter(|(_, &active)| active)
+        .map(|(floor, _)| floor.to_string())
+        .collect();
+    format!("[{}]", floors.join(", "))
+}
+
+fn hall_calls(hall_requests: &[Vec<bool>]) -> String {
+    let mut calls = Vec::new();
+    for (floor, active) in hall_requests.iter().enumerate() {
+        for (column, &active) in active.iter().enumerate() {
+            if active {
+                calls.push(call_name(&Request::from_column(floor as u8, column, None)));
+            }
+        }
+    }
+    format!("[{}]", calls.join(", "))
+}
+
+fn requests(requests: &[Request]) -> String {
+    let requests: Vec<String> = requests.iter()
+        .map(|request| {
+            let mut name = call_name(request);
+            if request.priority > 0 {
+                name.push_str(&format!(" priority {}", request.priority));
+            }
+            match request.trace_id {
+                Some(trace_id) => format!("{} ({})", name, trace_id),
+                None => name,
+            }
+        })
+        .collect();
+    format!("[{}]", requests.join(", "))
+}
+
+fn call_name(request: &Request) -> String {
+    match request.call {
+        Call::HallUp => format!("up {}", request.floor),
+        Call::HallDown => format!("down {}", request.floor),
+        Call::Cab => format!("cab {}", request.floor),
+        Call::Destination => match request.destination {
+            Some(destination) => format!("{} to {}", request.floor, destination),
+            None => format!("destination {}", request.floor),
+        },
+    }
+}
@@ -0,0 +1,265 @@
+//! ----- ELEVSNIFF -----
+//! This tool listens to the ports of every node in the configuration, decodes
+//! each packet as the message type sent on its port and prints a timestamped
+//! stream of the decoded packets, optionally recording them to a file.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Instant;
+
+use chrono::{DateTime, Local};
+use clap::{Parser, ValueEnum};
+use crossbeam_channel::{unbounded, Receiver, Select};
+use network_rust::udpnet::{self, bcast::BcError, recording::RecordedPacket};
+
+use shared_resources::cli::ConfigArgs;
+use shared_resources::config::{PortKind, SnifferConfig};
+
+mod decoder;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Elevator messages from the slaves to the master
+    Update,
+    /// Assigned hall requests from the master to the slaves
+    Command,
+    /// Elevator status from each slave to its process pair, over localhost
+    SlavePp,
+    /// Changed hall requests from the master to the backup
+    BackupUpdate,
+    /// Stored hall requests from the backup to the master
+    BackupAck,
+    /// Heartbeats from the master to its process pair, over localhost
+    MasterPp,
+    /// Stored hall requests from the backup to its process pair, over localhost
+    BackupPp,
+}
+
+impl Kind {
+    fn port_kind(self) -> PortKind {
+        match self {
+            Kind::Update => PortKind::Update,
+            Kind::Command => PortKind::Command,
+            Kind::SlavePp => PortKind::SlavePPUpdate,
+            Kind::BackupUpdate => PortKind::BackupUpdate,
+            Kind::BackupAck => PortKind::BackupAck,
+            Kind::MasterPp => PortKind::MasterPP,
+            Kind::BackupPp => PortKind::BackupPP,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorChoice {
+    /// Color the output when printing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Parser)]
+#[command(name = "elevsniff", about = "Prints the packets sent between the nodes, decoded as their message types")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    /// Only listen to these ports
+    #[arg(long, value_name = "PORT", value_delimiter = ',')]
+    port: Vec<u16>,
+
+    /// Only listen to these kinds of ports
+    #[arg(long, value_enum, value_delimiter = ',')]
+    kind: Vec<Kind>,
+
+    /// Only listen to the ports of these elevators, leaving out the ports shared by all elevators
+    #[arg(long, value_name = "ID", value_delimiter = ',')]
+    elevator: Vec<u8>,
+
+    /// Only show packets whose summary or payload contains this text
+    #[arg(long, value_name = "TEXT")]
+    grep: Option<String>,
+
+    /// Only show packets that differ from the previous packet on the same port
+    #[arg(long)]
+    changes: bool,
+
+    /// Print the payload of each packet instead of a summary
+    #[arg(long)]
+    raw: bool,
+
+    /// Record every shown packet to this file, one JSON object per line. The recording can be
+    /// replayed to a node with its --replay argument
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Also listen to the process pair ports. Only one listener on a computer receives the packets sent
+    /// over localhost, so this may make a waiting process pair take over
+    #[arg(long)]
+    process_pairs: bool,
+
+    /// When to color the output
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorChoice,
+}
+
+/// A port listened to, and the elevator it belongs to if each elevator has its own port of its kind
+struct Listener {
+    port: u16,
+    kind: PortKind,
+    elevator: Option<u8>,
+    packet_rx: Receiver<(SocketAddr, Vec<u8>)>,
+}
+
+/// A recorded packet, in the format replayed by the nodes along with when and where it was sniffed
+#[derive(serde::Serialize)]
+struct SniffedPacket {
+    #[serde(flatten)]
+    packet: RecordedPacket,
+    timestamp: String,
+    kind: &'static str,
+}
+
+fn main() {
+    let args = Args::parse();
+    let config = SnifferConfig::get(&args.config);
+
+    let listeners = listen(&args, &config);
+    if listeners.is_empty() {
+        eprintln!("error: no ports left to listen to after filtering");
+        process::exit(1);
+    }
+
+    let started = Instant::now();
+    let mut record = args.record.as_ref().map(|path| {
+        File::create(path).unwrap_or_else(|e| {
+            eprintln!("error: could not open {}: {}", path.display(), e);
+            process::exit(1);
+        })
+    });
+    let color = match args.color {
+        ColorChoice::Auto => io::stdout().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    eprintln!(
+        "Listening to ports {}",
+        listeners.iter().map(|listener| listener.port.to_string()).collect::<Vec<String>>().join(", ")
+    );
+
+    let mut select = Select::new();
+    for listener in &listeners {
+        select.recv(&listener.packet_rx);
+    }
+    let mut previous_payloads: HashMap<u16, Vec<u8>> = HashMap::new();
+    let mut stdout = io::stdout().lock();
+    loop {
+        let operation = select.select();
+        let listener = &listeners[operation.index()];
+        let (from, payload) = operation.recv(&listener.packet_rx).unwrap();
+        let time = Local::now();
+
+        if args.changes && previous_payloads.get(&listener.port) == Some(&payload) {
+            continue;
+        }
+        previous_payloads.insert(listener.port, payload.clone());
+
+        let payload = String::from_utf8_lossy(&payload);
+        let summary = decoder::summarize(listener.kind, &payload, config.elevator.num_floors);
+        let text = match (&summary, args.raw) {
+            (Ok(_), true) => payload.to_string(),
+            (Ok(summary), false) => summary.clone(),
+            (Err(e), _) => format!("undecodable, {}: {}", e, payload),
+        };
+        if let Some(grep) = &args.grep {
+            if !text.contains(grep.as_str()) && !payload.contains(grep.as_str()) {
+                continue;
+            }
+        }
+
+        let line = format_line(time, listener, from, &text, summary.is_ok(), color);
+        if writeln!(stdout, "{}", line).is_err() {
+            return
+        }
+        if let Some(file) = &mut record {
+            write_record(file, started, time, listener, from, &payload);
+        }
+    }
+}
+
+/// Starts listening to the ports selected by the arguments
+fn listen(args: &Args, config: &SnifferConfig) -> Vec<Listener> {
+    let mut listeners = Vec::new();
+    for kind in PortKind::iter() {
+        let process_pair_port = matches!(kind, PortKind::SlavePPUpdate | PortKind::MasterPP | PortKind::BackupPP);
+        let selected = args.kind.is_empty() || args.kind.iter().any(|selected| selected.port_kind() == kind);
+        if !selected || (process_pair_port && !args.process_pairs) {
+            continue;
+        }
+        for (index, port) in config.network.ports(kind).into_iter().enumerate() {
+            let elevator = kind.per_elevator().then_some(index as u8);
+            if !args.port.is_empty() && !args.port.contains(&port) {
+                continue;
+            }
+            if !args.elevator.is_empty() && !elevator.is_some_and(|elevator| args.elevator.contains(&elevator)) {
+                continue;
+            }
+            let (packet_tx, packet_rx) = unbounded();
+            thread::Builder::new().name(format!("sniff_{}", port)).spawn(move || {
+                if let Err(BcError::IOError(e)) = udpnet::bcast::rx_raw(port, packet_tx) {
+                    eprintln!("error: could not listen on port {}: {}", port, e);
+                    process::exit(1);
+                }
+            }).unwrap();
+            listeners.push(Listener { port, kind, elevator, packet_rx });
+        }
+    }
+    if !args.process_pairs && args.kind.iter().any(|kind| matches!(kind, Kind::SlavePp | Kind::MasterPp | Kind::BackupPp)) {
+        eprintln!("warning: the process pair ports are only listened to with --process-pairs");
+    }
+    listeners
+}
+
+fn format_line(time: DateTime<Local>, listener: &Listener, from: SocketAddr, text: &str, decoded: bool, color: bool) -> String {
+    let kind = match listener.elevator {
+        Some(elevator) => format!("{} {}", decoder::kind_name(listener.kind), elevator),
+        None => decoder::kind_name(listener.kind).to_string(),
+    };
+    let time = time.format("%H:%M:%S%.3f");
+    if !color {
+        return format!("{} {:>5} {:<15} {:<21} {}", time, listener.port, kind, from, text)
+    }
+    let kind_color = match listener.kind {
+        PortKind::Update => "32",
+        PortKind::Command => "36",
+        PortKind::SlavePPUpdate => "34",
+        PortKind::BackupUpdate => "35",
+        PortKind::BackupAck => "33",
+        PortKind::MasterPP | PortKind::BackupPP => "90",
+    };
+    let text_color = if decoded { "0" } else { "31" };
+    format!(
+        "\x1b[90m{}\x1b[0m {:>5} \x1b[{}m{:<15}\x1b[0m \x1b[90m{:<21}\x1b[0m \x1b[{}m{}\x1b[0m",
+        time, listener.port, kind_color, kind, from, text_color, text
+    )
+}
+
+fn write_record(file: &mut File, started: Instant, time: DateTime<Local>, listener: &Listener, from: SocketAddr, payload: &str) {
+    let record = SniffedPacket {
+        packet: RecordedPacket {
+            time: started.elapsed().as_secs_f64(),
+            port: listener.port,
+            from,
+            payload: payload.to_string(),
+        },
+        timestamp: time.to_rfc3339(),
+        kind: decoder::kind_name(listener.kind),
+    };
+    if let Err(e) = writeln!(file, "{}", serde_json::to_string(&record).unwrap()) {
+        eprintln!("warning: could not record packet: {}", e);
+    }
+}
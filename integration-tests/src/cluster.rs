@@ -0,0 +1,330 @@
+//! ----- CLUSTER -----
+//! This module starts a backup, a master and slaves, each with its process
+//! pair, in a temporary directory with a configuration of its own. Every
+//! cluster gets its own ports, so the tests can run in parallel. All processes
+//! of the cluster are killed when it is dropped, and the logs are kept if the
+//! test failed.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::elevator_server::ElevatorServer;
+use crate::link::Link;
+use crate::wait_until;
+
+pub const NUM_FLOORS: u8 = 4;
+/// Time the cluster gets to start before the test fails
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(20);
+const FIRST_PORT: u16 = 20000;
+const PORTS_PER_CLUSTER: u16 = 40;
+const CLUSTERS_PER_PROCESS: u16 = 5;
+/// Bearer token the operator commands of every cluster are sent with
+const OPERATOR_TOKEN: &str = "integration-tests";
+
+static NEXT_CLUSTER: AtomicU16 = AtomicU16::new(0);
+static BUILD: Once = Once::new();
+
+/// The nodes the tests can kill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Node {
+    Master,
+    Backup,
+    Slave(u8),
+}
+
+struct Process {
+    node: Node,
+    child: Child,
+}
+
+pub struct Cluster {
+    directory: PathBuf,
+    api_port: u16,
+    pub elevators: Vec<ElevatorServer>,
+    links: Vec<Link>,
+    processes: Vec<Process>,
+}
+
+impl Cluster {
+    /// Starts a cluster with one elevator per entry in `floors`, with the car
+    /// of each elevator standing at the given floor, and waits until the
+    /// master has heard from every slave. Parking is disabled, so the
+    /// elevators stay where they are until they get requests, and the traffic
+    /// mode stays inter-floor, so every hall request is assigned the same way.
+    /// The cars are reported empty unless the test selects a load sensor.
+    pub fn start(floors: &[u8]) -> Self {
+        Cluster::start_with(floors, json!({}))
+    }
+
+    /// Like `start`, but with the sections of `overrides` replacing the
+    /// settings of the configuration, such as `{"parking": {"enabled": true}}`,
+    /// or added to it if the configuration has no such section
+    pub fn start_with(floors: &[u8], overrides: Value) -> Self {
+        build_binaries();
+
+        let number = NEXT_CLUSTER.fetch_add(1, Ordering::Relaxed);
+        assert!(number < CLUSTERS_PER_PROCESS, "Too many clusters in one test binary");
+        let process_slot = (std::process::id() % 200) as u16;
+        let mut next_port = FIRST_PORT + (process_slot * CLUSTERS_PER_PROCESS + number) * PORTS_PER_CLUSTER;
+        let mut ports = |count: usize| -> Vec<u16> {
+            let allocated = (next_port..next_port + count as u16).collect();
+            next_port += count as u16;
+            allocated
+        };
+
+        let num_elevators = floors.len();
+        let update_ports = ports(num_elevators);
+        let command_ports = ports(num_elevators);
+        let slave_update_ports = ports(num_elevators);
+        let slave_command_ports = ports(num_elevators);
+        let slave_pp_update_ports = ports(num_elevators);
+        let server_ports = ports(num_elevators);
+        let api_port = ports(1)[0];
+        let node_ports = ports(4);
+
+        let directory = std::env::temp_dir().join(format!("elevator-cluster-{}-{}", std::process::id(), number));
+        fs::remove_dir_all(&directory).ok();
+        fs::create_dir_all(&directory).unwrap();
+
+        let mut config = json!({
+            "node": { "headless": true, "log_level": "debug" },
+            "network": {
+                "update_ports": update_ports,
+                "command_ports": command_ports,
+                "slave_pp_update_ports": slave_pp_update_ports,
+                "backup_update_ports": [node_ports[0]],
+                "backup_ack_ports": [node_ports[1]],
+                "master_pp_ports": [node_ports[2]],
+                "backup_pp_ports": [node_ports[3]],
+            },
+            "elevator": { "num_floors": NUM_FLOORS, "num_elevators": num_elevators },
+            "timing": {
+                "door_open_duration": 1.0,
+                "slave_timeout": 2.0,
+                "master_timeout": 2.0,
+                "backup_timeout": 2.0,
+                "master_request_buffer_timeout": 1.0,
+                "slave_request_buffer_timeout": 3.0,
+            },
+            "hall_request_assigner": { "strategy": "nearest" },
+            "logging": { "directory": directory.join("logs") },
+            "metrics": { "enabled": false },
+            "api": { "enabled": true, "address": "127.0.0.1", "port": api_port, "token": OPERATOR_TOKEN },
+            "journal": { "directory": directory.join("journal") },
+            "parking": { "enabled": false },
+            "traffic": { "detection": false, "schedule": [] },
+            "load": { "sensor": "none", "directory": directory.join("load") },
+        });
+        if let Value::Object(overrides) = overrides {
+            for (section, settings) in overrides {
+                let Value::Object(settings) = settings else { continue };
+                match config.get_mut(&section).and_then(Value::as_object_mut) {
+                    Some(existing) => existing.extend(settings),
+                    None => config[section] = Value::Object(settings),
+                }
+            }
+        }
+        let config_path = directory.join("config.json");
+        write_json(&config_path, &config);
+
+        let mut cluster = Cluster {
+            directory,
+            api_port,
+            elevators: Vec::new(),
+            links: Vec::new(),
+            processes: Vec::new(),
+        };
+
+        cluster.spawn(Node::Backup, "backup", &["--config".as_ref(), config_path.as_os_str()]);
+        cluster.spawn(Node::Master, "master", &["--config".as_ref(), config_path.as_os_str()]);
+
+        for (id, &floor) in floors.iter().enumerate() {
+            cluster.elevators.push(ElevatorServer::start(server_ports[id], NUM_FLOORS, floor));
+            cluster.links.push(Link::start(&[
+                (slave_update_ports[id], update_ports[id]),
+                (command_ports[id], slave_command_ports[id]),
+            ]));
+
+            // The slave talks to the master through its link
+            let mut slave_update = update_ports.clone();
+            slave_update[id] = slave_update_ports[id];
+            let mut slave_command = command_ports.clone();
+            slave_command[id] = slave_command_ports[id];
+            let node_config_path = cluster.directory.join(format!("slave-{}.json", id));
+            write_json(&node_config_path, &json!({
+                "network": { "update_ports": slave_update, "command_ports": slave_command },
+            }));
+
+            let id_arg = id.to_string();
+            let server_port_arg = server_ports[id].to_string();
+            cluster.spawn(Node::Slave(id as u8), "slave", &[
+                "--config".as_ref(), config_path.as_os_str(),
+                "--node-config".as_ref(), node_config_path.as_os_str(),
+                "--id".as_ref(), id_arg.as_ref(),
+                "--server-port".as_ref(), server_port_arg.as_ref(),
+            ]);
+        }
+
+        let ready = wait_until(STARTUP_TIMEOUT, || cluster.connected_elevators().len() == num_elevators);
+        assert!(ready, "The master did not hear from every slave, see {}", cluster.directory.display());
+        cluster
+    }
+
+    /// Kills the process started for `node` without warning, leaving its
+    /// process pair to take over
+    pub fn kill(&mut self, node: Node) {
+        let process = self.processes.iter_mut()
+            .find(|process| process.node == node)
+            .unwrap_or_else(|| panic!("No process was started for {:?}", node));
+        process.child.kill().unwrap();
+        process.child.wait().unwrap();
+    }
+
+    /// Cuts the network between the slave of `elevator` and the master
+    pub fn cut(&self, elevator: u8) {
+        self.links[elevator as usize].cut();
+    }
+
+    pub fn restore(&self, elevator: u8) {
+        self.links[elevator as usize].restore();
+    }
+
+    /// Writes the load of the car of `elevator` in kilograms for the `file`
+    /// load sensor, replacing the file at once so it is never read half written
+    pub fn set_load(&self, elevator: u8, load: u16) {
+        let directory = self.directory.join("load");
+        fs::create_dir_all(&directory).unwrap();
+        let written = directory.join(format!("elevator-{}.tmp", elevator));
+        fs::write(&written, load.to_string()).unwrap();
+        fs::rename(&written, directory.join(format!("elevator-{}", elevator))).unwrap();
+    }
+
+    /// Installs a stand-in for the hall request assigner executable of the
+    /// `executable` strategy, which gives every hall request to `elevator`
+    /// whatever the state of the elevators
+    pub fn install_assigner(&self, elevator: u8) {
+        let assignments: serde_json::Map<String, Value> = (0..self.elevators.len())
+            .map(|id| (id.to_string(), json!(vec![[id == elevator as usize; 2]; NUM_FLOORS as usize])))
+            .collect();
+        let directory = self.directory.join("hra_execs");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("hall_request_assigner");
+        fs::write(&path, format!("#!/bin/sh\necho '{}'\n", Value::Object(assignments))).unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+    }
+
+    /// Reads `path` from the status API of the master, if it answers
+    pub fn api(&self, path: &str) -> Option<Value> {
+        self.http(&format!("GET {} HTTP/1.0\r\nHost: 127.0.0.1\r\n\r\n", path), "200")
+    }
+
+    /// Sends an operator command to `path` of the API of the master, returning
+    /// the accepted command if it answers
+    pub fn command(&self, path: &str, body: Value) -> Option<Value> {
+        let body = body.to_string();
+        self.http(&format!(
+            "POST {} HTTP/1.0\r\nHost: 127.0.0.1\r\nAuthorization: Bearer {}\r\nContent-Length: {}\r\n\r\n{}",
+            path, OPERATOR_TOKEN, body.len(), body,
+        ), "202")
+    }
+
+    fn http(&self, request: &str, status: &str) -> Option<Value> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.api_port)).ok()?;
+        stream.set_read_timeout(Some(Duration::from_secs(2))).ok()?;
+        stream.write_all(request.as_bytes()).ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let (head, body) = response.split_once("\r\n\r\n")?;
+        let status_code = head.split(' ').nth(1)?;
+        if status_code != status {
+            return None
+        }
+        serde_json::from_str(body).ok()
+    }
+
+    /// The elevators the master has heard from
+    pub fn connected_elevators(&self) -> Vec<String> {
+        match self.api("/api/elevators") {
+            Some(Value::Object(elevators)) => elevators.keys().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn spawn(&mut self, node: Node, binary: &str, args: &[&std::ffi::OsStr]) {
+        let name = match node {
+            Node::Slave(id) => format!("slave-{}", id),
+            _ => binary.to_string(),
+        };
+        let output = File::create(self.directory.join(format!("{}.out", name))).unwrap();
+        let mut command = Command::new(binary_path(binary));
+        command.args(args)
+            .args(["--role", "primary", "--headless"])
+            .current_dir(&self.directory)
+            .stdin(Stdio::null())
+            .stdout(output.try_clone().unwrap())
+            .stderr(output);
+        // The process pairs are spawned into the same process group, so the
+        // whole group can be killed when the cluster is dropped
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let child = command.spawn()
+            .unwrap_or_else(|e| panic!("Could not start {}: {}", binary_path(binary).display(), e));
+        self.processes.push(Process { node, child });
+    }
+}
+
+impl Drop for Cluster {
+    fn drop(&mut self) {
+        for process in &mut self.processes {
+            #[cfg(unix)]
+            unsafe {
+                libc::kill(-(process.child.id() as i32), libc::SIGKILL);
+            }
+            process.child.kill().ok();
+            process.child.wait().ok();
+        }
+        if thread::panicking() {
+            eprintln!("The logs of the cluster are kept in {}", self.directory.display());
+        } else {
+            fs::remove_dir_all(&self.directory).ok();
+        }
+    }
+}
+
+fn write_json(path: &Path, value: &Value) {
+    fs::write(path, serde_json::to_string_pretty(value).unwrap()).unwrap();
+}
+
+/// The binaries are built next to the test binaries, which are in the `deps`
+/// folder of the profile
+fn binary_path(name: &str) -> PathBuf {
+    let test_binary = std::env::current_exe().unwrap();
+    let profile_directory = test_binary.parent().unwrap().parent().unwrap();
+    profile_directory.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+}
+
+/// `cargo test` only builds the binaries of the crate being tested, so the
+/// nodes are built before the first cluster starts
+fn build_binaries() {
+    BUILD.call_once(|| {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let mut command = Command::new(cargo);
+        command.args(["build", "--quiet", "-p", "master", "-p", "slave", "-p", "backup"])
+            .current_dir(env!("CARGO_MANIFEST_DIR"));
+        if !cfg!(debug_assertions) {
+            command.arg("--release");
+        }
+        let status = command.status().expect("Could not run cargo to build the nodes");
+        assert!(status.success(), "Could not build the nodes");
+    });
+}
@@ -0,0 +1,197 @@
+//! ----- ELEVATOR SERVER -----
+//! This module simulates the elevator server the slave connects to, speaking
+//! the same TCP protocol as the elevator hardware server and the simulator.
+//! The car moves between the floors while the motor runs, and the tests press
+//! the buttons and check the lamps and the doors. The state of the elevator is
+//! kept when the slave reconnects, as when its process pair takes over.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use shared_resources::call::Call;
+
+/// Time the car takes to travel from one floor to the next
+const FLOOR_TRAVEL_TIME: Duration = Duration::from_millis(800);
+/// Distance from a floor, in floors, within which the floor sensor detects the car
+const SENSOR_RANGE: f64 = 0.1;
+/// Time a pressed button is held down
+const PRESS_DURATION: Duration = Duration::from_millis(200);
+const SIMULATION_PERIOD: Duration = Duration::from_millis(10);
+
+struct Car {
+    num_floors: u8,
+    /// Position of the car in floors above the bottom floor
+    position: f64,
+    motor: f64,
+    /// When each pressed button is released
+    buttons: Vec<[Option<Instant>; 3]>,
+    lamps: Vec<[bool; 3]>,
+    door_open: bool,
+    /// Floors the door opened at, in order
+    door_openings: Vec<u8>,
+    connections: usize,
+}
+
+#[derive(Clone)]
+pub struct ElevatorServer {
+    port: u16,
+    car: Arc<Mutex<Car>>,
+}
+
+impl ElevatorServer {
+    /// Starts a server on `port` with the car standing at `floor`. The server
+    /// stops simulating when the last handle to it is dropped.
+    pub fn start(port: u16, num_floors: u8, floor: u8) -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .unwrap_or_else(|e| panic!("Could not bind the elevator server to port {}: {}", port, e));
+        let car = Arc::new(Mutex::new(Car {
+            num_floors,
+            position: floor as f64,
+            motor: 0.0,
+            buttons: vec![[None; 3]; num_floors as usize],
+            lamps: vec![[false; 3]; num_floors as usize],
+            door_open: false,
+            door_openings: Vec::new(),
+            connections: 0,
+        }));
+
+        let simulated_car = Arc::downgrade(&car);
+        thread::spawn(move || simulate(simulated_car));
+
+        let accepting_car = Arc::downgrade(&car);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Some(car) = accepting_car.upgrade() else { return };
+                if let Ok(stream) = stream {
+                    car.lock().unwrap().connections += 1;
+                    thread::spawn(move || serve(stream, car));
+                }
+            }
+        });
+
+        ElevatorServer { port, car }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Holds the button down long enough for the slave to notice
+    pub fn press(&self, floor: u8, call: Call) {
+        self.car.lock().unwrap().buttons[floor as usize][call as usize] = Some(Instant::now() + PRESS_DURATION);
+    }
+
+    /// Holds the button down for `duration`, as a long press
+    pub fn hold(&self, floor: u8, call: Call, duration: Duration) {
+        self.car.lock().unwrap().buttons[floor as usize][call as usize] = Some(Instant::now() + duration);
+    }
+
+    /// The floor the floor sensor detects the car at, if any
+    pub fn floor(&self) -> Option<u8> {
+        self.car.lock().unwrap().sensor()
+    }
+
+    pub fn lamp(&self, floor: u8, call: Call) -> bool {
+        self.car.lock().unwrap().lamps[floor as usize][call as usize]
+    }
+
+    pub fn door_open(&self) -> bool {
+        self.car.lock().unwrap().door_open
+    }
+
+    /// Whether the door has opened at `floor`
+    pub fn served(&self, floor: u8) -> bool {
+        self.car.lock().unwrap().door_openings.contains(&floor)
+    }
+
+    /// Floors the door opened at, in order
+    pub fn door_openings(&self) -> Vec<u8> {
+        self.car.lock().unwrap().door_openings.clone()
+    }
+
+    /// Number of times a slave has connected to the server
+    pub fn connections(&self) -> usize {
+        self.car.lock().unwrap().connections
+    }
+}
+
+impl Car {
+    fn sensor(&self) -> Option<u8> {
+        let nearest = self.position.round();
+        ((self.position - nearest).abs() < SENSOR_RANGE).then_some(nearest as u8)
+    }
+
+    /// Carries out a command from the slave, returning the reply if it is a query
+    fn handle(&mut self, command: [u8; 4]) -> Option<[u8; 4]> {
+        let floor = command[2] as usize;
+        let call = command[1] as usize;
+        match command[0] {
+            1 => {
+                self.motor = match command[1] {
+                    0 => 0.0,
+                    1 => 1.0,
+                    _ => -1.0,
+                };
+                None
+            },
+            2 => {
+                if let Some(lamp) = self.lamps.get_mut(floor).and_then(|lamps| lamps.get_mut(call)) {
+                    *lamp = command[3] != 0;
+                }
+                None
+            },
+            4 => {
+                let open = command[1] != 0;
+                if open && !self.door_open {
+                    if let Some(floor) = self.sensor() {
+                        self.door_openings.push(floor);
+                    }
+                }
+                self.door_open = open;
+                None
+            },
+            6 => {
+                let pressed = self.buttons.get(floor)
+                    .and_then(|buttons| buttons.get(call))
+                    .is_some_and(|release| release.is_some_and(|release| Instant::now() < release));
+                Some([6, pressed as u8, 0, 0])
+            },
+            7 => Some(match self.sensor() {
+                Some(floor) => [7, 1, floor, 0],
+                None => [7, 0, 0, 0],
+            }),
+            8 => Some([8, 0, 0, 0]),
+            9 => Some([9, 0, 0, 0]),
+            // The floor indicator and the stop lamp are not simulated
+            _ => None,
+        }
+    }
+}
+
+fn simulate(car: Weak<Mutex<Car>>) {
+    let floors_per_step = SIMULATION_PERIOD.as_secs_f64() / FLOOR_TRAVEL_TIME.as_secs_f64();
+    while let Some(car) = car.upgrade() {
+        {
+            let mut car = car.lock().unwrap();
+            let top = (car.num_floors - 1) as f64;
+            car.position = (car.position + car.motor * floors_per_step).clamp(0.0, top);
+        }
+        drop(car);
+        thread::sleep(SIMULATION_PERIOD);
+    }
+}
+
+fn serve(mut stream: TcpStream, car: Arc<Mutex<Car>>) {
+    let mut command = [0; 4];
+    while stream.read_exact(&mut command).is_ok() {
+        let reply = car.lock().unwrap().handle(command);
+        if let Some(reply) = reply {
+            if stream.write_all(&reply).is_err() {
+                return
+            }
+        }
+    }
+}
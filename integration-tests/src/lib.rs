@@ -0,0 +1,70 @@
+//! ----- INTEGRATION TESTS -----
+//! This crate runs the master, backup and slave binaries against simulated
+//! elevator servers on loopback ports. The tests are found in the `tests`
+//! folder, while this library holds what they share.
+
+pub mod cluster;
+pub mod elevator_server;
+pub mod link;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use cluster::Cluster;
+
+/// Time a test waits for a request to be served or a state to be reached
+pub const SERVICE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Checks `condition` every 50 ms until it holds, returning false if it did
+/// not hold within `timeout`
+pub fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    loop {
+        if condition() {
+            return true
+        }
+        if start.elapsed() > timeout {
+            return false
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Waits until every elevator of `cluster` has found its floor and is idle
+pub fn settle(cluster: &Cluster) {
+    // Under load a car may drift off its floor before its slave stops it at startup
+    let settled = wait_until(SERVICE_TIMEOUT, || {
+        cluster.api("/api/elevators").is_some_and(|elevators| {
+            (0..cluster.elevators.len()).all(|id| elevators[id.to_string()]["state"]["behaviour"] == json!("idle"))
+        })
+    });
+    assert!(settled, "The elevators did not finish starting up");
+}
+
+/// Traffic mode the master reports in its status
+pub fn traffic_mode(cluster: &Cluster) -> Option<String> {
+    cluster.api("/api/status")
+        .and_then(|status| status["traffic_mode"].as_str().map(String::from))
+}
+
+/// Whether the master reports elevator `id` as full
+pub fn reported_full(cluster: &Cluster, id: &str) -> bool {
+    cluster.api("/api/elevators").is_some_and(|elevators| elevators[id]["full"] == json!(true))
+}
+
+/// Service mode the master reports for elevator `id`
+pub fn mode(cluster: &Cluster, id: &str) -> Option<String> {
+    let elevators = cluster.api("/api/elevators")?;
+    elevators[id]["mode"].as_str().map(String::from)
+}
+
+/// Whether the master lists a hall request for `call` at `floor`
+pub fn listed(cluster: &Cluster, floor: u8, call: &str) -> bool {
+    cluster.api("/api/hall_requests").is_some_and(|hall_requests| {
+        hall_requests.as_array().is_some_and(|hall_requests| {
+            hall_requests.iter().any(|request| request["floor"] == json!(floor) && request["call"] == json!(call))
+        })
+    })
+}
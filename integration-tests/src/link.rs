@@ -0,0 +1,52 @@
+//! ----- LINK -----
+//! This module relays the packets between a slave and the master, so that the
+//! tests can cut the network of a single slave without touching the others.
+//! The slave is configured with its own update and command ports, and the link
+//! passes the packets on between these and the ports of the master.
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam_channel as cbc;
+use network_rust::udpnet;
+
+pub struct Link {
+    connected: Arc<AtomicBool>,
+}
+
+impl Link {
+    /// Starts relaying the packets broadcast on the first port of each pair to
+    /// the second port on localhost
+    pub fn start(routes: &[(u16, u16)]) -> Self {
+        let connected = Arc::new(AtomicBool::new(true));
+        for &(from, to) in routes {
+            let (packet_tx, packet_rx) = cbc::unbounded::<(std::net::SocketAddr, Vec<u8>)>();
+            thread::spawn(move || {
+                if udpnet::bcast::rx_raw(from, packet_tx).is_err() {
+                    panic!("Could not receive on port {}", from);
+                }
+            });
+            let connected = connected.clone();
+            thread::spawn(move || {
+                let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+                for (_, payload) in packet_rx {
+                    if connected.load(Ordering::Relaxed) {
+                        socket.send_to(&payload, ("127.0.0.1", to)).ok();
+                    }
+                }
+            });
+        }
+        Link { connected }
+    }
+
+    /// Drops every packet until the link is restored
+    pub fn cut(&self) {
+        self.connected.store(false, Ordering::Relaxed);
+    }
+
+    pub fn restore(&self) {
+        self.connected.store(true, Ordering::Relaxed);
+    }
+}
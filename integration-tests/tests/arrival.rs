@@ -0,0 +1,69 @@
+//! The master predicts when the assigned elevator arrives at each hall request,
+//! counting the floors it travels and the stops it makes on the way
+
+use serde_json::{json, Value};
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+/// Time the simulated cars take from one floor to the next
+const FLOOR_TRAVEL_DURATION: f64 = 0.8;
+/// Door open duration of the test cluster
+const DOOR_OPEN_DURATION: f64 = 1.0;
+
+fn arrival(cluster: &Cluster, floor: u8, call: &str) -> Option<Value> {
+    let hall_requests = cluster.api("/api/hall_requests")?;
+    hall_requests.as_array()?.iter()
+        .find(|request| request["floor"] == json!(floor) && request["call"] == json!(call))
+        .map(|request| request["arrival"].clone())
+        .filter(|arrival| !arrival.is_null())
+}
+
+fn first_arrival(cluster: &Cluster, floor: u8, call: &str) -> Value {
+    let mut found = None;
+    wait_until(SERVICE_TIMEOUT, || {
+        found = arrival(cluster, floor, call);
+        found.is_some()
+    });
+    found.expect("The hall request was given no arrival")
+}
+
+#[test]
+fn arrival_is_the_travel_time_of_the_nearest_elevator() {
+    let cluster = Cluster::start_with(&[0, 3], json!({
+        "timing": { "floor_travel_duration": FLOOR_TRAVEL_DURATION },
+    }));
+    settle(&cluster);
+
+    cluster.elevators[0].press(2, Call::HallDown);
+
+    let arrival = first_arrival(&cluster, 2, "HallDown");
+    assert_eq!(arrival["elevator"], json!("1"), "The arrival is not that of the nearest elevator");
+    let seconds = arrival["seconds"].as_f64().unwrap();
+    assert!(seconds <= FLOOR_TRAVEL_DURATION + 1e-9, "The elevator one floor away is predicted to arrive in {} s", seconds);
+}
+
+#[test]
+fn arrival_counts_the_stops_on_the_way() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "timing": { "floor_travel_duration": FLOOR_TRAVEL_DURATION },
+    }));
+    settle(&cluster);
+    let elevator = &cluster.elevators[0];
+
+    elevator.press(3, Call::Cab);
+    let moving = wait_until(SERVICE_TIMEOUT, || {
+        cluster.api("/api/elevators").is_some_and(|elevators| elevators["0"]["state"]["behaviour"] == json!("moving"))
+    });
+    assert!(moving, "The elevator did not leave for its cab request");
+    elevator.press(0, Call::HallUp);
+
+    // The car finishes its trip up and stops there before it comes back down
+    let arrival = first_arrival(&cluster, 0, "HallUp");
+    let seconds = arrival["seconds"].as_f64().unwrap();
+    let direct_return = 3.0 * FLOOR_TRAVEL_DURATION + DOOR_OPEN_DURATION;
+    assert!(seconds > direct_return, "The elevator is predicted to arrive in {} s, ignoring its stop on the way", seconds);
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(0) && elevator.door_openings().ends_with(&[3, 0]));
+    assert!(served, "The elevator did not serve the hall request after its cab request");
+}
@@ -0,0 +1,87 @@
+//! Hall calls are cancelled by holding their hall button or by an operator,
+//! and expire when they have waited for longer than the maximum age
+
+use std::time::Duration;
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{listed, settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+#[test]
+fn long_press_cancels_the_hall_call() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "cancel": { "long_press": 0.3 },
+    }));
+    let elevator = &cluster.elevators[0];
+    settle(&cluster);
+
+    // The press places the hall call, and releasing the button after a long press cancels it
+    // before the car can reach the floor
+    elevator.hold(3, Call::HallDown, Duration::from_millis(600));
+    let lit = wait_until(SERVICE_TIMEOUT, || elevator.lamp(3, Call::HallDown));
+    assert!(lit, "The hall call was not placed");
+
+    let cancelled = wait_until(SERVICE_TIMEOUT, || !elevator.lamp(3, Call::HallDown) && !listed(&cluster, 3, "HallDown"));
+    assert!(cancelled, "The hall call was not cancelled by the long press");
+    std::thread::sleep(Duration::from_secs(3));
+    assert!(!elevator.served(3), "The elevator served the cancelled hall call, it opened its doors at {:?}", elevator.door_openings());
+    assert!(!elevator.lamp(3, Call::HallDown), "The cancelled hall call was placed again");
+}
+
+#[test]
+fn waiting_hall_call_is_cancelled_while_the_car_serves_a_priority_call() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "cancel": { "long_press": 0.3 },
+        "priority": { "floors": { "3": 1 } },
+    }));
+    settle(&cluster);
+    let elevator = &cluster.elevators[0];
+
+    // The only car is busy with the priority call, so the cancelled hall call is not assigned to any elevator
+    elevator.press(3, Call::HallDown);
+    elevator.hold(1, Call::HallUp, Duration::from_millis(600));
+
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(3));
+    assert!(served, "The elevator did not serve the priority call");
+    std::thread::sleep(Duration::from_secs(3));
+    assert!(!listed(&cluster, 1, "HallUp"), "The cancelled hall call is still stored");
+    assert!(!elevator.served(1), "The elevator served the cancelled hall call, it opened its doors at {:?}", elevator.door_openings());
+}
+
+#[test]
+fn operators_only_cancel_active_hall_calls() {
+    let cluster = Cluster::start(&[0]);
+    // Out of service the elevator leaves the hall call waiting
+    let accepted = cluster.command("/api/elevators/0/out_of_service", json!({}));
+    assert!(accepted.is_some(), "The elevator was not taken out of service");
+
+    let cancelled = cluster.command("/api/hall_requests/cancel", json!({ "floor": 2, "call": "HallUp" }));
+    assert!(cancelled.is_none(), "The cancellation of a hall call that is not active was accepted");
+
+    cluster.command("/api/hall_requests", json!({ "floor": 2, "call": "HallUp" }));
+    let stored = wait_until(SERVICE_TIMEOUT, || listed(&cluster, 2, "HallUp"));
+    assert!(stored, "The hall call was not stored");
+    let cancelled = cluster.command("/api/hall_requests/cancel", json!({ "floor": 2, "call": "HallUp" }));
+    assert!(cancelled.is_some(), "The cancellation of an active hall call was not accepted");
+    let cleared = wait_until(SERVICE_TIMEOUT, || !listed(&cluster, 2, "HallUp"));
+    assert!(cleared, "The hall call was not cancelled");
+}
+
+#[test]
+fn hall_calls_expire_after_the_maximum_age() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "cancel": { "max_age": 2.0 },
+    }));
+    // Out of service the elevator leaves the hall call waiting
+    let accepted = cluster.command("/api/elevators/0/out_of_service", json!({}));
+    assert!(accepted.is_some(), "The elevator was not taken out of service");
+
+    cluster.elevators[0].press(2, Call::HallUp);
+    let stored = wait_until(SERVICE_TIMEOUT, || listed(&cluster, 2, "HallUp"));
+    assert!(stored, "The hall call was not stored");
+
+    let expired = wait_until(SERVICE_TIMEOUT, || !listed(&cluster, 2, "HallUp"));
+    assert!(expired, "The hall call did not expire");
+}
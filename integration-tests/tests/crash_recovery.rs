@@ -0,0 +1,67 @@
+//! Requests are served when a node crashes and its process pair takes over
+
+use std::thread;
+use std::time::Duration;
+
+use integration_tests::cluster::{Cluster, Node};
+use integration_tests::wait_until;
+use shared_resources::call::Call;
+
+/// Long enough for a process pair to take over and serve the request
+const RECOVERY_TIMEOUT: Duration = Duration::from_secs(20);
+/// Time the process pairs get to start listening to their primaries
+const PROCESS_PAIR_STARTUP: Duration = Duration::from_secs(1);
+
+#[test]
+fn master_process_pair_takes_over() {
+    let mut cluster = Cluster::start(&[0, 0]);
+    thread::sleep(PROCESS_PAIR_STARTUP);
+    cluster.kill(Node::Master);
+
+    // Without a master the request is never confirmed, so serving it takes a new master
+    cluster.elevators[0].press(3, Call::HallDown);
+    let served = wait_until(RECOVERY_TIMEOUT, || cluster.elevators.iter().any(|elevator| elevator.served(3)));
+    assert!(served, "The hall request placed after the master crashed was not served");
+}
+
+#[test]
+fn confirmed_hall_request_survives_a_master_crash() {
+    let mut cluster = Cluster::start(&[0, 0]);
+    thread::sleep(PROCESS_PAIR_STARTUP);
+    cluster.elevators[0].press(3, Call::HallDown);
+    let confirmed = wait_until(RECOVERY_TIMEOUT, || cluster.elevators[0].lamp(3, Call::HallDown));
+    assert!(confirmed, "The hall request was not confirmed");
+    cluster.kill(Node::Master);
+
+    let served = wait_until(RECOVERY_TIMEOUT, || cluster.elevators.iter().any(|elevator| elevator.served(3)));
+    assert!(served, "The confirmed hall request was lost when the master crashed");
+}
+
+#[test]
+fn backup_process_pair_takes_over() {
+    let mut cluster = Cluster::start(&[0, 0]);
+    thread::sleep(PROCESS_PAIR_STARTUP);
+    cluster.kill(Node::Backup);
+
+    // The master only confirms requests stored by the backup
+    cluster.elevators[1].press(2, Call::HallUp);
+    let served = wait_until(RECOVERY_TIMEOUT, || cluster.elevators.iter().any(|elevator| elevator.served(2)));
+    assert!(served, "The hall request placed after the backup crashed was not served");
+}
+
+#[test]
+fn slave_process_pair_restores_cab_requests() {
+    let mut cluster = Cluster::start(&[0]);
+    thread::sleep(PROCESS_PAIR_STARTUP);
+    cluster.elevators[0].press(3, Call::Cab);
+    let accepted = wait_until(RECOVERY_TIMEOUT, || cluster.elevators[0].lamp(3, Call::Cab));
+    assert!(accepted, "The cab request was not accepted");
+    // Gives the slave time to pass the cab request on to its process pair
+    thread::sleep(Duration::from_millis(500));
+    cluster.kill(Node::Slave(0));
+
+    let reconnected = wait_until(RECOVERY_TIMEOUT, || cluster.elevators[0].connections() > 1);
+    assert!(reconnected, "The process pair of the slave did not connect to the elevator server");
+    let served = wait_until(RECOVERY_TIMEOUT, || cluster.elevators[0].served(3));
+    assert!(served, "The cab request was lost when the slave crashed");
+}
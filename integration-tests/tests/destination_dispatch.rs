@@ -0,0 +1,51 @@
+//! Destination calls are picked up at their floor and taken to their destination,
+//! alongside the up and down buttons. The simulated elevators have no destination
+//! panels, so the calls are placed through the operator API.
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+fn destination_call(cluster: &Cluster, floor: u8, destination: u8) {
+    let accepted = cluster.command("/api/hall_requests", json!({ "floor": floor, "call": "Destination", "destination": destination }));
+    assert!(accepted.is_some(), "The destination call from {} to {} was not accepted", floor, destination);
+}
+
+#[test]
+fn destination_call_becomes_a_pickup_and_a_cab_request() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "hall_request_assigner": { "strategy": "destination" },
+    }));
+    let elevator = &cluster.elevators[0];
+    let started = elevator.door_openings().len();
+
+    destination_call(&cluster, 1, 3);
+    elevator.press(2, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(3) && elevator.served(2));
+    assert!(served, "The elevator did not serve both calls, it opened its doors at {:?}", elevator.door_openings());
+    let openings = elevator.door_openings()[started..].to_vec();
+    let pickup = openings.iter().position(|&floor| floor == 1).expect("The passenger was not picked up");
+    assert!(openings[pickup..].contains(&3), "The passenger was not taken to the destination, doors opened at {:?}", openings);
+    assert!(!elevator.lamp(3, Call::Cab), "The cab request for the destination was not cleared");
+}
+
+#[test]
+fn passengers_going_to_the_same_floor_share_an_elevator() {
+    let cluster = Cluster::start_with(&[0, 2], json!({
+        "hall_request_assigner": { "strategy": "destination" },
+    }));
+    let (first, second) = (&cluster.elevators[0], &cluster.elevators[1]);
+    settle(&cluster);
+    let second_started = second.door_openings().len();
+
+    destination_call(&cluster, 0, 3);
+    destination_call(&cluster, 2, 3);
+
+    let served = wait_until(SERVICE_TIMEOUT, || first.served(3) || second.served(3));
+    assert!(served, "No elevator took the passengers to floor 3");
+    assert!(first.served(2) && first.served(3), "The passengers were not picked up by the same elevator");
+    assert_eq!(second.door_openings().len(), second_started, "The other elevator picked up a passenger");
+}
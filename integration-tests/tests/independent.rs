@@ -0,0 +1,54 @@
+//! Elevators in independent service are left out of the group, serving their
+//! cab requests only, whether switched by an operator or at the car when the
+//! button combination is on
+
+use std::time::Duration;
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{mode, settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+#[test]
+fn independent_elevator_is_not_given_hall_requests() {
+    let cluster = Cluster::start(&[1, 0]);
+    let (independent, group) = (&cluster.elevators[0], &cluster.elevators[1]);
+    let accepted = cluster.command("/api/elevators/0/independent", json!({}));
+    assert!(accepted.is_some(), "The independent service command was not accepted");
+    let switched = wait_until(SERVICE_TIMEOUT, || mode(&cluster, "0").as_deref() == Some("independent"));
+    assert!(switched, "The elevator did not report independent service");
+
+    independent.press(2, Call::HallUp);
+    let served = wait_until(SERVICE_TIMEOUT, || group.served(2));
+    assert!(served, "The elevator under group control did not serve the hall request");
+    assert!(!independent.served(2), "The elevator in independent service served the hall request");
+
+    independent.press(3, Call::Cab);
+    let served = wait_until(SERVICE_TIMEOUT, || independent.served(3));
+    assert!(served, "The elevator in independent service did not serve its cab request");
+}
+
+#[test]
+fn button_combination_switches_independent_service() {
+    let cluster = Cluster::start_with(&[1], json!({
+        "elevator": { "independent_combination_window": 1.0 },
+    }));
+    let elevator = &cluster.elevators[0];
+    settle(&cluster);
+
+    elevator.press(0, Call::Cab);
+    elevator.press(3, Call::Cab);
+    let switched = wait_until(SERVICE_TIMEOUT, || mode(&cluster, "0").as_deref() == Some("independent"));
+    assert!(switched, "The button combination did not switch on independent service");
+    assert!(!elevator.lamp(0, Call::Cab) && !elevator.lamp(3, Call::Cab), "The button combination left cab requests");
+
+    elevator.press(2, Call::HallDown);
+    std::thread::sleep(Duration::from_secs(3));
+    assert!(!elevator.served(2), "The elevator in independent service served a hall request");
+
+    elevator.press(3, Call::Cab);
+    elevator.press(0, Call::Cab);
+    let served = wait_until(SERVICE_TIMEOUT, || mode(&cluster, "0").as_deref() == Some("normal") && elevator.served(2));
+    assert!(served, "The elevator did not serve the hall request after returning to group control");
+}
@@ -0,0 +1,71 @@
+//! Full cars pass the hall requests on their way, and other elevators are
+//! preferred for new hall requests, also with the default `executable` strategy
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{reported_full, settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+/// Above the full share of the default capacity
+const FULL_LOAD: u16 = 600;
+
+#[test]
+fn full_car_passes_hall_requests_until_passengers_leave() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "load": { "sensor": "file" },
+    }));
+    let elevator = &cluster.elevators[0];
+    cluster.set_load(0, FULL_LOAD);
+    let full = wait_until(SERVICE_TIMEOUT, || reported_full(&cluster, "0"));
+    assert!(full, "The elevator did not report a full car");
+    let started = elevator.door_openings().len();
+
+    elevator.press(3, Call::Cab);
+    elevator.press(2, Call::HallUp);
+
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(3) && elevator.served(2) && !elevator.lamp(2, Call::HallUp));
+    assert!(served, "The elevator did not serve both requests, it opened its doors at {:?}", elevator.door_openings());
+    assert_eq!(elevator.door_openings()[started], 3, "The full car stopped for the hall request on its way");
+}
+
+#[test]
+fn hall_requests_go_to_an_elevator_that_is_not_full() {
+    let cluster = Cluster::start_with(&[1, 3], json!({
+        "load": { "sensor": "file" },
+    }));
+    let (full, empty) = (&cluster.elevators[0], &cluster.elevators[1]);
+    settle(&cluster);
+    cluster.set_load(0, FULL_LOAD);
+    let reported = wait_until(SERVICE_TIMEOUT, || reported_full(&cluster, "0"));
+    assert!(reported, "The elevator did not report a full car");
+    let full_started = full.door_openings().len();
+
+    full.press(1, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || empty.served(1));
+    assert!(served, "The elevator that is not full did not serve the hall request");
+    assert_eq!(full.door_openings().len(), full_started, "The full car was given the hall request");
+}
+
+#[test]
+fn executable_does_not_give_hall_requests_to_a_full_car() {
+    let cluster = Cluster::start_with(&[1, 3], json!({
+        "load": { "sensor": "file" },
+        "hall_request_assigner": { "strategy": "executable" },
+    }));
+    // The stand-in executable gives the hall requests to the full car
+    cluster.install_assigner(0);
+    let (full, empty) = (&cluster.elevators[0], &cluster.elevators[1]);
+    settle(&cluster);
+    cluster.set_load(0, FULL_LOAD);
+    let reported = wait_until(SERVICE_TIMEOUT, || reported_full(&cluster, "0"));
+    assert!(reported, "The elevator did not report a full car");
+    let full_started = full.door_openings().len();
+
+    full.press(1, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || empty.served(1));
+    assert!(served, "The elevator that is not full did not serve the hall request");
+    assert_eq!(full.door_openings().len(), full_started, "The full car was given the hall request");
+}
@@ -0,0 +1,56 @@
+//! Requests are served when the network between a slave and the master is lost
+
+use std::thread;
+use std::time::Duration;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::wait_until;
+use shared_resources::call::Call;
+
+const SERVICE_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[test]
+fn hall_request_waits_for_the_network_to_return() {
+    let cluster = Cluster::start(&[0, 0]);
+    cluster.cut(1);
+    cluster.elevators[1].press(2, Call::HallUp);
+
+    // A disconnected slave can not have its hall requests confirmed, so nobody serves them
+    thread::sleep(Duration::from_secs(1));
+    assert!(!cluster.elevators.iter().any(|elevator| elevator.served(2)), "The hall request was served while disconnected");
+    assert!(!cluster.elevators[1].lamp(2, Call::HallUp), "The hall lamp was lit while disconnected");
+
+    // The slave still holds the request, as long as the request buffer has not timed out
+    cluster.restore(1);
+    let served = wait_until(SERVICE_TIMEOUT, || cluster.elevators.iter().any(|elevator| elevator.served(2)));
+    assert!(served, "The hall request was not served after the network returned");
+}
+
+#[test]
+fn hall_request_is_reassigned_from_a_disconnected_elevator() {
+    // Elevator 1 is nearest, but never hears of the request
+    let cluster = Cluster::start(&[0, 3]);
+    // The doors open when the slave starts
+    let door_openings = cluster.elevators[1].door_openings().len();
+    cluster.cut(1);
+    cluster.elevators[0].press(3, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || cluster.elevators[0].served(3));
+    assert!(served, "Elevator 0 did not serve the hall request of the disconnected elevator");
+    assert!(!cluster.elevators[1].door_openings()[door_openings..].contains(&3), "The disconnected elevator served a request it never heard of");
+}
+
+#[test]
+fn elevator_rejoins_when_the_network_returns() {
+    let cluster = Cluster::start(&[0, 0]);
+    cluster.cut(1);
+    let left = wait_until(SERVICE_TIMEOUT, || cluster.connected_elevators().len() == 1);
+    assert!(left, "The master did not disconnect the elevator");
+
+    cluster.restore(1);
+    let rejoined = wait_until(SERVICE_TIMEOUT, || cluster.connected_elevators().len() == 2);
+    assert!(rejoined, "The elevator did not rejoin when the network returned");
+    cluster.elevators[1].press(2, Call::HallDown);
+    let served = wait_until(SERVICE_TIMEOUT, || cluster.elevators.iter().any(|elevator| elevator.served(2)));
+    assert!(served, "The hall request was not served after the elevator rejoined");
+}
@@ -0,0 +1,36 @@
+//! Requests pressed on a panel are delivered to an elevator and served
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+#[test]
+fn hall_request_is_served() {
+    let cluster = Cluster::start(&[0, 0]);
+    cluster.elevators[0].press(3, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || cluster.elevators.iter().any(|elevator| elevator.served(3)));
+    assert!(served, "No elevator served the hall request at floor 3");
+}
+
+#[test]
+fn hall_lamps_are_lit_on_every_panel_until_served() {
+    let cluster = Cluster::start(&[0, 0]);
+    cluster.elevators[1].press(2, Call::HallUp);
+
+    let lit = wait_until(SERVICE_TIMEOUT, || cluster.elevators.iter().all(|elevator| elevator.lamp(2, Call::HallUp)));
+    assert!(lit, "The hall lamp at floor 2 was not lit on every panel");
+    let cleared = wait_until(SERVICE_TIMEOUT, || cluster.elevators.iter().all(|elevator| !elevator.lamp(2, Call::HallUp)));
+    assert!(cleared, "The hall lamp at floor 2 was not cleared");
+    assert!(cluster.elevators.iter().any(|elevator| elevator.served(2)), "The hall lamp was cleared without serving floor 2");
+}
+
+#[test]
+fn cab_request_is_served_by_its_own_elevator() {
+    let cluster = Cluster::start(&[0, 0]);
+    cluster.elevators[1].press(2, Call::Cab);
+
+    let served = wait_until(SERVICE_TIMEOUT, || cluster.elevators[1].served(2));
+    assert!(served, "Elevator 1 did not serve its cab request at floor 2");
+    assert!(!cluster.elevators[0].served(2), "Elevator 0 served the cab request of elevator 1");
+}
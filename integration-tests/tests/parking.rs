@@ -0,0 +1,84 @@
+//! Idle elevators are parked across the building, and leave their parking trip for requests
+
+use std::time::Duration;
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::wait_until;
+use shared_resources::call::Call;
+
+const PARKING_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[test]
+fn idle_elevators_are_spread_across_the_building() {
+    let cluster = Cluster::start_with(&[0, 0], json!({
+        "parking": { "enabled": true },
+    }));
+
+    let parked = wait_until(PARKING_TIMEOUT, || {
+        let mut floors: Vec<Option<u8>> = cluster.elevators.iter().map(|elevator| elevator.floor()).collect();
+        floors.sort();
+        floors == [Some(0), Some(2)]
+    });
+    assert!(parked, "The elevators were not parked at floors 0 and 2");
+    assert!(!cluster.elevators.iter().any(|elevator| elevator.served(2)), "The doors opened at the parking floor");
+}
+
+#[test]
+fn half_of_the_idle_elevators_wait_at_the_lobby_during_up_peak() {
+    let cluster = Cluster::start_with(&[3, 3], json!({
+        "parking": { "enabled": true },
+        "traffic": { "lobby_floor": 0, "schedule": [{ "start": 0, "end": 24, "mode": "up_peak" }] },
+    }));
+
+    let parked = wait_until(PARKING_TIMEOUT, || {
+        let mut floors: Vec<Option<u8>> = cluster.elevators.iter().map(|elevator| elevator.floor()).collect();
+        floors.sort();
+        floors == [Some(0), Some(1)]
+    });
+    assert!(parked, "The elevators were not parked at the lobby and floor 1");
+}
+
+#[test]
+fn parking_is_abandoned_for_a_request() {
+    let cluster = Cluster::start_with(&[3], json!({
+        "parking": { "enabled": true },
+    }));
+    let elevator = &cluster.elevators[0];
+    let behaviour = || cluster.api("/api/elevators").map(|elevators| elevators["0"]["state"]["behaviour"].clone());
+    let moving_down = || {
+        cluster.api("/api/elevators").is_some_and(|elevators| {
+            elevators["0"]["state"]["behaviour"] == json!("moving") && elevators["0"]["state"]["direction"] == json!("down")
+        })
+    };
+    // A starting elevator also moves down until it finds a floor, so the parking trip is the next move after it stops
+    let stopped = wait_until(PARKING_TIMEOUT, || behaviour().is_some_and(|behaviour| behaviour != json!("moving")));
+    assert!(stopped, "The elevator did not stop after starting");
+    let left = wait_until(PARKING_TIMEOUT, moving_down);
+    assert!(left, "The elevator did not leave for its parking floor");
+    let started = elevator.door_openings().len();
+
+    elevator.press(3, Call::Cab);
+    let served = wait_until(PARKING_TIMEOUT, || elevator.door_openings().len() > started);
+    assert!(served, "The elevator did not return to serve the cab request");
+    assert_eq!(elevator.door_openings()[started], 3, "The elevator stopped on the way to the cab request");
+
+    let parked = wait_until(PARKING_TIMEOUT, || elevator.floor() == Some(1) && !elevator.door_open());
+    assert!(parked, "The elevator did not park again after serving the request");
+}
+
+#[test]
+fn idle_elevators_wait_above_the_lobby_during_down_peak() {
+    let cluster = Cluster::start_with(&[0, 0], json!({
+        "parking": { "enabled": true },
+        "traffic": { "lobby_floor": 0, "schedule": [{ "start": 0, "end": 24, "mode": "down_peak" }] },
+    }));
+
+    let parked = wait_until(PARKING_TIMEOUT, || {
+        let mut floors: Vec<Option<u8>> = cluster.elevators.iter().map(|elevator| elevator.floor()).collect();
+        floors.sort();
+        floors == [Some(1), Some(2)]
+    });
+    assert!(parked, "The elevators were not parked at floors 1 and 2");
+}
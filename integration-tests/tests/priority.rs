@@ -0,0 +1,79 @@
+//! Priority hall calls are served before the other hall requests, taking the
+//! elevators away from them if needed
+
+use serde_json::{json, Value};
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+fn priority(cluster: &Cluster, floor: u8, call: &str) -> Option<u64> {
+    let hall_requests = cluster.api("/api/hall_requests")?;
+    hall_requests.as_array()?.iter()
+        .find(|request| request["floor"] == json!(floor) && request["call"] == json!(call))
+        .and_then(|request| request["priority"].as_u64())
+}
+
+#[test]
+fn priority_call_is_served_before_the_requests_on_the_way() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "priority": { "floors": { "3": 1 } },
+    }));
+    settle(&cluster);
+    let elevator = &cluster.elevators[0];
+    let started = elevator.door_openings().len();
+
+    elevator.press(1, Call::HallUp);
+    elevator.press(3, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(3) && elevator.served(1));
+    assert!(served, "The elevator did not serve both hall requests, it opened its doors at {:?}", elevator.door_openings());
+    assert_eq!(elevator.door_openings()[started], 3, "The elevator stopped for the normal hall request before the priority call");
+}
+
+#[test]
+fn waiting_requests_stay_lit_while_the_car_serves_a_priority_call() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "priority": { "floors": { "3": 1 } },
+    }));
+    settle(&cluster);
+    let elevator = &cluster.elevators[0];
+
+    elevator.press(3, Call::HallDown);
+    elevator.press(1, Call::HallUp);
+
+    // The only car is busy with the priority call, so the normal call waits unassigned
+    let lit = wait_until(SERVICE_TIMEOUT, || elevator.lamp(1, Call::HallUp) || elevator.served(3));
+    assert!(lit && !elevator.served(3), "The lamp of the waiting hall request was not lit before the priority call was served");
+    let mut went_dark = false;
+    let served = wait_until(SERVICE_TIMEOUT, || {
+        went_dark |= !elevator.lamp(1, Call::HallUp);
+        elevator.served(3)
+    });
+    assert!(served, "The elevator did not serve the priority call");
+    assert!(!went_dark, "The lamp of the waiting hall request went dark while the car served the priority call");
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(1) && !elevator.lamp(1, Call::HallUp));
+    assert!(served, "The waiting hall request was not served after the priority call");
+}
+
+#[test]
+fn operator_placed_requests_get_the_priority_of_their_button() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "priority": {
+            "floors": { "2": 1 },
+            "buttons": [{ "floor": 2, "call": "HallUp", "level": 2 }],
+        },
+    }));
+    settle(&cluster);
+
+    for call in ["HallUp", "HallDown"] {
+        let accepted = cluster.command("/api/hall_requests", json!({ "floor": 2, "call": call }));
+        assert!(accepted.is_some(), "The hall request was not accepted");
+    }
+
+    let listed = wait_until(SERVICE_TIMEOUT, || {
+        priority(&cluster, 2, "HallUp") == Some(2) && priority(&cluster, 2, "HallDown") == Some(1)
+    });
+    let hall_requests = cluster.api("/api/hall_requests").unwrap_or(Value::Null);
+    assert!(listed, "The hall requests were not given their configured priority: {}", hall_requests);
+}
@@ -0,0 +1,56 @@
+//! An emergency recall cancels the hall requests and sends every elevator
+//! non-stop to the recall floor, where it waits with its doors open until reset
+
+use std::time::Duration;
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{mode, settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+#[test]
+fn recalled_elevators_go_non_stop_to_the_recall_floor() {
+    let cluster = Cluster::start(&[3, 2]);
+    settle(&cluster);
+    let started: Vec<usize> = cluster.elevators.iter().map(|elevator| elevator.door_openings().len()).collect();
+
+    cluster.elevators[0].press(1, Call::HallUp);
+    let accepted = cluster.command("/api/recall", json!({ "floor": 0 }));
+    assert!(accepted.is_some(), "The recall was not accepted");
+
+    let arrived = wait_until(SERVICE_TIMEOUT, || {
+        ["0", "1"].iter().all(|id| mode(&cluster, id).as_deref() == Some("recalled"))
+    });
+    assert!(arrived, "The elevators did not arrive at the recall floor");
+    for (elevator, started) in cluster.elevators.iter().zip(started) {
+        assert!(!elevator.door_openings()[started..].contains(&1), "An elevator stopped for the cancelled hall request");
+        assert!(elevator.door_open(), "An elevator did not hold its doors open at the recall floor");
+    }
+    assert!(!cluster.elevators[0].lamp(1, Call::HallUp), "The hall request was not cancelled");
+
+    cluster.elevators[1].press(3, Call::Cab);
+    cluster.elevators[1].press(2, Call::HallDown);
+    std::thread::sleep(Duration::from_secs(2));
+    assert!(!cluster.elevators[1].lamp(3, Call::Cab), "A recalled elevator accepted a cab request");
+    assert!(!cluster.elevators[1].lamp(2, Call::HallDown), "A hall request was accepted during the recall");
+}
+
+#[test]
+fn reset_returns_the_elevators_to_normal_service() {
+    let cluster = Cluster::start(&[0]);
+    settle(&cluster);
+    let elevator = &cluster.elevators[0];
+
+    cluster.command("/api/recall", json!({ "floor": 2 }));
+    let arrived = wait_until(SERVICE_TIMEOUT, || mode(&cluster, "0").as_deref() == Some("recalled"));
+    assert!(arrived, "The elevator did not arrive at the recall floor");
+    let accepted = cluster.command("/api/recall/reset", json!({}));
+    assert!(accepted.is_some(), "The reset was not accepted");
+    let closed = wait_until(SERVICE_TIMEOUT, || !elevator.door_open());
+    assert!(closed, "The elevator kept its doors open after the reset");
+
+    elevator.press(3, Call::Cab);
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(3));
+    assert!(served, "The elevator did not serve a cab request after the reset");
+}
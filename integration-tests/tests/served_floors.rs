@@ -0,0 +1,40 @@
+//! Elevators are only given hall requests at the floors they serve, and reject
+//! cab requests to the floors they do not serve
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{settle, wait_until, SERVICE_TIMEOUT};
+use shared_resources::call::Call;
+
+#[test]
+fn hall_requests_go_to_an_elevator_serving_the_floor() {
+    let cluster = Cluster::start_with(&[0, 2], json!({
+        "elevator": { "served_floors": { "1": [0, 1, 2] } },
+    }));
+    let (express, local) = (&cluster.elevators[0], &cluster.elevators[1]);
+    settle(&cluster);
+    let local_started = local.door_openings().len();
+
+    local.press(3, Call::HallDown);
+
+    let served = wait_until(SERVICE_TIMEOUT, || express.served(3));
+    assert!(served, "The elevator serving floor 3 did not serve the hall request");
+    assert_eq!(local.door_openings().len(), local_started, "The elevator not serving floor 3 was given the hall request");
+}
+
+#[test]
+fn cab_requests_to_floors_not_served_are_rejected() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "elevator": { "served_floors": { "0": [0, 1, 2] } },
+    }));
+    let elevator = &cluster.elevators[0];
+
+    elevator.press(3, Call::Cab);
+    elevator.press(2, Call::Cab);
+
+    let served = wait_until(SERVICE_TIMEOUT, || elevator.served(2) && !elevator.door_open());
+    assert!(served, "The cab request to a floor served was not served");
+    assert!(!elevator.lamp(3, Call::Cab), "The cab request to a floor not served was accepted");
+    assert!(!elevator.served(3), "The elevator went to a floor it does not serve");
+}
@@ -0,0 +1,64 @@
+//! The master follows the traffic mode of the building, either scheduled or
+//! detected, also when the hall requests are assigned by the executable
+
+use std::time::Duration;
+
+use serde_json::json;
+
+use integration_tests::cluster::Cluster;
+use integration_tests::{settle, traffic_mode, wait_until};
+use shared_resources::call::Call;
+
+const MODE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn scheduled_traffic_mode_is_shown_in_the_status() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "traffic": { "schedule": [{ "start": 0, "end": 24, "mode": "down_peak" }] },
+    }));
+
+    let scheduled = wait_until(MODE_TIMEOUT, || traffic_mode(&cluster).as_deref() == Some("down_peak"));
+    assert!(scheduled, "The status shows {:?} instead of the scheduled mode", traffic_mode(&cluster));
+}
+
+#[test]
+fn up_calls_at_the_lobby_are_detected_as_up_peak() {
+    let cluster = Cluster::start_with(&[0], json!({
+        "traffic": { "lobby_floor": 0, "detection": true, "window": 60.0, "min_requests": 3, "peak_share": 0.6 },
+    }));
+    let elevator = &cluster.elevators[0];
+    assert_eq!(traffic_mode(&cluster).as_deref(), Some("inter_floor"));
+
+    for _ in 0..3 {
+        let openings = elevator.door_openings().len();
+        elevator.press(0, Call::HallUp);
+        let served = wait_until(MODE_TIMEOUT, || {
+            elevator.door_openings().len() > openings && !elevator.door_open() && !elevator.lamp(0, Call::HallUp)
+        });
+        assert!(served, "The up call at the lobby was not served");
+    }
+
+    let detected = wait_until(MODE_TIMEOUT, || traffic_mode(&cluster).as_deref() == Some("up_peak"));
+    assert!(detected, "The status shows {:?} instead of up-peak", traffic_mode(&cluster));
+}
+
+#[test]
+fn executable_leaves_the_lobby_elevator_waiting_during_up_peak() {
+    let cluster = Cluster::start_with(&[0, 2], json!({
+        "traffic": { "schedule": [{ "start": 0, "end": 24, "mode": "up_peak" }] },
+        "hall_request_assigner": { "strategy": "executable" },
+    }));
+    // The stand-in executable gives the hall requests to the elevator waiting at the lobby
+    cluster.install_assigner(0);
+    let (lobby, upper) = (&cluster.elevators[0], &cluster.elevators[1]);
+    settle(&cluster);
+    let scheduled = wait_until(MODE_TIMEOUT, || traffic_mode(&cluster).as_deref() == Some("up_peak"));
+    assert!(scheduled, "The status shows {:?} instead of up-peak", traffic_mode(&cluster));
+    let lobby_started = lobby.door_openings().len();
+
+    upper.press(3, Call::HallDown);
+
+    let served = wait_until(MODE_TIMEOUT, || upper.served(3));
+    assert!(served, "The elevator away from the lobby did not serve the hall request");
+    assert_eq!(lobby.door_openings().len(), lobby_started, "The elevator waiting at the lobby was given the hall request");
+}
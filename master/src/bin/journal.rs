@@ -0,0 +1,344 @@
+//! ----- JOURNAL -----
+//! This tool reads the journal of the master and prints the events matching
+//! the given filters, or the history of each hall request, answering questions
+//! like which elevator served a call at a given time and how long it waited.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone};
+use clap::{Parser, ValueEnum};
+
+use master::utilities::journal::{Entry, Event};
+use shared_resources::call::Call;
+use shared_resources::request::{Request, TraceId};
+
+const DEFAULT_JOURNAL_DIRECTORY: &str = "journal";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum HallCall {
+    Up,
+    Down,
+}
+
+#[derive(Parser)]
+#[command(name = "journal", about = "Queries the journal of significant events on the master")]
+struct Args {
+    /// Journal files or directories of journal files to read [default: journal]
+    paths: Vec<PathBuf>,
+
+    /// Only include what happened at or after this time, as HH:MM[:SS] or YYYY-MM-DD HH:MM[:SS].
+    /// Times without a date are on the day of the last event in the journal
+    #[arg(long, value_name = "TIME")]
+    from: Option<String>,
+
+    /// Only include what happened at or before this time
+    #[arg(long, value_name = "TIME")]
+    to: Option<String>,
+
+    /// Only include what happened during this minute, or second if given with seconds
+    #[arg(long, value_name = "TIME", conflicts_with_all = ["from", "to"])]
+    at: Option<String>,
+
+    /// Only include events about this elevator, or requests assigned to or served by it
+    #[arg(long, value_name = "ID")]
+    elevator: Option<String>,
+
+    /// Only include hall requests at this floor
+    #[arg(long)]
+    floor: Option<u8>,
+
+    /// Only include hall requests in this direction, including the destination calls travelling in it
+    #[arg(long, value_enum)]
+    call: Option<HallCall>,
+
+    /// Only include the hall request with this trace id
+    #[arg(long, value_name = "ID")]
+    trace_id: Option<TraceId>,
+
+    /// Print the history of each hall request instead of the events
+    #[arg(long)]
+    requests: bool,
+}
+
+/// What happened to a hall request from when it was received until it was served or cancelled
+struct RequestHistory {
+    request: Request,
+    received: Option<DateTime<Local>>,
+    first_seen: DateTime<Local>,
+    last_seen: DateTime<Local>,
+    confirmed: Option<DateTime<Local>>,
+    assigned_to: Vec<String>,
+    served: Option<(DateTime<Local>, String)>,
+    cancelled: Option<DateTime<Local>>,
+}
+
+impl RequestHistory {
+    fn new(request: &Request, time: DateTime<Local>) -> Self {
+        RequestHistory {
+            request: request.clone(),
+            received: None,
+            first_seen: time,
+            last_seen: time,
+            confirmed: None,
+            assigned_to: Vec::new(),
+            served: None,
+            cancelled: None,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.served.is_some() || self.cancelled.is_some()
+    }
+
+    fn add(&mut self, time: DateTime<Local>, event: &Event) {
+        self.last_seen = time;
+        if self.request.trace_id.is_none() {
+            self.request.trace_id = event.request().and_then(|request| request.trace_id);
+        }
+        match event {
+            Event::RequestReceived { .. } => self.received = self.received.or(Some(time)),
+            Event::RequestConfirmed { .. } => self.confirmed = self.confirmed.or(Some(time)),
+            Event::RequestAssigned { elevator, .. } | Event::RequestReassigned { elevator, .. }
+                if self.assigned_to.last() != Some(elevator) => self.assigned_to.push(elevator.clone()),
+            Event::RequestServed { elevator, .. } => self.served = self.served.take().or(Some((time, elevator.clone()))),
+            Event::RequestCancelled { .. } | Event::RequestWithdrawn { .. } | Event::RequestExpired { .. } =>
+                self.cancelled = self.cancelled.or(Some(time)),
+            _ => (),
+        }
+    }
+
+    /// Time from when the request was received until it was served
+    fn waited(&self) -> Option<Duration> {
+        let (served, _) = self.served.as_ref()?;
+        Some(*served - self.received.unwrap_or(self.first_seen))
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let paths = if args.paths.is_empty() { vec![PathBuf::from(DEFAULT_JOURNAL_DIRECTORY)] } else { args.paths.clone() };
+
+    let entries = read_journal(&paths);
+    let last_day = match entries.last() {
+        Some(entry) => entry.time.date_naive(),
+        None => {
+            println!("No events found in {}", paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "));
+            return
+        },
+    };
+    let (from, to) = match &args.at {
+        Some(at) => {
+            let (time, precision) = parse_time(at, last_day);
+            (Some(time), Some(time + precision - Duration::milliseconds(1)))
+        },
+        None => (
+            args.from.as_ref().map(|from| parse_time(from, last_day).0),
+            args.to.as_ref().map(|to| {
+                let (time, precision) = parse_time(to, last_day);
+                time + precision - Duration::milliseconds(1)
+            }),
+        ),
+    };
+    let in_range = |start: DateTime<Local>, end: DateTime<Local>| {
+        from.is_none_or(|from| end >= from) && to.is_none_or(|to| start <= to)
+    };
+
+    if args.requests {
+        let histories: Vec<RequestHistory> = request_histories(&entries).into_iter()
+            .filter(|history| matches_request(&args, &history.request))
+            .filter(|history| in_range(history.received.unwrap_or(history.first_seen), history.last_seen))
+            .filter(|history| args.elevator.as_ref().is_none_or(|elevator| {
+                history.assigned_to.contains(elevator) || history.served.as_ref().is_some_and(|(_, served_by)| served_by == elevator)
+            }))
+            .collect();
+        print_requests(&histories);
+    } else {
+        for entry in &entries {
+            let request_matches = match entry.event.request() {
+                Some(request) => matches_request(&args, request),
+                None => args.floor.is_none() && args.call.is_none() && args.trace_id.is_none(),
+            };
+            let elevator_matches = args.elevator.as_ref().is_none_or(|elevator| entry.event.elevator() == Some(elevator.as_str()));
+            if request_matches && elevator_matches && in_range(entry.time, entry.time) {
+                println!("{}  {}", entry.time.format("%Y-%m-%d %H:%M:%S%.3f"), describe(&entry.event));
+            }
+        }
+    }
+}
+
+/// Reads every journal file in `paths`, ordering the events by time. Lines
+/// that can not be read, like one cut short by a crash, are skipped.
+fn read_journal(paths: &[PathBuf]) -> Vec<Entry> {
+    let mut files = Vec::new();
+    for path in paths {
+        match fs::read_dir(path) {
+            Ok(dir_entries) => files.extend(dir_entries
+                .filter_map(|dir_entry| dir_entry.ok().map(|dir_entry| dir_entry.path()))
+                .filter(|file_path| file_path.file_name().is_some_and(|name| name.to_string_lossy().contains(".jsonl")))),
+            Err(_) => files.push(path.clone()),
+        }
+    }
+
+    let mut entries = Vec::new();
+    for file_path in files {
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", file_path.display(), e);
+                process::exit(1);
+            },
+        };
+        for (index, line) in content.lines().enumerate() {
+            match serde_json::from_str::<Entry>(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => eprintln!("warning: skipping {} line {}: {}", file_path.display(), index + 1, e),
+            }
+        }
+    }
+    entries.sort_by_key(|entry| entry.time);
+    entries
+}
+
+/// Parses a time given on the command line, returning it along with the
+/// precision it was given with
+fn parse_time(text: &str, default_day: NaiveDate) -> (DateTime<Local>, Duration) {
+    let text = text.trim();
+    let (day, time) = match text.split_once([' ', 'T']) {
+        Some((day, time)) => match NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+            Ok(day) => (day, time),
+            Err(_) => invalid_time(text),
+        },
+        None => (default_day, text),
+    };
+    let (time, precision) = if let Ok(time) = NaiveTime::parse_from_str(time, "%H:%M:%S%.f") {
+        (time, Duration::seconds(1))
+    } else if let Ok(time) = NaiveTime::parse_from_str(time, "%H:%M") {
+        (time, Duration::minutes(1))
+    } else {
+        invalid_time(text)
+    };
+    match Local.from_local_datetime(&day.and_time(time)).earliest() {
+        Some(time) => (time, precision),
+        None => invalid_time(text),
+    }
+}
+
+fn invalid_time(text: &str) -> ! {
+    eprintln!("error: invalid time \"{}\", expected HH:MM[:SS] or YYYY-MM-DD HH:MM[:SS]", text);
+    process::exit(2);
+}
+
+fn matches_request(args: &Args, request: &Request) -> bool {
+    let call = match request.hall_call() {
+        Call::HallUp => Some(HallCall::Up),
+        Call::HallDown => Some(HallCall::Down),
+        Call::Cab | Call::Destination => None,
+    };
+    args.floor.is_none_or(|floor| request.floor == floor)
+        && args.call.is_none_or(|selected| call == Some(selected))
+        && args.trace_id.is_none_or(|trace_id| request.trace_id == Some(trace_id))
+}
+
+/// Groups the request events into the history of each hall request. A new
+/// history starts when a request is received or stored by the backup after
+/// the previous request for the same hall call was finished.
+fn request_histories(entries: &[Entry]) -> Vec<RequestHistory> {
+    let mut histories: Vec<RequestHistory> = Vec::new();
+    let mut latest: HashMap<(u8, usize), usize> = HashMap::new();
+    for entry in entries {
+        let request = match entry.event.request() {
+            Some(request) => request,
+            None => continue,
+        };
+        let key = (request.floor, request.column());
+        let starts_request = matches!(entry.event, Event::RequestReceived { .. } | Event::RequestConfirmed { .. });
+        let index = match latest.get(&key) {
+            Some(&index) if !(starts_request && histories[index].finished()) => index,
+            _ => {
+                histories.push(RequestHistory::new(request, entry.time));
+                latest.insert(key, histories.len() - 1);
+                histories.len() - 1
+            },
+        };
+        histories[index].add(entry.time, &entry.event);
+    }
+    histories
+}
+
+fn print_requests(histories: &[RequestHistory]) {
+    let served = histories.iter().filter(|history| history.served.is_some()).count();
+    let cancelled = histories.iter().filter(|history| history.cancelled.is_some()).count();
+    println!("{} hall requests, {} served, {} cancelled, {} not finished\n",
+        histories.len(), served, cancelled, histories.len() - served - cancelled);
+    println!("{:<8} {:<23} {:<12} {:<9} {:<12} {:>9}  {:<16}", "CALL", "RECEIVED", "ASSIGNED TO", "SERVED BY", "SERVED AT", "WAITED", "TRACE ID");
+    for history in histories {
+        let (served_by, served_at) = match &history.served {
+            Some((time, elevator)) => (elevator.clone(), time.format("%H:%M:%S%.3f").to_string()),
+            None if history.cancelled.is_some() => (String::from("cancelled"), String::from("-")),
+            None => (String::from("-"), String::from("-")),
+        };
+        println!("{:<8} {:<23} {:<12} {:<9} {:<12} {:>9}  {:<16}",
+            call_name(&history.request),
+            history.received.unwrap_or(history.first_seen).format("%Y-%m-%d %H:%M:%S%.3f"),
+            history.assigned_to.join(", "),
+            served_by,
+            served_at,
+            history.waited()
+                .map(|waited| format!("{:.1} s", waited.num_milliseconds() as f64 / 1000.0))
+                .unwrap_or_else(|| String::from("-")),
+            history.request.trace_id.map(|trace_id| trace_id.to_string()).unwrap_or_default(),
+        );
+    }
+}
+
+fn describe(event: &Event) -> String {
+    let request = |request: &Request, text: String| match request.trace_id {
+        Some(trace_id) => format!("{} {} ({})", call_name(request), text, trace_id),
+        None => format!("{} {}", call_name(request), text),
+    };
+    match event {
+        Event::MasterStarted => String::from("master started"),
+        Event::ElevatorJoined { elevator } => format!("elevator {} joined", elevator),
+        Event::ElevatorLeft { elevator } => format!("elevator {} left", elevator),
+        Event::ElevatorUnavailable { elevator } => format!("elevator {} unavailable", elevator),
+        Event::ElevatorAvailable { elevator } => format!("elevator {} available again", elevator),
+        Event::ElevatorOutOfService { elevator } => format!("elevator {} taken out of service", elevator),
+        Event::ElevatorInService { elevator } => format!("elevator {} put back in service", elevator),
+        Event::ElevatorIndependent { elevator } => format!("elevator {} in independent service", elevator),
+        Event::ElevatorGroupControl { elevator } => format!("elevator {} back under group control", elevator),
+        Event::RequestReceived { request: hall_request } => request(hall_request, String::from("received")),
+        Event::RequestConfirmed { request: hall_request } => request(hall_request, String::from("stored by the backup")),
+        Event::RequestAssigned { request: hall_request, elevator } =>
+            request(hall_request, format!("assigned to elevator {}", elevator)),
+        Event::RequestReassigned { request: hall_request, elevator, previous } =>
+            request(hall_request, format!("reassigned from elevator {} to elevator {}", previous, elevator)),
+        Event::RequestServed { request: hall_request, elevator } =>
+            request(hall_request, format!("served by elevator {}", elevator)),
+        Event::RequestCancelled { request: hall_request } => request(hall_request, String::from("cancelled by operator")),
+        Event::RequestWithdrawn { request: hall_request, elevator } =>
+            request(hall_request, format!("cancelled by the passenger at elevator {}", elevator)),
+        Event::RequestExpired { request: hall_request } => request(hall_request, String::from("expired")),
+        Event::AssignerFailed { error } => format!("hall request assigner failed: {}", error),
+        Event::AssignerRecovered => String::from("hall request assigner working again"),
+        Event::BackupSilent => String::from("backup silent"),
+        Event::BackupResponding => String::from("backup responding again"),
+        Event::TrafficModeChanged { mode } => format!("traffic mode changed to {}", mode.as_str()),
+        Event::RecallStarted { floor } => format!("emergency recall to floor {} started", floor),
+        Event::RecallReset => String::from("emergency recall reset"),
+    }
+}
+
+fn call_name(request: &Request) -> String {
+    match request.call {
+        Call::HallUp => format!("up {}", request.floor),
+        Call::HallDown => format!("down {}", request.floor),
+        Call::Cab => format!("cab {}", request.floor),
+        Call::Destination => match request.destination {
+            Some(destination) => format!("{} to {}", request.floor, destination),
+            None => format!("destination {}", request.floor),
+        },
+    }
+}
@@ -0,0 +1,166 @@
+//! ----- REQUEST REPORT -----
+//! This tool reads the JSON log files of the master, backup and slaves and
+//! correlates the lifecycle events of each hall request by trace id, printing
+//! latency percentiles for the stages a hall request passes through.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use chrono::{DateTime, FixedOffset};
+use clap::Parser;
+use serde_json::Value;
+
+use shared_resources::request::{Stage, LIFECYCLE_TARGET};
+
+const DEFAULT_LOG_DIRECTORIES: [&str; 3] = ["logs", "../backup/logs", "../slave/logs"];
+
+/// Intervals reported for each request, from the first time the request reached the first stage
+/// to the first time it reached the second stage
+const INTERVALS: [(&str, Stage, Stage); 4] = [
+    ("waiting time", Stage::Placed, Stage::Served),
+    ("confirmation latency", Stage::Placed, Stage::Confirmed),
+    ("backup round trip", Stage::Received, Stage::Acknowledged),
+    ("time to serve", Stage::Assigned, Stage::Served),
+];
+
+#[derive(Parser)]
+#[command(name = "request_report", about = "Reports the latency of hall requests from the logs of all nodes")]
+struct Args {
+    /// Log files or directories of log files to read [default: logs, ../backup/logs and ../slave/logs]
+    paths: Vec<PathBuf>,
+
+    /// Print the latency of every request, not only the percentiles
+    #[arg(long)]
+    requests: bool,
+}
+
+#[derive(Default)]
+struct RequestTrace {
+    floor: Option<u64>,
+    call: Option<String>,
+    stages: BTreeMap<Stage, DateTime<FixedOffset>>,
+}
+
+impl RequestTrace {
+    fn interval(&self, from: Stage, to: Stage) -> Option<f64> {
+        let duration = *self.stages.get(&to)? - *self.stages.get(&from)?;
+        Some(duration.num_microseconds()? as f64 / 1e6)
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let paths = if args.paths.is_empty() {
+        DEFAULT_LOG_DIRECTORIES.iter().map(PathBuf::from).filter(|path| path.exists()).collect()
+    } else {
+        args.paths
+    };
+
+    let mut traces: HashMap<String, RequestTrace> = HashMap::new();
+    for file_path in log_files(&paths) {
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("error: could not read {}: {}", file_path.display(), e);
+                process::exit(1);
+            },
+        };
+        for line in content.lines() {
+            read_event(line, &mut traces);
+        }
+    }
+
+    if traces.is_empty() {
+        println!("No traced hall requests found in {}", paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "));
+        return
+    }
+
+    let unserved = traces.values().filter(|trace| !trace.stages.contains_key(&Stage::Served)).count();
+    println!("{} traced hall requests, {} not served\n", traces.len(), unserved);
+    println!("{:<22} {:>7} {:>9} {:>9} {:>9} {:>9}", "INTERVAL", "COUNT", "P50", "P90", "P99", "MAX");
+    for (name, from, to) in INTERVALS {
+        let mut durations: Vec<f64> = traces.values().filter_map(|trace| trace.interval(from, to)).collect();
+        durations.sort_by(|a, b| a.total_cmp(b));
+        if durations.is_empty() {
+            println!("{:<22} {:>7}", name, 0);
+            continue;
+        }
+        println!("{:<22} {:>7} {:>8.3}s {:>8.3}s {:>8.3}s {:>8.3}s",
+            name,
+            durations.len(),
+            percentile(&durations, 50.0),
+            percentile(&durations, 90.0),
+            percentile(&durations, 99.0),
+            durations[durations.len() - 1],
+        );
+    }
+
+    if args.requests {
+        let mut sorted_traces: Vec<(&String, &RequestTrace)> = traces.iter().collect();
+        sorted_traces.sort_by_key(|(_, trace)| trace.stages.values().min().copied());
+        println!("\n{:<16} {:>5} {:<9} {}", "TRACE ID", "FLOOR", "CALL", INTERVALS.map(|(name, _, _)| format!("{:>21}", name)).join(" "));
+        for (trace_id, trace) in sorted_traces {
+            let intervals: Vec<String> = INTERVALS.iter()
+                .map(|(_, from, to)| match trace.interval(*from, *to) {
+                    Some(duration) => format!("{:>20.3}s", duration),
+                    None => format!("{:>21}", "-"),
+                })
+                .collect();
+            println!("{:<16} {:>5} {:<9} {}",
+                trace_id,
+                trace.floor.map(|floor| floor.to_string()).unwrap_or_default(),
+                trace.call.clone().unwrap_or_default(),
+                intervals.join(" "),
+            );
+        }
+    }
+}
+
+fn log_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        match fs::read_dir(path) {
+            Ok(entries) => files.extend(entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file_path| file_path.extension().is_some_and(|extension| extension == "log"))),
+            Err(_) => files.push(path.clone()),
+        }
+    }
+    files
+}
+
+/// Adds the lifecycle event logged on `line` to the trace of its request, ignoring any other lines
+fn read_event(line: &str, traces: &mut HashMap<String, RequestTrace>) {
+    let Ok(event) = serde_json::from_str::<Value>(line) else {
+        return
+    };
+    if event["target"] != LIFECYCLE_TARGET {
+        return
+    }
+    let Some(stage) = event["fields"]["stage"].as_str().and_then(Stage::parse) else {
+        return
+    };
+    let Some(timestamp) = event["timestamp"].as_str().and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok()) else {
+        return
+    };
+    let span = &event["span"];
+    let Some(trace_id) = span["trace_id"].as_str() else {
+        return
+    };
+
+    let trace = traces.entry(trace_id.to_string()).or_default();
+    trace.floor = trace.floor.or(span["floor"].as_u64());
+    trace.call = trace.call.take().or(span["call"].as_str().map(String::from));
+    let first = trace.stages.entry(stage).or_insert(timestamp);
+    if timestamp < *first {
+        *first = timestamp;
+    }
+}
+
+/// Nearest rank percentile of sorted values
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,383 @@
+//! ----- API MODULE -----
+//! This module serves a JSON API over HTTP with the current hall requests,
+//! connected elevators, assignments and the requests waiting for confirmation
+//! from the backup, as received from the network module. Operators holding the
+//! configured token may also place and cancel hall requests and take elevators
+//! out of service, which is forwarded to the network module.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+use tiny_http::{Header, Method, Request as HttpRequest, Response, Server};
+use tracing::{info, warn};
+
+use shared_resources::call::Call;
+use shared_resources::command_message::Arrival;
+use shared_resources::config::{MasterConfig, TrafficMode};
+use shared_resources::elevator_message::ServiceMode;
+use shared_resources::request::{Request, Stage, TraceId};
+
+use crate::utilities::hall_request_assigner::HRAElevState;
+use crate::utilities::operator_command::OperatorCommand;
+use crate::utilities::status::Status;
+
+/// Largest request body accepted by the command endpoints
+const MAX_BODY_SIZE: u64 = 4096;
+
+#[derive(serde::Serialize)]
+struct StatusResponse {
+    /// Seconds since the status was taken
+    age: f64,
+    hall_requests: Vec<HallRequestResponse>,
+    elevators: BTreeMap<String, ElevatorResponse>,
+    assignments: BTreeMap<String, Vec<Vec<bool>>>,
+    backup: BackupResponse,
+    out_of_service: Vec<String>,
+    /// Elevators put in independent service by an operator, the mode of each
+    /// elevator also tells whether it was switched at the car
+    independent: Vec<String>,
+    traffic_mode: TrafficMode,
+    /// Floor the elevators are recalled to, while an emergency recall is active
+    recall_floor: Option<u8>,
+}
+
+#[derive(serde::Serialize)]
+struct HallRequestResponse {
+    floor: u8,
+    call: Call,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    destination: Option<u8>,
+    trace_id: Option<TraceId>,
+    /// Priority level of the request, 0 for normal requests
+    priority: u8,
+    assigned_to: Vec<String>,
+    /// Predicted arrival of the elevator assigned the request
+    arrival: Option<Arrival>,
+}
+
+#[derive(serde::Serialize)]
+struct ElevatorResponse {
+    state: HRAElevState,
+    /// Whether each floor is served by the elevator, indexed by floor
+    served_floors: Vec<bool>,
+    /// Load of the car in kilograms
+    load: f64,
+    /// Whether the car is full, passing hall requests while it has passengers to drop off
+    full: bool,
+    mode: ServiceMode,
+    /// Seconds since the last message from the elevator
+    last_seen: f64,
+    /// Seconds since the elevator last made progress
+    last_available: f64,
+    /// Whether an operator has taken the elevator out of service
+    in_service: bool,
+    /// Whether the elevator is given hall requests
+    available: bool,
+}
+
+#[derive(serde::Serialize)]
+struct BackupResponse {
+    unconfirmed_new_requests: Vec<Request>,
+    unconfirmed_served_requests: Vec<Request>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HallRequestCommand {
+    floor: u8,
+    call: Call,
+    /// Floor the passenger is going to, only given for destination calls
+    #[serde(default)]
+    destination: Option<u8>,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RecallCommand {
+    /// Floor to recall the elevators to, the configured recall floor if left out
+    #[serde(default)]
+    floor: Option<u8>,
+}
+
+pub fn main(
+    config: MasterConfig,
+    config_rx: Receiver<MasterConfig>,
+    status_rx: Receiver<Status>,
+    operator_command_tx: Sender<OperatorCommand>,
+) {
+    if !config.api.enabled {
+        return
+    }
+    let server = match Server::http((config.api.address.as_str(), config.api.port)) {
+        Ok(server) => server,
+        Err(e) => {
+            warn!(address = config.api.address, port = config.api.port, "Could not start the API server: {}", e);
+            return
+        },
+    };
+    info!(address = config.api.address, port = config.api.port, "Serving the API");
+
+    let (http_request_tx, http_request_rx) = unbounded::<HttpRequest>();
+    thread::Builder::new().name("api_server".to_string()).spawn(move || {
+        for http_request in server.incoming_requests() {
+            if http_request_tx.send(http_request).is_err() {
+                return
+            }
+        }
+    }).unwrap();
+
+    let mut config = config;
+    let mut status: Option<Status> = None;
+    loop {
+        select! {
+            recv(status_rx) -> msg => {
+                status = Some(msg.unwrap());
+            },
+            recv(config_rx) -> msg => {
+                config = msg.unwrap();
+            },
+            recv(http_request_rx) -> msg => {
+                let mut http_request = msg.unwrap();
+                let response = match *http_request.method() {
+                    Method::Get => respond(&http_request, status.as_ref()),
+                    Method::Post => respond_to_command(&mut http_request, &config, status.as_ref(), &operator_command_tx),
+                    _ => error_response(405, "Only GET and POST requests are supported"),
+                };
+                if let Err(e) = http_request.respond(response) {
+                    warn!("Could not respond to API request: {}", e);
+                }
+            },
+        }
+    }
+}
+
+fn respond(http_request: &HttpRequest, status: Option<&Status>) -> Response<std::io::Cursor<Vec<u8>>> {
+    let Some(status) = status else {
+        return error_response(503, "No status received from the network module yet")
+    };
+    let status = status_response(status);
+    let body = match http_request.url() {
+        "/api/status" => serde_json::to_value(&status),
+        "/api/hall_requests" => serde_json::to_value(&status.hall_requests),
+        "/api/elevators" => serde_json::to_value(&status.elevators),
+        "/api/assignments" => serde_json::to_value(&status.assignments),
+        "/api/backup" => serde_json::to_value(&status.backup),
+        _ => return error_response(404, "Not found"),
+    };
+    match body {
+        Ok(body) => json_response(200, &body),
+        Err(e) => error_response(500, &e.to_string()),
+    }
+}
+
+fn respond_to_command(
+    http_request: &mut HttpRequest,
+    config: &MasterConfig,
+    status: Option<&Status>,
+    operator_command_tx: &Sender<OperatorCommand>,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let Some(token) = &config.api.token else {
+        return error_response(403, "Operator commands are disabled, no api.token is configured")
+    };
+    if !authorized(http_request, token) {
+        return error_response(401, "Missing or invalid bearer token")
+    }
+
+    let url = http_request.url().to_string();
+    let command = match url.as_str() {
+        "/api/hall_requests" | "/api/hall_requests/cancel" => {
+            let mut request = match read_hall_request(http_request, config.elevator.num_floors) {
+                Ok(request) => request,
+                Err(message) => return error_response(400, &message),
+            };
+            if url == "/api/hall_requests" {
+                request.trace_id = Some(TraceId::new());
+                request.log_stage(Stage::Placed);
+                OperatorCommand::PlaceHallRequest(request)
+            } else {
+                let Some(status) = status else {
+                    return error_response(503, "No status received from the network module yet")
+                };
+                // Judged from the latest status, as the network module does not answer the command
+                let same_request = |other: &Request| other.floor == request.floor && other.column() == request.column();
+                let active = status.hall_requests.get(request.floor as usize)
+                    .and_then(|calls| calls.get(request.column()))
+                    .copied()
+                    .unwrap_or(false);
+                if status.unconfirmed_served_requests.iter().any(same_request) {
+                    return error_response(409, "The hall request is already being cleared")
+                }
+                if !active && !status.unconfirmed_new_requests.iter().any(same_request) {
+                    return error_response(404, "The hall request is not active")
+                }
+                OperatorCommand::CancelHallRequest(request)
+            }
+        },
+        "/api/recall" => match read_recall_floor(http_request, config.elevator.num_floors) {
+            Ok(floor) => OperatorCommand::StartRecall { floor },
+            Err(message) => return error_response(400, &message),
+        },
+        "/api/recall/reset" => OperatorCommand::ResetRecall,
+        _ => {
+            let service_command = url.strip_prefix("/api/elevators/")
+                .and_then(|path| path.rsplit_once('/'))
+                .filter(|(id, _)| !id.is_empty() && !id.contains('/'));
+            match service_command {
+                Some((id, "in_service")) => OperatorCommand::SetInService { id: id.to_string(), in_service: true },
+                Some((id, "out_of_service")) => OperatorCommand::SetInService { id: id.to_string(), in_service: false },
+                Some((id, "independent")) => OperatorCommand::SetIndependent { id: id.to_string(), independent: true },
+                Some((id, "group_control")) => OperatorCommand::SetIndependent { id: id.to_string(), independent: false },
+                _ => return error_response(404, "Not found"),
+            }
+        },
+    };
+
+    info!(path = url, "Received operator command");
+    let body = match &command {
+        OperatorCommand::PlaceHallRequest(request) | OperatorCommand::CancelHallRequest(request) =>
+            serde_json::to_value(request),
+        OperatorCommand::SetInService { id, in_service } =>
+            Ok(serde_json::json!({ "id": id, "in_service": in_service })),
+        OperatorCommand::SetIndependent { id, independent } =>
+            Ok(serde_json::json!({ "id": id, "independent": independent })),
+        OperatorCommand::StartRecall { floor } => Ok(serde_json::json!({ "recall": true, "floor": floor })),
+        OperatorCommand::ResetRecall => Ok(serde_json::json!({ "recall": false })),
+    };
+    if operator_command_tx.send(command).is_err() {
+        return error_response(503, "The network module is not running")
+    }
+    match body {
+        Ok(body) => json_response(202, &body),
+        Err(e) => error_response(500, &e.to_string()),
+    }
+}
+
+/// Checks the bearer token of a request in constant time
+fn authorized(http_request: &HttpRequest, token: &str) -> bool {
+    let given = http_request.headers().iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "));
+    let Some(given) = given else {
+        return false
+    };
+    let (given, token) = (given.as_bytes(), token.as_bytes());
+    let difference = given.iter().zip(token).fold(0, |difference, (a, b)| difference | (a ^ b));
+    given.len() == token.len() && difference == 0
+}
+
+fn read_hall_request(http_request: &mut HttpRequest, num_floors: u8) -> Result<Request, String> {
+    let mut body = String::new();
+    http_request.as_reader().take(MAX_BODY_SIZE).read_to_string(&mut body).map_err(|e| e.to_string())?;
+    let command: HallRequestCommand = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    let request = Request {
+        destination: command.destination,
+        ..Request::new(command.floor, command.call)
+    };
+    request.validate(num_floors)?;
+    Ok(request)
+}
+
+/// Reads the recall floor of a recall command, an empty body recalls to the configured floor
+fn read_recall_floor(http_request: &mut HttpRequest, num_floors: u8) -> Result<Option<u8>, String> {
+    let mut body = String::new();
+    http_request.as_reader().take(MAX_BODY_SIZE).read_to_string(&mut body).map_err(|e| e.to_string())?;
+    let command: RecallCommand = if body.trim().is_empty() {
+        RecallCommand::default()
+    } else {
+        serde_json::from_str(&body).map_err(|e| e.to_string())?
+    };
+    match command.floor {
+        Some(floor) if floor >= num_floors => Err(format!("floor must be below {}", num_floors)),
+        floor => Ok(floor),
+    }
+}
+
+fn status_response(status: &Status) -> StatusResponse {
+    let mut hall_requests = Vec::new();
+    for (floor, calls) in status.hall_requests.iter().enumerate() {
+        for (column, &active) in calls.iter().enumerate() {
+            if !active {
+                continue;
+            }
+            let mut assigned_to: Vec<String> = status.assignments.iter()
+                .filter(|(_, requests)| requests.get(floor).is_some_and(|calls| calls[column]))
+                .map(|(id, _)| id.clone())
+                .collect();
+            assigned_to.sort();
+            let request = Request::from_column(floor as u8, column, status.trace_ids[floor][column]);
+            hall_requests.push(HallRequestResponse {
+                floor: request.floor,
+                call: request.call,
+                destination: request.destination,
+                trace_id: request.trace_id,
+                priority: status.priorities[floor][column],
+                assigned_to,
+                arrival: status.arrivals.get(floor).and_then(|calls| calls.get(column)).cloned().flatten(),
+            });
+        }
+    }
+
+    let now = Instant::now();
+    let elevators = status.connected_elevators.iter()
+        .map(|(id, data)| (id.clone(), ElevatorResponse {
+            state: data.state.clone(),
+            served_floors: data.served_floors.clone(),
+            load: data.load,
+            full: data.full,
+            mode: data.mode,
+            last_seen: now.duration_since(data.last_seen).as_secs_f64(),
+            last_available: now.duration_since(data.last_available).as_secs_f64(),
+            in_service: !status.out_of_service.contains(id),
+            available: now.duration_since(data.last_available) < status.slave_timeout
+                && !status.out_of_service.contains(id)
+                && !status.independent.contains(id)
+                && data.mode == ServiceMode::Normal,
+        }))
+        .collect();
+    let mut out_of_service: Vec<String> = status.out_of_service.iter().cloned().collect();
+    out_of_service.sort();
+    let mut independent: Vec<String> = status.independent.iter().cloned().collect();
+    independent.sort();
+
+    StatusResponse {
+        age: now.duration_since(status.taken).as_secs_f64(),
+        hall_requests,
+        elevators,
+        assignments: status.assignments.clone().into_iter().collect(),
+        backup: BackupResponse {
+            unconfirmed_new_requests: unique(&status.unconfirmed_new_requests),
+            unconfirmed_served_requests: unique(&status.unconfirmed_served_requests),
+        },
+        out_of_service,
+        independent,
+        traffic_mode: status.traffic_mode,
+        recall_floor: status.recall_floor,
+    }
+}
+
+/// The request buffers may hold the same request several times, only the first is kept
+fn unique(requests: &[Request]) -> Vec<Request> {
+    let mut unique_requests: Vec<Request> = Vec::new();
+    for request in requests {
+        let duplicate = unique_requests.iter()
+            .any(|other| other.floor == request.floor && other.column() == request.column());
+        if !duplicate {
+            unique_requests.push(request.clone());
+        }
+    }
+    unique_requests
+}
+
+fn json_response(status_code: u16, body: &serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_data(serde_json::to_vec_pretty(body).unwrap())
+        .with_status_code(status_code)
+        .with_header(content_type)
+}
+
+fn error_response(status_code: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status_code, &serde_json::json!({ "error": message }))
+}
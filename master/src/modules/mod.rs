@@ -4,38 +4,82 @@ use std::fs;
 use std::path::PathBuf;
 
 use crossbeam_channel::unbounded;
+use network_rust::udpnet;
+use shared_resources::cli::Cli;
 use shared_resources::config::MasterConfig;
+use shared_resources::config_watcher;
+use shared_resources::logging;
+use shared_resources::metrics;
 
+mod api;
 mod network;
 mod process_pair;
 
 use crate::utilities::debug;
 
 pub fn run() -> Result<()> {
-    let config = MasterConfig::get();
-    let num_floors = config.elevator.num_floors;
+    let cli = Cli::parse_for("master", "Collects elevator states and distributes hall requests among the elevators");
+    let config = MasterConfig::get(&cli);
+    logging::init("master", &config.node)?;
+    if let Some(path) = &cli.record {
+        udpnet::recording::record_to(path)?;
+    }
+    if let Some(path) = &cli.replay {
+        udpnet::recording::replay_from(path)?;
+    }
     let (hall_requests_tx, hall_requests_rx) = unbounded();
     let (connected_elevators_tx, connected_elevators_rx) = unbounded();
+    let (status_tx, status_rx) = unbounded();
+    let (operator_command_tx, operator_command_rx) = unbounded();
 
     let program_dir = PathBuf::from("./.");
     let program_path: String = fs::canonicalize(&program_dir).unwrap().into_os_string().into_string().unwrap();
     let process_pair_port = config.network.pp_port;
-    let process_pair_handle = thread::spawn(move || process_pair::process_pair(process_pair_port));
+    let node = config.node.clone();
+    let process_pair_handle = thread::spawn(move || process_pair::process_pair(&node, process_pair_port));
     process_pair_handle.join().unwrap();
 
-    process_pair::spawn_process_pair(program_path);
+    if cli.spawns_process_pair() {
+        process_pair::spawn_process_pair(program_path, cli.process_pair_args(), config.node.headless);
+    }
+    metrics::serve(&config.metrics);
 
-    thread::spawn(move || network::main(
-        config,
-        hall_requests_tx,
-        connected_elevators_tx,
-    ));
+    let (network_config_tx, network_config_rx) = unbounded();
+    let (debug_config_tx, debug_config_rx) = unbounded();
+    let (api_config_tx, api_config_rx) = unbounded();
+    config_watcher::watch(cli, vec![network_config_tx, debug_config_tx, api_config_tx])?;
+
+    {
+        let config = config.clone();
+        thread::spawn(move || network::main(
+            config,
+            network_config_rx,
+            hall_requests_tx,
+            connected_elevators_tx,
+            status_tx,
+            operator_command_rx,
+        ));
+    }
+
+    {
+        let config = config.clone();
+        thread::Builder::new().name("api".to_string()).spawn(move || api::main(
+            config,
+            api_config_rx,
+            status_rx,
+            operator_command_tx,
+        ))?;
+    }
     
     thread::spawn(move || debug::main(
-        num_floors,
+        config,
+        debug_config_rx,
         hall_requests_rx,
         connected_elevators_rx
     ));
 
-    loop { }
+    // The threads do the work, the main thread only has to stay alive
+    loop {
+        thread::park();
+    }
 }
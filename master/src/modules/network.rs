@@ -1,30 +1,50 @@
-/// ----- NETWORK MODULE -----
-/// This module is responsible for collecting states and hall requests from the 
-/// slave nodes and uses the hall_request_assigner algorithm to distribute orders
-/// among the connected elevators and UDP broadcasts the result. 
+//! ----- NETWORK MODULE -----
+//! This module is responsible for collecting states and hall requests from the 
+//! slave nodes and uses the hall_request_assigner algorithm to distribute orders
+//! among the connected elevators and UDP broadcasts the result. 
+//! It also carries out the operator commands received from the API module,
+//! and records the significant events in the journal.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
 use std::time::{Instant, Duration};
 use std::thread;
 use std::process;
 
-use crossbeam_channel::{unbounded, select, Sender, tick};
+use crossbeam_channel::{unbounded, select, Sender, Receiver, tick};
 
 use network_rust::udpnet;
-use shared_resources::config::MasterConfig;
+use prometheus::{register_int_counter, IntCounter};
+use tracing::{debug, info, warn};
+use shared_resources::config::{MasterConfig, PriorityConfig};
 use shared_resources::call::Call;
-use shared_resources::elevator_message::ElevatorMessage;
+use shared_resources::command_message::{Arrival, CommandMessage};
+use shared_resources::elevator_message::{ElevatorMessage, ServiceMode};
 use shared_resources::request_buffer::RequestBuffer;
-use shared_resources::request::Request;
+use shared_resources::request::{Request, Stage, TraceId, LIFECYCLE_TARGET};
 
+use crate::utilities::arrival;
 use crate::utilities::hall_request_assigner::*;
+use crate::utilities::journal::{Event, Journal};
+use crate::utilities::operator_command::OperatorCommand;
+use crate::utilities::parking;
+use crate::utilities::status::Status;
+use crate::utilities::traffic::TrafficMonitor;
+
+static REASSIGNMENTS: LazyLock<IntCounter> = LazyLock::new(|| register_int_counter!(
+    "hall_request_reassignments_total",
+    "Number of hall requests moved from one elevator to another"
+).unwrap());
 
 pub fn main(
     config: MasterConfig,
+    config_rx: Receiver<MasterConfig>,
     hall_requests_tx: Sender<Vec<Vec<bool>>>,
     connected_elevators_tx: Sender<HashMap<String, ElevatorData>>,
+    status_tx: Sender<Status>,
+    operator_command_rx: Receiver<OperatorCommand>,
 ) {
-    let (command_tx, command_rx) = unbounded::<HashMap<String, Vec<Vec<bool>>>>();
+    let (command_tx, command_rx) = unbounded::<CommandMessage>();
     for port in config.network.command_ports {
         let command_rx = command_rx.clone();
         thread::spawn(move || {
@@ -65,19 +85,49 @@ pub fn main(
         }
     }).unwrap();
 
-    const SLAVE_TIMEOUT: f64 = 4.0;
+    let mut journal = Journal::open(&config.journal, config.node.id);
+    journal.record(Event::MasterStarted);
 
-    let hra_exec_path = config.hall_request_assigner.exec_path;
+    let mut slave_timeout = Duration::from_secs_f64(config.timing.slave_timeout);
+    let mut backup_timeout = Duration::from_secs_f64(config.timing.backup_timeout);
+    let mut floor_travel_duration = config.timing.floor_travel_duration;
+    let mut door_open_duration = config.timing.door_open_duration;
+    let mut hall_request_assigner = config.hall_request_assigner;
+    let mut parking = config.parking;
+    let mut traffic = config.traffic;
+    let mut traffic_monitor = TrafficMonitor::new();
+    let mut recall = config.recall;
+    let mut priority = config.priority;
+    let mut cancel = config.cancel;
+    // A recall started by an operator lasts until an operator resets it
+    let mut operator_recall: Option<u8> = None;
+    let mut recall_floor: Option<u8> = None;
     let update_freq = Duration::from_secs_f64(0.1);
     let timer = tick(update_freq);
 
     let mut connected_elevators: HashMap<String, ElevatorData> = HashMap::new();
-    let mut hall_requests = vec![vec![false; Call::num_hall_calls() as usize]; config.elevator.num_floors as usize];
+    let hall_columns = Request::hall_columns(config.elevator.num_floors);
+    let mut hall_requests = vec![vec![false; hall_columns]; config.elevator.num_floors as usize];
     let mut output: HashMap<String, Vec<Vec<bool>>> = HashMap::new();
+    let mut arrivals: Vec<Vec<Option<Arrival>>> = Vec::new();
+    let mut trace_ids: Vec<Vec<Option<TraceId>>> = vec![vec![None; hall_columns]; config.elevator.num_floors as usize];
+    let mut priorities: Vec<Vec<u8>> = vec![vec![0; hall_columns]; config.elevator.num_floors as usize];
+    // When the backup stored each active hall request
+    let mut confirmed_at: Vec<Vec<Option<Instant>>> = vec![vec![None; hall_columns]; config.elevator.num_floors as usize];
+    let mut out_of_service: HashSet<String> = HashSet::new();
+    // Elevators an operator has put in independent service, the slaves report the mode they are in
+    let mut independent: HashSet<String> = HashSet::new();
+    let mut unavailable: HashSet<String> = HashSet::new();
+    let mut served = vec![vec![false; hall_columns]; config.elevator.num_floors as usize];
+    let mut assigner_failing = false;
+    let mut last_backup_ack = Instant::now();
+    let mut backup_silent = false;
 
-    const BUFFER_TIMEOUT: u64 = 2;
-    let mut new_hall_request_buffer = RequestBuffer::new(BUFFER_TIMEOUT);
-    let mut served_hall_request_buffer = RequestBuffer::new(BUFFER_TIMEOUT);
+    let buffer_timeout = Duration::from_secs_f64(config.timing.request_buffer_timeout);
+    let mut new_hall_request_buffer = RequestBuffer::new("master_new_hall_requests", buffer_timeout);
+    let mut served_hall_request_buffer = RequestBuffer::new("master_served_hall_requests", buffer_timeout);
+    // Cancellations received from the slaves, acknowledged until they stop sending them
+    let mut withdrawn_hall_request_buffer = RequestBuffer::new("master_withdrawn_hall_requests", buffer_timeout);
 
     loop {
         select! {
@@ -87,28 +137,58 @@ pub fn main(
                 let floor = msg.clone().unwrap().floor;
                 let direction = msg.clone().unwrap().direction;
                 let cab_requests = msg.clone().unwrap().cab_requests;
+                // Slaves that do not advertise their served floors serve every floor
+                let mut served_floors = msg.clone().unwrap().served_floors;
+                served_floors.resize(config.elevator.num_floors as usize, served_floors.is_empty());
+                let load = msg.clone().unwrap().load;
+                let full = msg.clone().unwrap().full;
+                let mode = msg.clone().unwrap().mode;
 
+                match connected_elevators.get(&id) {
+                    None => {
+                        info!(elevator = id, "Elevator connected");
+                        journal.record(Event::ElevatorJoined { elevator: id.clone() });
+                    },
+                    Some(data) if data.mode != mode => {
+                        info!(elevator = id, mode = mode.as_str(), "Elevator service mode changed");
+                        if mode == ServiceMode::Independent {
+                            journal.record(Event::ElevatorIndependent { elevator: id.clone() });
+                        } else if data.mode == ServiceMode::Independent {
+                            journal.record(Event::ElevatorGroupControl { elevator: id.clone() });
+                        }
+                    },
+                    Some(_) => {},
+                }
                 connected_elevators.entry(id.clone()).or_insert(ElevatorData{
                     state: HRAElevState { 
                         behaviour: behaviour.clone(), 
-                        floor: floor, 
+                        floor, 
                         direction: direction.clone(), 
                         cab_requests: cab_requests.clone(),
                     },
+                    served_floors: served_floors.clone(),
+                    load,
+                    full,
+                    mode,
                     last_seen: Instant::now(),
                     last_available: Instant::now(),
                 });
                 connected_elevators.insert(id.clone(), ElevatorData{
                     state: HRAElevState { 
                         behaviour: behaviour.clone(), 
-                        floor: floor, 
+                        floor, 
                         direction: direction.clone(), 
-                        cab_requests: cab_requests,
+                        cab_requests,
                     },
+                    served_floors,
+                    load,
+                    full,
+                    mode,
                     last_seen: Instant::now(),
                     last_available: 
                         if behaviour != connected_elevators[&id].state.behaviour
                             || behaviour == "idle"
+                            || mode == ServiceMode::Recalled
                             || floor != connected_elevators[&id].state.floor
                             || direction != connected_elevators[&id].state.direction
                         { Instant::now() } else { connected_elevators[&id].last_available },
@@ -116,23 +196,85 @@ pub fn main(
                 
                 if behaviour == "doorOpen" {
                     let call = if direction == "up" { Call::HallUp } else { Call::HallDown };
-                    served_hall_request_buffer.insert_new_request(Request { floor: floor, call: call });
+                    // The passengers of destination calls only board the elevator they were assigned to
+                    let picked_up: Vec<usize> = (0..hall_columns)
+                        .filter(|&column| {
+                            let request = hall_request(floor as usize, column, &trace_ids);
+                            match request.call {
+                                Call::Destination => request.hall_call() as usize == call as usize
+                                    && output.get(&id).is_some_and(|requests| requests[floor as usize][column]),
+                                _ => column == call as usize,
+                            }
+                        })
+                        .collect();
+                    for column in picked_up {
+                        let request = hall_request(floor as usize, column, &trace_ids);
+                        if hall_requests[floor as usize][column] {
+                            request.span().in_scope(|| debug!(elevator = id, "Hall request served"));
+                            // The doors stay open for a while, only the first report is journaled
+                            if !served[floor as usize][column] {
+                                served[floor as usize][column] = true;
+                                journal.record(Event::RequestServed { request: request.clone(), elevator: id.clone() });
+                            }
+                        }
+                        served_hall_request_buffer.insert_new_request(request);
+                    }
                 }
                 
                 for request in msg.clone().unwrap().new_hall_orders {
-                    new_hall_request_buffer.insert_new_request(request);
+                    if let Err(e) = request.validate(config.elevator.num_floors) {
+                        warn!(elevator = id, floor = request.floor, call = ?request.call, "Dropped invalid hall request: {}", e);
+                        continue
+                    }
+                    if recall_floor.is_some() {
+                        request.span().in_scope(|| debug!("Hall request ignored during the emergency recall"));
+                        continue;
+                    }
+                    receive_new_hall_request(request, &priority, &hall_requests, &mut trace_ids, &mut priorities, &mut new_hall_request_buffer, &mut traffic_monitor, &mut journal);
                 }
-
-                let mut states = HashMap::new();
-                for (id, data) in connected_elevators.clone() {
-                    if data.last_available.elapsed() < Duration::from_secs_f64(SLAVE_TIMEOUT) {
-                        states.insert(id, data.state);
+                for request in msg.clone().unwrap().cancelled_hall_orders {
+                    // Acknowledged even when there is nothing to cancel, so the slave stops sending it
+                    withdrawn_hall_request_buffer.remove_request(&request);
+                    withdrawn_hall_request_buffer.insert_new_request(request.clone());
+                    if let Err(e) = request.validate(config.elevator.num_floors) {
+                        warn!(elevator = id, floor = request.floor, call = ?request.call, "Dropped invalid hall request cancellation: {}", e);
+                        continue
                     }
+                    withdraw_hall_request(request, Some(&id), &hall_requests, &trace_ids, &mut new_hall_request_buffer, &mut served_hall_request_buffer, &mut journal);
                 }
-                output = match assign_orders(&hra_exec_path, hall_requests.clone(), states) {
+
+                let available: HashMap<String, ElevatorData> = connected_elevators.iter()
+                    .filter(|(id, data)| {
+                        data.last_available.elapsed() < slave_timeout
+                            && !out_of_service.contains(*id)
+                            && !independent.contains(*id)
+                            && data.mode == ServiceMode::Normal
+                    })
+                    .map(|(id, data)| (id.clone(), data.clone()))
+                    .collect();
+                // No elevator is given hall requests during a recall
+                let assigned = if recall_floor.is_some() {
+                    Ok(HashMap::new())
+                } else {
+                    assign_orders(&hall_request_assigner, traffic_monitor.mode(), traffic.lobby_floor, hall_requests.clone(), &priorities, &available)
+                };
+                let assigned = match assigned {
                     Ok(result) => result,
-                    Err(_) => continue,
+                    Err(e) => {
+                        warn!("Could not assign hall requests: {}", e);
+                        if !assigner_failing {
+                            assigner_failing = true;
+                            journal.record(Event::AssignerFailed { error: e.to_string() });
+                        }
+                        continue
+                    },
                 };
+                if assigner_failing {
+                    assigner_failing = false;
+                    journal.record(Event::AssignerRecovered);
+                }
+                record_new_assignments(&output, &assigned, &trace_ids, &mut journal);
+                output = assigned;
 
                 hall_requests_tx.send(hall_requests.clone()).unwrap();
 
@@ -142,26 +284,392 @@ pub fn main(
                 )).unwrap();
             },
             recv(backup_confirmed_orders_rx) -> msg => {
-                hall_requests = msg.unwrap();
-                new_hall_request_buffer.remove_confirmed_requests(&hall_requests);
-                let mut inverted_hall_requests = hall_requests.clone();
-                for floor in 0..config.elevator.num_floors {
-                    for call in 0..Call::num_hall_calls() {
-                        inverted_hall_requests[floor as usize][call as usize] = !inverted_hall_requests[floor as usize][call as usize];
+                let confirmed_hall_requests = msg.unwrap();
+                last_backup_ack = Instant::now();
+                if backup_silent {
+                    backup_silent = false;
+                    info!("Backup responding again");
+                    journal.record(Event::BackupResponding);
+                }
+                record_backup_changes(&hall_requests, &confirmed_hall_requests, &priority, &mut trace_ids, &mut priorities, &mut confirmed_at, &mut journal);
+                hall_requests = confirmed_hall_requests;
+                for (served_calls, calls) in served.iter_mut().zip(&hall_requests) {
+                    for (served, &active) in served_calls.iter_mut().zip(calls) {
+                        *served &= active;
                     }
                 }
+                new_hall_request_buffer.remove_confirmed_requests(&hall_requests);
+                let inverted_hall_requests: Vec<Vec<bool>> = hall_requests.iter()
+                    .map(|calls| calls.iter().map(|&active| !active).collect())
+                    .collect();
                 served_hall_request_buffer.remove_confirmed_requests(&inverted_hall_requests);
             },
             recv(timer) -> _ => {
+                withdrawn_hall_request_buffer.remove_timed_out_orders();
                 for id in connected_elevators.clone().keys() {
-                    if connected_elevators[id].last_seen.elapsed() > Duration::from_secs_f64(SLAVE_TIMEOUT) {
+                    if connected_elevators[id].last_seen.elapsed() > slave_timeout {
+                        warn!(elevator = id, "Elevator timed out");
                         connected_elevators.remove(id);
+                        unavailable.remove(id);
+                        journal.record(Event::ElevatorLeft { elevator: id.clone() });
+                    }
+                }
+                for (id, data) in &connected_elevators {
+                    let available = data.last_available.elapsed() < slave_timeout;
+                    if !available && unavailable.insert(id.clone()) {
+                        warn!(elevator = id, "Elevator unavailable, it has not changed state for too long");
+                        journal.record(Event::ElevatorUnavailable { elevator: id.clone() });
+                    } else if available && unavailable.remove(id) {
+                        info!(elevator = id, "Elevator available again");
+                        journal.record(Event::ElevatorAvailable { elevator: id.clone() });
+                    }
+                }
+                if let Some(mode) = traffic_monitor.update(&traffic) {
+                    info!(mode = mode.as_str(), "Traffic mode changed");
+                    journal.record(Event::TrafficModeChanged { mode });
+                }
+                if let Some(max_age) = cancel.max_age {
+                    let max_age = Duration::from_secs_f64(max_age);
+                    if expire_hall_requests(max_age, &hall_requests, &confirmed_at, &trace_ids, &mut served_hall_request_buffer, &mut journal) {
+                        backup_changed_requests_tx.send((
+                            new_hall_request_buffer.get_new_requests(),
+                            served_hall_request_buffer.get_new_requests()
+                        )).unwrap();
                     }
                 }
+                if !backup_silent && last_backup_ack.elapsed() > backup_timeout {
+                    backup_silent = true;
+                    warn!("Backup silent");
+                    journal.record(Event::BackupSilent);
+                }
                 connected_elevators_tx.send(connected_elevators.clone()).unwrap();
+                // The API module stops receiving if the API is disabled
+                status_tx.send(Status {
+                    taken: Instant::now(),
+                    hall_requests: hall_requests.clone(),
+                    trace_ids: trace_ids.clone(),
+                    priorities: priorities.clone(),
+                    arrivals: arrivals.clone(),
+                    connected_elevators: connected_elevators.clone(),
+                    assignments: output.clone(),
+                    unconfirmed_new_requests: new_hall_request_buffer.get_new_requests(),
+                    unconfirmed_served_requests: served_hall_request_buffer.get_new_requests(),
+                    slave_timeout,
+                    out_of_service: out_of_service.clone(),
+                    independent: independent.clone(),
+                    traffic_mode: traffic_monitor.mode(),
+                    recall_floor,
+                }).ok();
+            },
+            recv(config_rx) -> msg => {
+                let config = msg.unwrap();
+                slave_timeout = Duration::from_secs_f64(config.timing.slave_timeout);
+                backup_timeout = Duration::from_secs_f64(config.timing.backup_timeout);
+                floor_travel_duration = config.timing.floor_travel_duration;
+                door_open_duration = config.timing.door_open_duration;
+                let buffer_timeout = Duration::from_secs_f64(config.timing.request_buffer_timeout);
+                new_hall_request_buffer.set_timeout(buffer_timeout);
+                served_hall_request_buffer.set_timeout(buffer_timeout);
+                withdrawn_hall_request_buffer.set_timeout(buffer_timeout);
+                hall_request_assigner = config.hall_request_assigner;
+                parking = config.parking;
+                traffic = config.traffic;
+                recall = config.recall;
+                priority = config.priority;
+                cancel = config.cancel;
+            },
+            recv(operator_command_rx) -> msg => {
+                match msg.unwrap() {
+                    OperatorCommand::PlaceHallRequest(request) if recall_floor.is_some() => {
+                        request.span().in_scope(|| info!("Hall request placed by operator ignored during the emergency recall"));
+                    },
+                    OperatorCommand::PlaceHallRequest(request) => {
+                        receive_new_hall_request(request, &priority, &hall_requests, &mut trace_ids, &mut priorities, &mut new_hall_request_buffer, &mut traffic_monitor, &mut journal);
+                    },
+                    OperatorCommand::CancelHallRequest(request) => {
+                        withdraw_hall_request(request, None, &hall_requests, &trace_ids, &mut new_hall_request_buffer, &mut served_hall_request_buffer, &mut journal);
+                    },
+                    OperatorCommand::SetInService { id, in_service } => {
+                        if in_service && out_of_service.remove(&id) {
+                            info!(elevator = id, "Elevator put back in service by operator");
+                            journal.record(Event::ElevatorInService { elevator: id });
+                        } else if !in_service && out_of_service.insert(id.clone()) {
+                            info!(elevator = id, "Elevator taken out of service by operator");
+                            journal.record(Event::ElevatorOutOfService { elevator: id });
+                        }
+                    },
+                    OperatorCommand::SetIndependent { id, independent: true } => {
+                        if independent.insert(id.clone()) {
+                            info!(elevator = id, "Elevator put in independent service by operator");
+                        }
+                    },
+                    OperatorCommand::SetIndependent { id, independent: false } => {
+                        if independent.remove(&id) {
+                            info!(elevator = id, "Elevator returned to group control by operator");
+                        }
+                    },
+                    OperatorCommand::StartRecall { floor } => {
+                        operator_recall = Some(floor.unwrap_or(recall.floor));
+                    },
+                    OperatorCommand::ResetRecall => {
+                        operator_recall = None;
+                    },
+                }
+                // Elevator messages may be absent, so the backup is updated right away
+                backup_changed_requests_tx.send((
+                    new_hall_request_buffer.get_new_requests(),
+                    served_hall_request_buffer.get_new_requests()
+                )).unwrap();
+            },
+        }
+        let requested_recall = operator_recall.or(recall.active.then_some(recall.floor));
+        if requested_recall != recall_floor {
+            match requested_recall {
+                Some(floor) => {
+                    warn!(floor, "Emergency recall started, cancelling the hall requests");
+                    journal.record(Event::RecallStarted { floor });
+                    new_hall_request_buffer.clear();
+                },
+                None => {
+                    info!("Emergency recall reset");
+                    journal.record(Event::RecallReset);
+                },
             }
+            recall_floor = requested_recall;
+        }
+        if recall_floor.is_some() {
+            cancel_hall_requests(&hall_requests, &trace_ids, &mut served_hall_request_buffer, &mut journal);
         }
-        command_tx.send(output.clone()).unwrap();
+        // Broadcast along with the assigned hall requests, when the elevators are expected to arrive at them
+        arrivals = arrival::arrival_times(
+            config.elevator.num_floors,
+            &connected_elevators,
+            &output,
+            floor_travel_duration,
+            door_open_duration,
+        );
+        command_tx.send(CommandMessage {
+            assigned_hall_requests: output.clone(),
+            hall_requests: hall_requests.clone(),
+            withdrawn_hall_requests: withdrawn_hall_request_buffer.get_new_requests(),
+            trace_ids: trace_ids.clone(),
+            priorities: priorities.clone(),
+            arrivals: arrivals.clone(),
+            parking_floors: if parking.enabled && recall_floor.is_none() {
+                parking::parking_floors(
+                    config.elevator.num_floors,
+                    traffic.lobby_floor,
+                    traffic_monitor.mode(),
+                    &connected_elevators,
+                    &output,
+                    &out_of_service,
+                    slave_timeout,
+                )
+            } else {
+                HashMap::new()
+            },
+            recall_floor,
+            independent: independent.clone(),
+        }).unwrap();
         process_pair_tx.send(true).unwrap();
     }
 }
+
+/// Logs and journals the hall requests assigned to a new elevator, counting
+/// the ones that were previously assigned to another elevator as reassignments.
+fn record_new_assignments(
+    previous: &HashMap<String, Vec<Vec<bool>>>,
+    assigned: &HashMap<String, Vec<Vec<bool>>>,
+    trace_ids: &[Vec<Option<TraceId>>],
+    journal: &mut Journal,
+) {
+    let assigned_to = |output: &HashMap<String, Vec<Vec<bool>>>, id: &String, floor: usize, call: usize| {
+        output.get(id).is_some_and(|requests| requests.get(floor).is_some_and(|calls| calls[call]))
+    };
+    for (id, requests) in assigned {
+        for (floor, calls) in requests.iter().enumerate() {
+            for (call, &active) in calls.iter().enumerate() {
+                if !active || assigned_to(previous, id, floor, call) {
+                    continue;
+                }
+                let previous_elevator = previous.keys().find(|&other| other != id && assigned_to(previous, other, floor, call));
+                let reassigned = previous_elevator.is_some();
+                if reassigned {
+                    REASSIGNMENTS.inc();
+                }
+                let request = hall_request(floor, call, trace_ids);
+                request.span().in_scope(|| info!(
+                    target: LIFECYCLE_TARGET,
+                    stage = Stage::Assigned.as_str(),
+                    elevator = id,
+                    reassigned,
+                    "Hall request assigned"
+                ));
+                journal.record(match previous_elevator {
+                    Some(previous) => Event::RequestReassigned { request, elevator: id.clone(), previous: previous.clone() },
+                    None => Event::RequestAssigned { request, elevator: id.clone() },
+                });
+            }
+        }
+    }
+}
+
+/// Logs the hall requests stored in or cleared from the backup, journaling
+/// the stored ones and forgetting the trace ids and priorities of the cleared
+/// ones. The backup only stores whether each request is active, so requests
+/// recovered from it after a failover are given their configured priority, and
+/// their age is counted from when they were recovered.
+fn record_backup_changes(
+    previous: &[Vec<bool>],
+    confirmed: &[Vec<bool>],
+    priority: &PriorityConfig,
+    trace_ids: &mut [Vec<Option<TraceId>>],
+    priorities: &mut [Vec<u8>],
+    confirmed_at: &mut [Vec<Option<Instant>>],
+    journal: &mut Journal,
+) {
+    for (floor, calls) in confirmed.iter().enumerate() {
+        for (call, &active) in calls.iter().enumerate() {
+            let previously_active = previous.get(floor).is_some_and(|calls| calls[call]);
+            if active && !previously_active {
+                let mut request = hall_request(floor, call, trace_ids);
+                priorities[floor][call] = priorities[floor][call].max(priority.level(&request));
+                request.priority = priorities[floor][call];
+                confirmed_at[floor][call] = Some(Instant::now());
+                request.log_stage(Stage::Acknowledged);
+                journal.record(Event::RequestConfirmed { request });
+            } else if !active && previously_active {
+                hall_request(floor, call, trace_ids).span().in_scope(|| info!("Hall request cleared from backup"));
+                trace_ids[floor][call] = None;
+                priorities[floor][call] = 0;
+                confirmed_at[floor][call] = None;
+            }
+        }
+    }
+}
+
+/// Buffers a new hall request until the backup has stored it, keeping the
+/// trace id of the first request received for each floor and call. Requests
+/// that are new to the master are recorded by the traffic monitor. Requests
+/// placed by an operator or sent by a slave with an older configuration are
+/// given at least their configured priority, which is kept along with the
+/// trace id while the request is active.
+#[allow(clippy::too_many_arguments)]
+fn receive_new_hall_request(
+    mut request: Request,
+    priority: &PriorityConfig,
+    hall_requests: &[Vec<bool>],
+    trace_ids: &mut [Vec<Option<TraceId>>],
+    priorities: &mut [Vec<u8>],
+    new_hall_request_buffer: &mut RequestBuffer,
+    traffic_monitor: &mut TrafficMonitor,
+    journal: &mut Journal,
+) {
+    request.priority = request.priority.max(priority.level(&request));
+    let level = &mut priorities[request.floor as usize][request.column()];
+    *level = (*level).max(request.priority);
+    let trace_id = &mut trace_ids[request.floor as usize][request.column()];
+    if trace_id.is_none() && !hall_requests[request.floor as usize][request.column()] {
+        *trace_id = request.trace_id;
+        request.log_stage(Stage::Received);
+        traffic_monitor.record(&request);
+        journal.record(Event::RequestReceived { request: request.clone() });
+    }
+    new_hall_request_buffer.insert_new_request(request);
+}
+
+/// Cancels the hall requests during an emergency recall, which sends every
+/// elevator to the recall floor and ignores new hall requests until it is
+/// reset. Only the requests that are not being cleared from the backup already
+/// are cancelled, as the requests confirmed by the backup after a recall
+/// started are cancelled as well.
+fn cancel_hall_requests(
+    hall_requests: &[Vec<bool>],
+    trace_ids: &[Vec<Option<TraceId>>],
+    served_hall_request_buffer: &mut RequestBuffer,
+    journal: &mut Journal,
+) {
+    let cancelling = served_hall_request_buffer.get_new_requests();
+    for (floor, calls) in hall_requests.iter().enumerate() {
+        for (column, &active) in calls.iter().enumerate() {
+            let already_cancelling = cancelling.iter().any(|request| request.floor as usize == floor && request.column() == column);
+            if !active || already_cancelling {
+                continue;
+            }
+            let request = hall_request(floor, column, trace_ids);
+            request.span().in_scope(|| info!("Hall request cancelled by the emergency recall"));
+            journal.record(Event::RequestCancelled { request: request.clone() });
+            served_hall_request_buffer.insert_new_request(request);
+        }
+    }
+}
+
+/// Cancels a hall request the passenger at `elevator` no longer needs, or an
+/// operator cancels without an elevator, unless it is being cleared already.
+/// The request is cleared from the backup like a served request, or dropped
+/// if it is still waiting for the backup to store it. The slave sends the
+/// cancellation until the master acknowledges it, so it is only
+/// journaled once.
+fn withdraw_hall_request(
+    request: Request,
+    elevator: Option<&str>,
+    hall_requests: &[Vec<bool>],
+    trace_ids: &[Vec<Option<TraceId>>],
+    new_hall_request_buffer: &mut RequestBuffer,
+    served_hall_request_buffer: &mut RequestBuffer,
+    journal: &mut Journal,
+) {
+    let (floor, column) = (request.floor as usize, request.column());
+    let same_request = |buffered: &Request| buffered.floor == request.floor && buffered.column() == column;
+    let pending = new_hall_request_buffer.get_new_requests().iter().any(same_request);
+    let cancelling = served_hall_request_buffer.get_new_requests().iter().any(same_request);
+    if cancelling || !(pending || hall_requests[floor][column]) {
+        return
+    }
+    let request = hall_request(floor, column, trace_ids);
+    match elevator {
+        Some(elevator) => {
+            request.span().in_scope(|| info!(elevator, "Hall request cancelled by the passenger"));
+            journal.record(Event::RequestWithdrawn { request: request.clone(), elevator: elevator.to_string() });
+        },
+        None => {
+            request.span().in_scope(|| info!("Hall request cancelled by operator"));
+            journal.record(Event::RequestCancelled { request: request.clone() });
+        },
+    }
+    new_hall_request_buffer.remove_request(&request);
+    served_hall_request_buffer.insert_new_request(request);
+}
+
+/// Cancels the hall requests stored for longer than `max_age`, clearing them
+/// from the backup like served requests, and returns whether any were cancelled
+fn expire_hall_requests(
+    max_age: Duration,
+    hall_requests: &[Vec<bool>],
+    confirmed_at: &[Vec<Option<Instant>>],
+    trace_ids: &[Vec<Option<TraceId>>],
+    served_hall_request_buffer: &mut RequestBuffer,
+    journal: &mut Journal,
+) -> bool {
+    let cancelling = served_hall_request_buffer.get_new_requests();
+    let mut expired = false;
+    for (floor, calls) in hall_requests.iter().enumerate() {
+        for (column, &active) in calls.iter().enumerate() {
+            let too_old = confirmed_at[floor][column].is_some_and(|confirmed| confirmed.elapsed() > max_age);
+            let already_cancelling = cancelling.iter().any(|request| request.floor as usize == floor && request.column() == column);
+            if !active || !too_old || already_cancelling {
+                continue;
+            }
+            let request = hall_request(floor, column, trace_ids);
+            request.span().in_scope(|| info!(max_age = max_age.as_secs_f64(), "Hall request expired"));
+            journal.record(Event::RequestExpired { request: request.clone() });
+            served_hall_request_buffer.insert_new_request(request);
+            expired = true;
+        }
+    }
+    expired
+}
+
+fn hall_request(floor: usize, column: usize, trace_ids: &[Vec<Option<TraceId>>]) -> Request {
+    Request::from_column(floor as u8, column, trace_ids[floor][column])
+}
@@ -0,0 +1,126 @@
+//! ----- ARRIVAL -----
+//! This stateless module predicts when the elevators arrive at the hall
+//! requests assigned to them.
+
+use std::collections::{BTreeSet, HashMap};
+
+use shared_resources::command_message::Arrival;
+use shared_resources::request::Request;
+
+use crate::utilities::hall_request_assigner::{ElevatorData, HRAElevState};
+
+/// Returns the predicted arrival at each assigned hall request, indexed by
+/// floor and column. A request assigned to several elevators gets the earliest
+/// arrival, and requests not assigned to a connected elevator get none.
+pub fn arrival_times(
+    num_floors: u8,
+    connected_elevators: &HashMap<String, ElevatorData>,
+    assignments: &HashMap<String, Vec<Vec<bool>>>,
+    floor_travel_duration: f64,
+    door_open_duration: f64,
+) -> Vec<Vec<Option<Arrival>>> {
+    let mut arrivals = vec![vec![None; Request::hall_columns(num_floors)]; num_floors as usize];
+    let mut ids: Vec<&String> = assignments.keys().collect();
+    ids.sort();
+    for id in ids {
+        let Some(data) = connected_elevators.get(id) else { continue };
+        let requests = &assignments[id];
+        let stops: BTreeSet<usize> = data.state.cab_requests.iter().enumerate()
+            .filter(|(_, &active)| active)
+            .map(|(floor, _)| floor)
+            .chain(requests.iter().enumerate().filter(|(_, calls)| calls.contains(&true)).map(|(floor, _)| floor))
+            .collect();
+        for (floor, calls) in requests.iter().enumerate() {
+            for (column, &active) in calls.iter().enumerate() {
+                let Some(arrival) = arrivals.get_mut(floor).and_then(|calls| calls.get_mut(column)) else { continue };
+                if !active {
+                    continue;
+                }
+                let seconds = arrival_time(&data.state, &stops, floor, floor_travel_duration, door_open_duration);
+                if arrival.as_ref().is_none_or(|arrival: &Arrival| seconds < arrival.seconds) {
+                    *arrival = Some(Arrival { elevator: id.clone(), seconds });
+                }
+            }
+        }
+    }
+    arrivals
+}
+
+/// Seconds until the elevator opens its doors at `target`, stopping at the
+/// floors of `stops` on its way. The elevator is followed from its current
+/// state, first in its direction of travel and then turning around. Every
+/// floor travelled takes `floor_travel_duration`, and every stop on the way
+/// takes `door_open_duration`. The stops the passengers picked up on the way
+/// will ask for are not known, so the estimate is a lower bound.
+fn arrival_time(state: &HRAElevState, stops: &BTreeSet<usize>, target: usize, floor_travel_duration: f64, door_open_duration: f64) -> f64 {
+    let mut floor = state.floor as isize;
+    let mut direction: isize = match state.direction.as_str() {
+        "up" => 1,
+        "down" => -1,
+        _ => 0,
+    };
+    let mut pending: BTreeSet<isize> = stops.iter().map(|&stop| stop as isize).collect();
+    pending.insert(target as isize);
+    let mut time = 0.0;
+    match state.behaviour.as_str() {
+        "doorOpen" if floor == target as isize => return 0.0,
+        // The doors are open about half of their time at the current floor
+        "doorOpen" => {
+            pending.remove(&floor);
+            time += door_open_duration / 2.0;
+        },
+        // The car is about halfway between the last floor it passed and the next
+        "moving" if direction != 0 => {
+            floor += direction;
+            time += floor_travel_duration / 2.0;
+        },
+        _ => {},
+    }
+    loop {
+        if pending.remove(&floor) {
+            if floor == target as isize {
+                return time
+            }
+            time += door_open_duration;
+        }
+        let ahead = |direction: isize| pending.iter().any(|&stop| (stop - floor) * direction > 0);
+        if direction == 0 || !ahead(direction) {
+            direction = if ahead(1) { 1 } else { -1 };
+        }
+        floor += direction;
+        time += floor_travel_duration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(behaviour: &str, floor: u8, direction: &str) -> HRAElevState {
+        HRAElevState {
+            behaviour: behaviour.to_string(),
+            floor,
+            direction: direction.to_string(),
+            cab_requests: vec![false; 6],
+        }
+    }
+
+    #[test]
+    fn each_floor_and_stop_on_the_way_adds_to_the_arrival() {
+        let idle = state("idle", 0, "stop");
+        assert_eq!(arrival_time(&idle, &BTreeSet::new(), 3, 2.0, 3.0), 6.0);
+        assert_eq!(arrival_time(&idle, &BTreeSet::from([1, 5]), 3, 2.0, 3.0), 9.0);
+    }
+
+    #[test]
+    fn elevators_finish_their_direction_of_travel_before_turning_around() {
+        let moving_up = state("moving", 2, "up");
+        // Halfway to floor 3, then up to the stop at 5 and back down to 1
+        assert_eq!(arrival_time(&moving_up, &BTreeSet::from([5]), 1, 2.0, 3.0), 1.0 + 2.0 * 2.0 + 3.0 + 4.0 * 2.0);
+    }
+
+    #[test]
+    fn elevators_with_open_doors_at_the_request_have_arrived() {
+        assert_eq!(arrival_time(&state("doorOpen", 2, "stop"), &BTreeSet::new(), 2, 2.0, 3.0), 0.0);
+    }
+}
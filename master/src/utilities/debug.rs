@@ -1,28 +1,32 @@
-/// ----- DEBUG MODULE -----
-/// This module receives information about the elevators currently
-/// connected to the master and the active hall requests, and does 
-/// a formatted print to the console.
+//! ----- DEBUG MODULE -----
+//! This module receives information about the elevators currently
+//! connected to the master and the active hall requests, and does 
+//! a formatted print to the console, unless running headless.
 
 use std::io::{stdout, Stdout, Write};
 use std::collections::HashMap;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::{Receiver, select};
 use crossterm::{cursor, terminal, Result, ExecutableCommand};
 
 use shared_resources::call::Call;
+use shared_resources::config::MasterConfig;
 
 use crate::utilities::hall_request_assigner::ElevatorData;
 
 const STATUS_SIZE: u16 = 20;
-const TIMEOUT: u128 = 4000 as u128;
 
 pub fn main(
-    num_floors: u8,
+    config: MasterConfig,
+    config_rx: Receiver<MasterConfig>,
     hall_requests_rx: Receiver<Vec<Vec<bool>>>,
     connected_elevators_rx: Receiver<HashMap<String, ElevatorData>>,
 ) -> Result<()> {
     let mut stdout = stdout();
+    let num_floors = config.elevator.num_floors;
+    let mut headless = config.node.headless;
+    let mut slave_timeout = Duration::from_secs_f64(config.timing.slave_timeout);
 
     let mut hall_requests = vec![vec![false; Call::num_hall_calls() as usize]; num_floors as usize];
     let mut connected_elevators: HashMap<String, ElevatorData> = HashMap::new();
@@ -31,11 +35,21 @@ pub fn main(
         select! {
             recv(hall_requests_rx) -> msg => {
                 hall_requests = msg.unwrap();
-                printstatus(num_floors, &mut stdout, &hall_requests, connected_elevators.clone())?;
+                if headless { continue; }
+                printstatus(num_floors, slave_timeout, &mut stdout, &hall_requests, connected_elevators.clone())?;
             },
             recv(connected_elevators_rx) -> msg => {
                 connected_elevators = msg.unwrap();
-                printstatus(num_floors, &mut stdout, &hall_requests, connected_elevators.clone())?;
+                if headless { continue; }
+                printstatus(num_floors, slave_timeout, &mut stdout, &hall_requests, connected_elevators.clone())?;
+            },
+            recv(config_rx) -> msg => {
+                let config = msg.unwrap();
+                if config.node.headless && !headless {
+                    stdout.execute(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+                }
+                headless = config.node.headless;
+                slave_timeout = Duration::from_secs_f64(config.timing.slave_timeout);
             },
         }
     }
@@ -43,8 +57,9 @@ pub fn main(
 
 fn printstatus(
     num_floors: u8,
+    slave_timeout: Duration,
     stdout: &mut Stdout,
-    hall_requests: &Vec<Vec<bool>>,
+    hall_requests: &[Vec<bool>],
     connected_elevators: HashMap<String, ElevatorData>,
 ) -> Result<()> {
     
@@ -69,7 +84,7 @@ fn printstatus(
         writeln!(stdout, "| {0:<10} | {1:>8}ms | {2:<10} | {3:<10} | {4:<10} | {5:<10} |", 
         id, 
         Instant::now().duration_since(elev.last_seen).as_millis(),
-        if Instant::now().duration_since(elev.last_available).as_millis() > TIMEOUT { "NO" } else { "YES"},
+        if Instant::now().duration_since(elev.last_available) > slave_timeout { "NO" } else { "YES"},
         elev.state.behaviour, 
         elev.state.floor, 
         elev.state.direction)?;
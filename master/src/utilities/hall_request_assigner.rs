@@ -1,16 +1,45 @@
-/// ----- HALL REQUEST ASSIGNER -----
-/// This stateless module provides some abstraction when calling the
-/// hall_request_assigner algorithm provided. It takes elevator states and
-/// active hall requests as parameters and returns a data structure containing
-/// which elevators are to serve which orders.
+//! ----- HALL REQUEST ASSIGNER -----
+//! This stateless module provides some abstraction when calling the
+//! hall_request_assigner algorithm provided. It takes the available elevators
+//! and active hall requests as parameters and returns a data structure containing
+//! which elevators are to serve which orders, either by calling the
+//! hall_request_assigner executable or with the built-in nearest or
+//! destination strategies.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::process::Command;
+use std::sync::LazyLock;
 use std::time::Instant;
 
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+
+use shared_resources::call::Call;
+use shared_resources::config::{AssignerStrategy, HallRequestAssignerConfig, TrafficMode};
+use shared_resources::elevator_message::ServiceMode;
+use shared_resources::request::Request;
+
+static ASSIGNER_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| register_histogram_vec!(
+    "hall_request_assigner_duration_seconds",
+    "Time spent assigning hall requests",
+    &["strategy"]
+).unwrap());
+
+static ASSIGNER_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| register_int_counter_vec!(
+    "hall_request_assigner_failures_total",
+    "Number of times the hall requests could not be assigned",
+    &["strategy"]
+).unwrap());
+
 #[derive(Clone)]
 pub struct ElevatorData {
     pub state: HRAElevState,
+    /// Whether each floor is served by the elevator, indexed by floor
+    pub served_floors: Vec<bool>,
+    /// Load of the car in kilograms
+    pub load: f64,
+    /// Whether the car is full, passing hall requests while it has passengers to drop off
+    pub full: bool,
+    pub mode: ServiceMode,
     pub last_seen: Instant,
     pub last_available: Instant,
 }
@@ -31,14 +60,95 @@ pub struct HRAInput {
     pub states: HashMap<String, HRAElevState>
 }
 
+/// Assigns the active hall requests to the `elevators`, the requests of a
+/// higher priority first with any strategy. The built-in strategies adapt to
+/// the traffic `mode`, and the requests the executable gives to an elevator the
+/// traffic mode keeps for other calls are assigned again. Elevators are only given the hall requests at floors they
+/// serve, and only the destination calls to floors they serve. Full cars pass
+/// the hall requests on their way, so the built-in strategies only give them
+/// hall requests when no other elevator is close.
 pub fn assign_orders(
-    exec_path: &String,
+    config: &HallRequestAssignerConfig,
+    mode: TrafficMode,
+    lobby_floor: u8,
+    hall_requests: Vec<Vec<bool>>,
+    priorities: &[Vec<u8>],
+    elevators: &HashMap<String, ElevatorData>,
+) -> Result<HashMap<String, Vec<Vec<bool>>>, serde_json::Error> {
+    let timer = ASSIGNER_DURATION.with_label_values(&[config.strategy.as_str()]).start_timer();
+    let result = assign_by_priority(hall_requests, priorities, elevators, |hall_requests, elevators| match config.strategy {
+        AssignerStrategy::Executable => {
+            let states = elevators.iter().map(|(id, data)| (id.clone(), data.state.clone())).collect();
+            assign_orders_executable(&config.exec_path, hall_requests, states)
+                .map(|assigned| reassign(mode, lobby_floor, assigned, elevators))
+        },
+        AssignerStrategy::Nearest => Ok(assign_orders_nearest(mode, lobby_floor, hall_requests, elevators)),
+        AssignerStrategy::Destination => Ok(assign_orders_destination(mode, lobby_floor, hall_requests, elevators)),
+    });
+    timer.observe_duration();
+    if result.is_err() {
+        ASSIGNER_FAILURES.with_label_values(&[config.strategy.as_str()]).inc();
+    }
+    result
+}
+
+/// Assigns the hall requests of each priority level in turn, starting with the
+/// highest. The elevators given requests of a level are not given requests of
+/// the levels below, which are taken over by the other elevators or wait until
+/// the priority requests are served.
+fn assign_by_priority<F>(
+    hall_requests: Vec<Vec<bool>>,
+    priorities: &[Vec<u8>],
+    elevators: &HashMap<String, ElevatorData>,
+    mut assign: F,
+) -> Result<HashMap<String, Vec<Vec<bool>>>, serde_json::Error>
+where
+    F: FnMut(Vec<Vec<bool>>, &HashMap<String, ElevatorData>) -> Result<HashMap<String, Vec<Vec<bool>>>, serde_json::Error>,
+{
+    let priority = |floor: usize, column: usize| priorities.get(floor).and_then(|levels| levels.get(column)).copied().unwrap_or(0);
+    let levels: BTreeSet<u8> = hall_requests.iter().enumerate()
+        .flat_map(|(floor, calls)| calls.iter().enumerate().filter(|(_, &active)| active).map(move |(column, _)| priority(floor, column)))
+        .collect();
+    if levels.len() <= 1 {
+        return assign(hall_requests, elevators)
+    }
+
+    let mut output: HashMap<String, Vec<Vec<bool>>> = elevators.keys()
+        .map(|id| (id.clone(), hall_requests.iter().map(|calls| vec![false; calls.len()]).collect()))
+        .collect();
+    let mut remaining = elevators.clone();
+    for &level in levels.iter().rev() {
+        if remaining.is_empty() {
+            break
+        }
+        let requests: Vec<Vec<bool>> = hall_requests.iter().enumerate()
+            .map(|(floor, calls)| calls.iter().enumerate().map(|(column, &active)| active && priority(floor, column) == level).collect())
+            .collect();
+        for (id, requests) in assign(requests, &remaining)? {
+            let Some(assigned_requests) = output.get_mut(&id) else { continue };
+            if requests.iter().flatten().any(|&active| active) {
+                remaining.remove(&id);
+            }
+            for (assigned_calls, calls) in assigned_requests.iter_mut().zip(requests) {
+                for (assigned_active, active) in assigned_calls.iter_mut().zip(calls) {
+                    *assigned_active |= active;
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// The executable only knows up and down calls, so each destination call is
+/// given to the elevator assigned the call in its direction of travel.
+fn assign_orders_executable(
+    exec_path: &str,
     hall_requests: Vec<Vec<bool>>,
     states: HashMap<String, HRAElevState>
 ) -> Result<HashMap<String, Vec<Vec<bool>>>, serde_json::Error> {
     let input = HRAInput {
-        hall_requests: hall_requests,
-        states: states
+        hall_requests: up_and_down_calls(&hall_requests),
+        states
     };
     let json_arg = serde_json::to_string(&input).unwrap();
     let command = "./".to_owned() + exec_path + " -i '" + json_arg.as_str() + "'";
@@ -48,5 +158,411 @@ pub fn assign_orders(
         .output()
         .expect("failed to call hall request assigner");
     let str_result = String::from_utf8(result.stdout).unwrap();
-    serde_json::from_str(&str_result)
+    let assigned: HashMap<String, Vec<Vec<bool>>> = serde_json::from_str(&str_result)?;
+    Ok(assigned.into_iter()
+        .map(|(id, assigned_calls)| {
+            let requests = hall_requests.iter().zip(&assigned_calls).enumerate()
+                .map(|(floor, (calls, assigned_calls))| calls.iter().enumerate()
+                    .map(|(column, &active)| {
+                        let call = Request::from_column(floor as u8, column, None).hall_call();
+                        active && assigned_calls[call as usize]
+                    })
+                    .collect())
+                .collect();
+            (id, requests)
+        })
+        .collect())
+}
+
+/// Assigns each hall request to the elevator with the lowest cost of serving
+/// it, where the cost is the distance to the requested floor with a penalty
+/// for elevators moving away from it and for full cars. Ties are broken by the
+/// lowest id.
+fn assign_orders_nearest(
+    mode: TrafficMode,
+    lobby_floor: u8,
+    hall_requests: Vec<Vec<bool>>,
+    elevators: &HashMap<String, ElevatorData>,
+) -> HashMap<String, Vec<Vec<bool>>> {
+    let num_floors = hall_requests.len();
+    let mut ids: Vec<&String> = elevators.keys().collect();
+    ids.sort();
+
+    let mut output: HashMap<String, Vec<Vec<bool>>> = elevators.keys()
+        .map(|id| (id.clone(), hall_requests.iter().map(|calls| vec![false; calls.len()]).collect()))
+        .collect();
+    for (floor, calls) in hall_requests.iter().enumerate() {
+        for (column, active) in calls.iter().enumerate() {
+            if !active {
+                continue;
+            }
+            let request = Request::from_column(floor as u8, column, None);
+            let call = request.hall_call() as usize;
+            let nearest = ids.iter().filter(|id| can_serve(&elevators[**id], &request)).min_by_key(|id| {
+                let data = &elevators[**id];
+                cost(&data.state, floor, num_floors) + traffic_cost(&data.state, mode, lobby_floor, floor, call, num_floors) + load_cost(data, num_floors)
+            });
+            if let Some(id) = nearest {
+                output.get_mut(*id).unwrap()[floor][column] = true;
+            }
+        }
+    }
+    output
+}
+
+/// Assigns the up and down calls like the nearest strategy, and groups the
+/// passengers of the destination calls by where they are going. All the
+/// passengers going to the same floor in the same direction are given to the
+/// same elevator, preferring elevators that already stop at that floor, so
+/// that each elevator makes few stops.
+fn assign_orders_destination(
+    mode: TrafficMode,
+    lobby_floor: u8,
+    hall_requests: Vec<Vec<bool>>,
+    elevators: &HashMap<String, ElevatorData>,
+) -> HashMap<String, Vec<Vec<bool>>> {
+    let num_floors = hall_requests.len();
+    let num_hall_calls = Call::num_hall_calls() as usize;
+    let up_and_down: Vec<Vec<bool>> = hall_requests.iter()
+        .map(|calls| calls.iter().enumerate().map(|(column, &active)| active && column < num_hall_calls).collect())
+        .collect();
+    let mut output = assign_orders_nearest(mode, lobby_floor, up_and_down, elevators);
+
+    let mut ids: Vec<&String> = elevators.keys().collect();
+    ids.sort();
+    let mut stops: HashMap<&String, HashSet<usize>> = ids.iter()
+        .map(|&id| (id, elevators[id].state.cab_requests.iter().enumerate().filter(|(_, &active)| active).map(|(floor, _)| floor).collect()))
+        .collect();
+
+    let mut groups: BTreeMap<(usize, bool), Vec<usize>> = BTreeMap::new();
+    for (floor, calls) in hall_requests.iter().enumerate() {
+        for (destination, &active) in calls.iter().skip(num_hall_calls).enumerate() {
+            if active {
+                groups.entry((destination, destination > floor)).or_default().push(floor);
+            }
+        }
+    }
+    for ((destination, going_up), origins) in groups {
+        let call = if going_up { Call::HallUp } else { Call::HallDown } as usize;
+        // Passengers no single elevator can pick up are split between the elevators serving their floors
+        let best = |origins: &[usize], stops: &HashMap<&String, HashSet<usize>>| ids.iter()
+            .filter(|id| origins.iter().all(|&origin| {
+                can_serve(&elevators[**id], &Request::destination(origin as u8, destination as u8))
+            }))
+            .min_by_key(|id| {
+                let data = &elevators[**id];
+                let extra_stop = if stops[**id].contains(&destination) { 0 } else { num_floors };
+                let pickups: usize = origins.iter()
+                    .map(|&origin| {
+                        cost(&data.state, origin, num_floors)
+                            + traffic_cost(&data.state, mode, lobby_floor, origin, call, num_floors)
+                            + load_cost(data, num_floors)
+                    })
+                    .sum();
+                pickups + extra_stop
+            })
+            .copied();
+        let assignments: Vec<(&String, Vec<usize>)> = match best(&origins, &stops) {
+            Some(id) => vec![(id, origins)],
+            None => origins.iter()
+                .filter_map(|&origin| best(&[origin], &stops).map(|id| (id, vec![origin])))
+                .collect(),
+        };
+        for (id, origins) in assignments {
+            for origin in origins {
+                output.get_mut(id).unwrap()[origin][num_hall_calls + destination] = true;
+            }
+            stops.get_mut(id).unwrap().insert(destination);
+        }
+    }
+    output
+}
+
+/// Takes the hall requests away from the elevators that can not serve them,
+/// and from the elevators the traffic mode keeps for other calls when another
+/// elevator is not kept, and assigns them again with the nearest strategy. The
+/// executable knows neither the served floors nor the traffic mode. The
+/// requests given to full cars are assigned again to the elevators that are not
+/// full, if any of them can serve the request.
+fn reassign(
+    mode: TrafficMode,
+    lobby_floor: u8,
+    mut assigned: HashMap<String, Vec<Vec<bool>>>,
+    elevators: &HashMap<String, ElevatorData>,
+) -> HashMap<String, Vec<Vec<bool>>> {
+    let not_full: HashMap<String, ElevatorData> = elevators.iter()
+        .filter(|(_, data)| !data.full)
+        .map(|(id, data)| (id.clone(), data.clone()))
+        .collect();
+    let mut reassigned: Vec<Vec<bool>> = Vec::new();
+    let mut passed: Vec<Vec<bool>> = Vec::new();
+    for (id, requests) in assigned.iter_mut() {
+        let num_floors = requests.len();
+        reassigned.resize(num_floors, Vec::new());
+        passed.resize(num_floors, Vec::new());
+        for (floor, calls) in requests.iter_mut().enumerate() {
+            reassigned[floor].resize(calls.len(), false);
+            passed[floor].resize(calls.len(), false);
+            for (column, active) in calls.iter_mut().enumerate() {
+                let request = Request::from_column(floor as u8, column, None);
+                let call = request.hall_call() as usize;
+                let kept = |data: &ElevatorData| traffic_cost(&data.state, mode, lobby_floor, floor, call, num_floors) > 0;
+                let Some(data) = elevators.get(id) else { continue };
+                if !*active {
+                    continue
+                } else if !can_serve(data, &request) {
+                    reassigned[floor][column] = true;
+                } else if data.full && not_full.values().any(|other| can_serve(other, &request)) {
+                    passed[floor][column] = true;
+                } else if kept(data) && elevators.values().any(|other| can_serve(other, &request) && !kept(other)) {
+                    reassigned[floor][column] = true;
+                } else {
+                    continue
+                }
+                *active = false;
+            }
+        }
+    }
+    for (requests, elevators) in [(reassigned, elevators), (passed, &not_full)] {
+        if !requests.iter().flatten().any(|&active| active) {
+            continue
+        }
+        for (id, requests) in assign_orders_nearest(mode, lobby_floor, requests, elevators) {
+            let Some(assigned_requests) = assigned.get_mut(&id) else { continue };
+            for (assigned_calls, calls) in assigned_requests.iter_mut().zip(requests) {
+                for (assigned_active, active) in assigned_calls.iter_mut().zip(calls) {
+                    *assigned_active |= active;
+                }
+            }
+        }
+    }
+    assigned
+}
+
+/// Whether the elevator serves the floor of the request, and its destination
+/// if it is a destination call
+fn can_serve(data: &ElevatorData, request: &Request) -> bool {
+    let serves = |floor: u8| data.served_floors.get(floor as usize).copied().unwrap_or(false);
+    serves(request.floor) && request.destination.is_none_or(serves)
+}
+
+/// Extra cost of giving a hall request to a full car, which would pass it on
+/// its way, as if the car was moving away from the request
+fn load_cost(data: &ElevatorData, num_floors: usize) -> usize {
+    if data.full { 2 * num_floors } else { 0 }
+}
+
+/// Merges the destination calls into the up and down calls at their floors
+fn up_and_down_calls(hall_requests: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    hall_requests.iter().enumerate()
+        .map(|(floor, calls)| {
+            let mut merged = vec![false; Call::num_hall_calls() as usize];
+            for (column, &active) in calls.iter().enumerate() {
+                merged[Request::from_column(floor as u8, column, None).hall_call() as usize] |= active;
+            }
+            merged
+        })
+        .collect()
+}
+
+fn cost(state: &HRAElevState, floor: usize, num_floors: usize) -> usize {
+    let distance = (state.floor as usize).abs_diff(floor);
+    let moving_away = match state.direction.as_str() {
+        "up" => floor < state.floor as usize,
+        "down" => floor > state.floor as usize,
+        _ => false,
+    };
+    if state.behaviour == "moving" && moving_away {
+        distance + 2 * num_floors
+    } else {
+        distance
+    }
+}
+
+/// Extra cost of serving a hall request in the given traffic mode. During
+/// up-peak, elevators waiting at the lobby are kept for the up calls there.
+/// During down-peak, elevators on their way down are preferred for the down
+/// calls below them, gathering passengers on their way to the lobby.
+fn traffic_cost(state: &HRAElevState, mode: TrafficMode, lobby_floor: u8, floor: usize, call: usize, num_floors: usize) -> usize {
+    let waiting_at_lobby = state.behaviour == "idle" && state.floor == lobby_floor;
+    let lobby_up_call = floor == lobby_floor as usize && call == Call::HallUp as usize;
+    let passing_down = state.behaviour == "moving" && state.direction == "down" && floor < state.floor as usize;
+    match mode {
+        TrafficMode::UpPeak if waiting_at_lobby && !lobby_up_call => num_floors,
+        TrafficMode::DownPeak if call == Call::HallDown as usize && !passing_down => num_floors / 2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUM_FLOORS: u8 = 6;
+
+    fn elevator(floor: u8, cab_floors: &[u8]) -> ElevatorData {
+        ElevatorData {
+            state: HRAElevState {
+                behaviour: "idle".to_string(),
+                floor,
+                direction: "stop".to_string(),
+                cab_requests: (0..NUM_FLOORS).map(|cab_floor| cab_floors.contains(&cab_floor)).collect(),
+            },
+            served_floors: vec![true; NUM_FLOORS as usize],
+            load: 0.0,
+            full: false,
+            mode: ServiceMode::Normal,
+            last_seen: Instant::now(),
+            last_available: Instant::now(),
+        }
+    }
+
+    fn hall_requests(requests: &[&Request]) -> Vec<Vec<bool>> {
+        let mut hall_requests = vec![vec![false; Request::hall_columns(NUM_FLOORS)]; NUM_FLOORS as usize];
+        for request in requests {
+            hall_requests[request.floor as usize][request.column()] = true;
+        }
+        hall_requests
+    }
+
+    fn assigned(output: &HashMap<String, Vec<Vec<bool>>>, id: &str, request: &Request) -> bool {
+        output[id][request.floor as usize][request.column()]
+    }
+
+    #[test]
+    fn passengers_going_to_the_same_floor_share_an_elevator() {
+        let elevators = HashMap::from([("0".to_string(), elevator(0, &[])), ("1".to_string(), elevator(3, &[]))]);
+        // Each passenger is nearest to another elevator, but one stop at floor 5 serves both
+        let (first, second) = (Request::destination(1, 5), Request::destination(3, 5));
+        let output = assign_orders_destination(TrafficMode::InterFloor, 0, hall_requests(&[&first, &second]), &elevators);
+        assert!(assigned(&output, "1", &first) && assigned(&output, "1", &second));
+        assert!(!assigned(&output, "0", &first) && !assigned(&output, "0", &second));
+    }
+
+    #[test]
+    fn elevators_already_stopping_at_the_destination_are_preferred() {
+        let elevators = HashMap::from([("0".to_string(), elevator(0, &[])), ("1".to_string(), elevator(3, &[5]))]);
+        let request = Request::destination(1, 5);
+        let output = assign_orders_destination(TrafficMode::InterFloor, 0, hall_requests(&[&request]), &elevators);
+        assert!(assigned(&output, "1", &request));
+        assert!(!assigned(&output, "0", &request));
+    }
+
+    #[test]
+    fn up_and_down_calls_are_assigned_to_the_nearest_elevator() {
+        let elevators = HashMap::from([("0".to_string(), elevator(0, &[])), ("1".to_string(), elevator(5, &[]))]);
+        let (up, down) = (Request::new(1, Call::HallUp), Request::new(4, Call::HallDown));
+        let output = assign_orders_destination(TrafficMode::InterFloor, 0, hall_requests(&[&up, &down]), &elevators);
+        assert!(assigned(&output, "0", &up));
+        assert!(assigned(&output, "1", &down));
+    }
+
+    #[test]
+    fn hall_requests_are_only_given_to_elevators_serving_their_floors() {
+        let mut low_rise = elevator(0, &[]);
+        low_rise.served_floors = vec![true, true, true, false, false, false];
+        let elevators = HashMap::from([("0".to_string(), low_rise), ("1".to_string(), elevator(5, &[]))]);
+        let (up, destination) = (Request::new(4, Call::HallUp), Request::destination(0, 4));
+        let output = assign_orders_destination(TrafficMode::InterFloor, 0, hall_requests(&[&up, &destination]), &elevators);
+        assert!(assigned(&output, "1", &up) && assigned(&output, "1", &destination));
+    }
+
+    #[test]
+    fn requests_given_to_elevators_that_can_not_serve_them_are_assigned_again() {
+        let mut low_rise = elevator(0, &[]);
+        low_rise.served_floors = vec![true, true, true, false, false, false];
+        let elevators = HashMap::from([("0".to_string(), low_rise), ("1".to_string(), elevator(5, &[]))]);
+        let (reachable, unreachable) = (Request::new(1, Call::HallUp), Request::new(4, Call::HallDown));
+        let assigned_by_executable = HashMap::from([
+            ("0".to_string(), hall_requests(&[&reachable, &unreachable])),
+            ("1".to_string(), hall_requests(&[])),
+        ]);
+        let output = reassign(TrafficMode::InterFloor, 0, assigned_by_executable, &elevators);
+        assert!(assigned(&output, "0", &reachable));
+        assert!(!assigned(&output, "0", &unreachable));
+        assert!(assigned(&output, "1", &unreachable));
+    }
+
+    #[test]
+    fn requests_given_to_full_cars_are_assigned_again_to_the_other_elevators() {
+        let mut full = elevator(2, &[0]);
+        full.full = true;
+        let mut low_rise = elevator(5, &[]);
+        low_rise.served_floors = vec![false, false, false, true, true, true];
+        let elevators = HashMap::from([("0".to_string(), full), ("1".to_string(), low_rise)]);
+        // Only the full car serves floor 1, so it keeps the request there
+        let (passed, kept) = (Request::new(4, Call::HallDown), Request::new(1, Call::HallUp));
+        let assigned_by_executable = HashMap::from([
+            ("0".to_string(), hall_requests(&[&passed, &kept])),
+            ("1".to_string(), hall_requests(&[])),
+        ]);
+        let output = reassign(TrafficMode::InterFloor, 0, assigned_by_executable, &elevators);
+        assert!(assigned(&output, "1", &passed) && !assigned(&output, "0", &passed));
+        assert!(assigned(&output, "0", &kept));
+    }
+
+    #[test]
+    fn elevators_kept_at_the_lobby_during_up_peak_are_relieved_of_other_requests() {
+        let elevators = HashMap::from([("0".to_string(), elevator(0, &[])), ("1".to_string(), elevator(4, &[]))]);
+        let (away, lobby) = (Request::new(2, Call::HallUp), Request::new(0, Call::HallUp));
+        let assigned_by_executable = HashMap::from([
+            ("0".to_string(), hall_requests(&[&away, &lobby])),
+            ("1".to_string(), hall_requests(&[])),
+        ]);
+        let output = reassign(TrafficMode::UpPeak, 0, assigned_by_executable.clone(), &elevators);
+        assert!(assigned(&output, "1", &away) && !assigned(&output, "0", &away));
+        assert!(assigned(&output, "0", &lobby));
+        // Outside up-peak the elevator at the lobby keeps what the executable gave it
+        let output = reassign(TrafficMode::InterFloor, 0, assigned_by_executable, &elevators);
+        assert!(assigned(&output, "0", &away) && assigned(&output, "0", &lobby));
+    }
+
+    #[test]
+    fn full_cars_are_only_given_hall_requests_when_no_other_elevator_is_close() {
+        let mut full = elevator(2, &[0]);
+        full.full = true;
+        let elevators = HashMap::from([("0".to_string(), full), ("1".to_string(), elevator(5, &[]))]);
+        let request = Request::new(2, Call::HallDown);
+        let output = assign_orders_nearest(TrafficMode::InterFloor, 0, hall_requests(&[&request]), &elevators);
+        assert!(assigned(&output, "1", &request));
+        assert!(!assigned(&output, "0", &request));
+    }
+
+    #[test]
+    fn priority_requests_are_assigned_first_and_keep_their_elevator() {
+        let elevators = HashMap::from([("0".to_string(), elevator(0, &[])), ("1".to_string(), elevator(5, &[]))]);
+        let (normal, priority) = (Request::new(1, Call::HallUp), Request::new(4, Call::HallDown));
+        let mut priorities = vec![vec![0; Request::hall_columns(NUM_FLOORS)]; NUM_FLOORS as usize];
+        priorities[priority.floor as usize][priority.column()] = 1;
+        // Both requests go to elevator 1 when assigned by level, it is only free for the priority call
+        let assign_to_1 = |requests: Vec<Vec<bool>>, elevators: &HashMap<String, ElevatorData>| {
+            let id = if elevators.contains_key("1") { "1" } else { "0" };
+            Ok(HashMap::from([(id.to_string(), requests)]))
+        };
+        let output = assign_by_priority(hall_requests(&[&normal, &priority]), &priorities, &elevators, assign_to_1).unwrap();
+        assert!(assigned(&output, "1", &priority) && !assigned(&output, "1", &normal));
+        assert!(assigned(&output, "0", &normal));
+    }
+
+    #[test]
+    fn requests_of_a_single_level_are_assigned_together() {
+        let elevators = HashMap::from([("0".to_string(), elevator(0, &[]))]);
+        let requests = hall_requests(&[&Request::new(1, Call::HallUp), &Request::new(4, Call::HallDown)]);
+        let mut calls = 0;
+        let output = assign_by_priority(requests.clone(), &[], &elevators, |requests, _| {
+            calls += 1;
+            Ok(HashMap::from([("0".to_string(), requests)]))
+        }).unwrap();
+        assert_eq!(calls, 1);
+        assert_eq!(output["0"], requests);
+    }
+
+    #[test]
+    fn destination_calls_are_merged_into_the_call_in_their_direction() {
+        let requests = hall_requests(&[&Request::destination(1, 5), &Request::destination(4, 2), &Request::new(4, Call::HallUp)]);
+        let merged = up_and_down_calls(&requests);
+        assert_eq!(merged[1], [true, false]);
+        assert_eq!(merged[4], [true, true]);
+        assert!(merged[0].iter().all(|&active| !active));
+    }
 }
@@ -0,0 +1,207 @@
+//! ----- JOURNAL -----
+//! This module keeps an append-only journal of the significant events on the
+//! master, such as elevators joining and leaving and hall requests being
+//! received, assigned and served. Each event is written as one JSON object per
+//! line, and the journal is rotated when it grows too large, so what the master
+//! did can be looked up long after the logs have scrolled by.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use tracing::{info, warn};
+
+use shared_resources::config::{JournalConfig, TrafficMode};
+use shared_resources::request::Request;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The master started, or its process pair took over
+    MasterStarted,
+    ElevatorJoined { elevator: String },
+    /// The elevator timed out and was disconnected
+    ElevatorLeft { elevator: String },
+    /// The elevator has been stuck in the same state for too long, and is left out of the assignment
+    ElevatorUnavailable { elevator: String },
+    ElevatorAvailable { elevator: String },
+    ElevatorOutOfService { elevator: String },
+    ElevatorInService { elevator: String },
+    /// The elevator reported independent service, by an operator or at the car
+    ElevatorIndependent { elevator: String },
+    ElevatorGroupControl { elevator: String },
+    RequestReceived {
+        #[serde(flatten)]
+        request: Request,
+    },
+    /// The backup stored the request
+    RequestConfirmed {
+        #[serde(flatten)]
+        request: Request,
+    },
+    RequestAssigned {
+        #[serde(flatten)]
+        request: Request,
+        elevator: String,
+    },
+    RequestReassigned {
+        #[serde(flatten)]
+        request: Request,
+        elevator: String,
+        previous: String,
+    },
+    /// The elevator opened its doors at the floor of the request
+    RequestServed {
+        #[serde(flatten)]
+        request: Request,
+        elevator: String,
+    },
+    RequestCancelled {
+        #[serde(flatten)]
+        request: Request,
+    },
+    /// The passenger cancelled the request by holding its hall button at the elevator
+    RequestWithdrawn {
+        #[serde(flatten)]
+        request: Request,
+        elevator: String,
+    },
+    /// The request was stored for longer than the maximum age, and was cancelled
+    RequestExpired {
+        #[serde(flatten)]
+        request: Request,
+    },
+    AssignerFailed { error: String },
+    AssignerRecovered,
+    BackupSilent,
+    BackupResponding,
+    TrafficModeChanged { mode: TrafficMode },
+    /// Every elevator was recalled to the floor, and the hall requests were cancelled
+    RecallStarted { floor: u8 },
+    RecallReset,
+}
+
+impl Event {
+    /// The hall request the event is about, if any
+    pub fn request(&self) -> Option<&Request> {
+        match self {
+            Event::RequestReceived { request }
+            | Event::RequestConfirmed { request }
+            | Event::RequestAssigned { request, .. }
+            | Event::RequestReassigned { request, .. }
+            | Event::RequestServed { request, .. }
+            | Event::RequestCancelled { request }
+            | Event::RequestWithdrawn { request, .. }
+            | Event::RequestExpired { request } => Some(request),
+            _ => None,
+        }
+    }
+
+    /// The elevator the event is about, if any
+    pub fn elevator(&self) -> Option<&str> {
+        match self {
+            Event::ElevatorJoined { elevator }
+            | Event::ElevatorLeft { elevator }
+            | Event::ElevatorUnavailable { elevator }
+            | Event::ElevatorAvailable { elevator }
+            | Event::ElevatorOutOfService { elevator }
+            | Event::ElevatorInService { elevator }
+            | Event::ElevatorIndependent { elevator }
+            | Event::ElevatorGroupControl { elevator }
+            | Event::RequestAssigned { elevator, .. }
+            | Event::RequestReassigned { elevator, .. }
+            | Event::RequestServed { elevator, .. }
+            | Event::RequestWithdrawn { elevator, .. } => Some(elevator),
+            _ => None,
+        }
+    }
+}
+
+/// A line in the journal
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub time: DateTime<Local>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+pub struct Journal {
+    config: JournalConfig,
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl Journal {
+    /// Opens the journal of the master `id`, appending to the journal left by
+    /// a previous master. Problems with the journal are logged, and never stop
+    /// the master.
+    pub fn open(config: &JournalConfig, id: u8) -> Self {
+        let mut journal = Journal {
+            config: config.clone(),
+            path: file_path(&config.directory, id),
+            file: None,
+            size: 0,
+        };
+        if config.enabled {
+            match journal.reopen() {
+                Ok(()) => info!(path = %journal.path.display(), "Writing the journal"),
+                Err(e) => warn!(path = %journal.path.display(), "Could not open the journal: {}", e),
+            }
+        }
+        journal
+    }
+
+    pub fn record(&mut self, event: Event) {
+        if self.file.is_none() {
+            return
+        }
+        let mut line = serde_json::to_string(&Entry { time: Local::now(), event }).unwrap();
+        line.push('\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_file_size {
+            if let Err(e) = self.rotate() {
+                warn!(path = %self.path.display(), "Could not rotate the journal: {}", e);
+                self.reopen().ok();
+            }
+        }
+        if let Some(file) = &mut self.file {
+            match file.write_all(line.as_bytes()) {
+                Ok(()) => self.size += line.len() as u64,
+                Err(e) => warn!(path = %self.path.display(), "Could not write to the journal: {}", e),
+            }
+        }
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        fs::create_dir_all(&self.config.directory)?;
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Renames the current journal to `<path>.1`, shifting the older journals
+    /// one number up and deleting the ones beyond `max_files`, and starts a new
+    /// journal.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let rotated = |number: u32| PathBuf::from(format!("{}.{}", self.path.display(), number));
+        let oldest = if self.config.max_files > 1 { rotated(self.config.max_files - 1) } else { self.path.clone() };
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for number in (1..self.config.max_files).rev() {
+            let from = if number == 1 { self.path.clone() } else { rotated(number - 1) };
+            if from.exists() {
+                fs::rename(from, rotated(number))?;
+            }
+        }
+        self.reopen()
+    }
+}
+
+/// Path of the current journal of the master `id`
+pub fn file_path(directory: &Path, id: u8) -> PathBuf {
+    directory.join(format!("master-{}.jsonl", id))
+}
@@ -1,2 +1,8 @@
+pub mod arrival;
 pub mod debug;
 pub mod hall_request_assigner;
+pub mod journal;
+pub mod operator_command;
+pub mod parking;
+pub mod status;
+pub mod traffic;
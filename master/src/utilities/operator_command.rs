@@ -0,0 +1,21 @@
+//! ----- OPERATOR COMMAND -----
+//! This module holds the commands an operator can give the master through the
+//! API, which the API module forwards to the network module to be carried out.
+
+use shared_resources::request::Request;
+
+#[derive(Debug, Clone)]
+pub enum OperatorCommand {
+    /// Places a hall request as if the hall button was pressed
+    PlaceHallRequest(Request),
+    /// Clears an active hall request as if it was served, or drops it if the
+    /// backup has not stored it yet
+    CancelHallRequest(Request),
+    /// Includes or excludes an elevator when assigning hall requests
+    SetInService { id: String, in_service: bool },
+    /// Takes an elevator out of group control to serve its cab requests only, or returns it
+    SetIndependent { id: String, independent: bool },
+    /// Recalls every elevator to the floor, or to the configured recall floor
+    StartRecall { floor: Option<u8> },
+    ResetRecall,
+}
@@ -0,0 +1,159 @@
+//! ----- PARKING -----
+//! This stateless module decides where to park the elevators that have
+//! nothing to do. The idle elevators are spread across the building, so that
+//! the next hall request is likely to be close to one of them. During up-peak
+//! half of them wait at the lobby instead, and during down-peak they are spread
+//! across the floors above the lobby. Each elevator is sent to the parking floor
+//! nearest to it, without elevators crossing paths, or to the floor it serves
+//! closest to that parking floor.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use shared_resources::config::TrafficMode;
+use shared_resources::elevator_message::ServiceMode;
+
+use crate::utilities::hall_request_assigner::ElevatorData;
+
+/// Returns the floor to park each idle elevator at. An elevator is idle when
+/// it is available and in service, has no cab requests nor assigned hall
+/// requests and does not have its doors open. Elevators already on their way
+/// to a parking floor are idle as well, so that they keep their parking floor.
+pub fn parking_floors(
+    num_floors: u8,
+    lobby_floor: u8,
+    mode: TrafficMode,
+    connected_elevators: &HashMap<String, ElevatorData>,
+    assignments: &HashMap<String, Vec<Vec<bool>>>,
+    out_of_service: &HashSet<String>,
+    slave_timeout: Duration,
+) -> HashMap<String, u8> {
+    if num_floors == 0 {
+        return HashMap::new()
+    }
+    let mut idle: Vec<(&String, u8)> = connected_elevators.iter()
+        .filter(|(id, data)| {
+            data.last_available.elapsed() < slave_timeout
+                && !out_of_service.contains(*id)
+                && data.mode == ServiceMode::Normal
+                && data.state.behaviour != "doorOpen"
+                && !data.state.cab_requests.contains(&true)
+                && !assignments.get(*id).is_some_and(|requests| requests.iter().flatten().any(|&request| request))
+        })
+        .map(|(id, data)| (id, data.state.floor))
+        .collect();
+    idle.sort_by(|(id, floor), (other_id, other_floor)| floor.cmp(other_floor).then(id.cmp(other_id)));
+
+    let targets = target_floors(num_floors, lobby_floor, mode, idle.len());
+    idle.into_iter()
+        .zip(targets)
+        .map(|((id, _), target)| (id.clone(), nearest_served_floor(&connected_elevators[id].served_floors, target)))
+        .collect()
+}
+
+/// The floor served by the elevator closest to `target`, preferring the lower
+/// one on a tie, or `target` if no floor is known to be served
+fn nearest_served_floor(served_floors: &[bool], target: u8) -> u8 {
+    (0..served_floors.len() as u8)
+        .filter(|&floor| served_floors[floor as usize])
+        .min_by_key(|&floor| (floor.abs_diff(target), floor))
+        .unwrap_or(target)
+}
+
+/// Parking floors for `count` elevators in ascending order. The floors to
+/// spread over are split into one zone per elevator, and each elevator parks
+/// in the middle of its zone, rounded down towards the ground floor.
+fn target_floors(num_floors: u8, lobby_floor: u8, mode: TrafficMode, count: usize) -> Vec<u8> {
+    let at_lobby = if mode == TrafficMode::UpPeak { count.div_ceil(2) } else { 0 };
+    let lowest = if mode == TrafficMode::DownPeak && lobby_floor + 1 < num_floors { lobby_floor + 1 } else { 0 };
+    let floors = (num_floors - lowest) as usize;
+    let spread = count - at_lobby;
+    let mut targets = vec![lobby_floor; at_lobby];
+    for zone in 0..spread {
+        let middle = ((2 * zone + 1) * floors / spread).saturating_sub(1) / 2;
+        targets.push(lowest + middle as u8);
+    }
+    targets.sort();
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    use crate::utilities::hall_request_assigner::HRAElevState;
+
+    fn elevator(floor: u8, behaviour: &str, cab_requests: Vec<bool>) -> ElevatorData {
+        ElevatorData {
+            state: HRAElevState {
+                behaviour: behaviour.to_string(),
+                floor,
+                direction: "stop".to_string(),
+                cab_requests,
+            },
+            served_floors: vec![true; 4],
+            load: 0.0,
+            full: false,
+            mode: ServiceMode::Normal,
+            last_seen: Instant::now(),
+            last_available: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn elevators_are_spread_across_the_building() {
+        assert_eq!(target_floors(4, 0, TrafficMode::InterFloor, 1), [1]);
+        assert_eq!(target_floors(4, 0, TrafficMode::InterFloor, 2), [0, 2]);
+        assert_eq!(target_floors(9, 0, TrafficMode::InterFloor, 3), [1, 4, 7]);
+    }
+
+    #[test]
+    fn half_of_the_elevators_wait_at_the_lobby_during_up_peak() {
+        assert_eq!(target_floors(9, 1, TrafficMode::UpPeak, 3), [1, 1, 4]);
+        assert_eq!(target_floors(9, 1, TrafficMode::UpPeak, 1), [1]);
+    }
+
+    #[test]
+    fn elevators_are_spread_above_the_lobby_during_down_peak() {
+        assert_eq!(target_floors(5, 0, TrafficMode::DownPeak, 2), [1, 3]);
+        // With the lobby at the top floor, the whole building is used
+        assert_eq!(target_floors(4, 3, TrafficMode::DownPeak, 2), [0, 2]);
+    }
+
+    #[test]
+    fn each_idle_elevator_is_sent_to_the_nearest_parking_floor() {
+        let connected_elevators = HashMap::from([
+            ("0".to_string(), elevator(3, "idle", vec![false; 4])),
+            ("1".to_string(), elevator(0, "idle", vec![false; 4])),
+        ]);
+        let floors = parking_floors(4, 0, TrafficMode::InterFloor, &connected_elevators, &HashMap::new(), &HashSet::new(), Duration::from_secs(3));
+        assert_eq!(floors, HashMap::from([("1".to_string(), 0), ("0".to_string(), 2)]));
+    }
+
+    #[test]
+    fn busy_elevators_are_not_parked() {
+        let connected_elevators = HashMap::from([
+            ("0".to_string(), elevator(0, "idle", vec![false, true, false, false])),
+            ("1".to_string(), elevator(1, "doorOpen", vec![false; 4])),
+            ("2".to_string(), elevator(2, "idle", vec![false; 4])),
+            ("3".to_string(), elevator(3, "idle", vec![false; 4])),
+            ("4".to_string(), elevator(3, "idle", vec![false; 4])),
+            ("5".to_string(), ElevatorData { mode: ServiceMode::Recalled, ..elevator(0, "idle", vec![false; 4]) }),
+        ]);
+        let mut assigned = vec![vec![false; 2]; 4];
+        assigned[1][0] = true;
+        let assignments = HashMap::from([("2".to_string(), assigned)]);
+        let out_of_service = HashSet::from(["3".to_string()]);
+
+        let floors = parking_floors(4, 0, TrafficMode::InterFloor, &connected_elevators, &assignments, &out_of_service, Duration::from_secs(3));
+        assert_eq!(floors, HashMap::from([("4".to_string(), 1)]));
+    }
+
+    #[test]
+    fn elevators_park_at_the_nearest_floor_they_serve() {
+        assert_eq!(nearest_served_floor(&[true, false, false, true], 1), 0);
+        assert_eq!(nearest_served_floor(&[true, false, false, true], 2), 3);
+        assert_eq!(nearest_served_floor(&[], 2), 2);
+    }
+}
@@ -0,0 +1,38 @@
+//! ----- STATUS -----
+//! This module holds a snapshot of the state of the master, which the network
+//! module periodically sends to the API module to be served.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use shared_resources::command_message::Arrival;
+use shared_resources::config::TrafficMode;
+use shared_resources::request::{Request, TraceId};
+
+use crate::utilities::hall_request_assigner::ElevatorData;
+
+#[derive(Clone)]
+pub struct Status {
+    pub taken: Instant,
+    /// Hall requests confirmed by the backup
+    pub hall_requests: Vec<Vec<bool>>,
+    pub trace_ids: Vec<Vec<Option<TraceId>>>,
+    /// Priority of each active hall request, indexed by floor and column
+    pub priorities: Vec<Vec<u8>>,
+    /// Predicted arrival at each assigned hall request, indexed by floor and column
+    pub arrivals: Vec<Vec<Option<Arrival>>>,
+    pub connected_elevators: HashMap<String, ElevatorData>,
+    pub assignments: HashMap<String, Vec<Vec<bool>>>,
+    /// Requests sent to the backup that the backup has not yet stored
+    pub unconfirmed_new_requests: Vec<Request>,
+    /// Served requests sent to the backup that the backup has not yet cleared
+    pub unconfirmed_served_requests: Vec<Request>,
+    pub slave_timeout: Duration,
+    /// Elevators taken out of service by an operator
+    pub out_of_service: HashSet<String>,
+    /// Elevators put in independent service by an operator
+    pub independent: HashSet<String>,
+    pub traffic_mode: TrafficMode,
+    /// Floor the elevators are recalled to, while an emergency recall is active
+    pub recall_floor: Option<u8>,
+}
@@ -0,0 +1,193 @@
+//! ----- TRAFFIC -----
+//! This module keeps track of the recent hall requests to tell which traffic
+//! pattern the building is in. During up-peak most passengers arrive at the
+//! lobby and travel up, and during down-peak most passengers travel down to
+//! leave the building. A configured schedule overrides the detection.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, Timelike};
+
+use shared_resources::call::Call;
+use shared_resources::config::{TrafficConfig, TrafficMode};
+use shared_resources::request::Request;
+
+/// How far below `peak_share` the share may drop before a peak mode is left,
+/// so that the mode does not flip back and forth around the threshold
+const HYSTERESIS: f64 = 0.1;
+
+pub struct TrafficMonitor {
+    received: VecDeque<(Instant, u8, Call)>,
+    mode: TrafficMode,
+}
+
+impl TrafficMonitor {
+    pub fn new() -> Self {
+        TrafficMonitor {
+            received: VecDeque::new(),
+            mode: TrafficMode::InterFloor,
+        }
+    }
+
+    pub fn mode(&self) -> TrafficMode {
+        self.mode
+    }
+
+    /// Records a new hall request, destination calls count as the up or down
+    /// call in their direction of travel
+    pub fn record(&mut self, request: &Request) {
+        self.received.push_back((Instant::now(), request.floor, request.hall_call()));
+    }
+
+    /// Forgets the hall requests older than the window and updates the traffic
+    /// mode, returning the new mode if it changed.
+    pub fn update(&mut self, config: &TrafficConfig) -> Option<TrafficMode> {
+        let window = Duration::try_from_secs_f64(config.window).unwrap_or(Duration::ZERO);
+        while self.received.front().is_some_and(|(received, _, _)| received.elapsed() > window) {
+            self.received.pop_front();
+        }
+        let mode = scheduled(config, Local::now().hour() as u8)
+            .unwrap_or_else(|| if config.detection { self.detect(config) } else { TrafficMode::InterFloor });
+        if mode == self.mode {
+            return None
+        }
+        self.mode = mode;
+        Some(mode)
+    }
+
+    fn detect(&self, config: &TrafficConfig) -> TrafficMode {
+        let total = self.received.len();
+        if total < config.min_requests.max(1) {
+            return TrafficMode::InterFloor
+        }
+        let share = |matches: fn(&(Instant, u8, Call), u8) -> bool| {
+            self.received.iter().filter(|call| matches(call, config.lobby_floor)).count() as f64 / total as f64
+        };
+        let up_at_lobby = share(|&(_, floor, call), lobby_floor| floor == lobby_floor && matches!(call, Call::HallUp));
+        let down = share(|&(_, _, call), _| matches!(call, Call::HallDown));
+        let threshold = |mode| if self.mode == mode { config.peak_share - HYSTERESIS } else { config.peak_share };
+        if up_at_lobby >= threshold(TrafficMode::UpPeak) {
+            TrafficMode::UpPeak
+        } else if down >= threshold(TrafficMode::DownPeak) {
+            TrafficMode::DownPeak
+        } else {
+            TrafficMode::InterFloor
+        }
+    }
+}
+
+impl Default for TrafficMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The mode of the first schedule entry covering the hour, where an entry
+/// ending before it starts covers the hours past midnight
+fn scheduled(config: &TrafficConfig, hour: u8) -> Option<TrafficMode> {
+    config.schedule.iter()
+        .find(|entry| if entry.start < entry.end {
+            entry.start <= hour && hour < entry.end
+        } else {
+            entry.start <= hour || hour < entry.end
+        })
+        .map(|entry| entry.mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_resources::config::TrafficScheduleEntry;
+
+    fn config() -> TrafficConfig {
+        TrafficConfig {
+            lobby_floor: 0,
+            detection: true,
+            window: 300.0,
+            min_requests: 4,
+            peak_share: 0.6,
+            schedule: Vec::new(),
+        }
+    }
+
+    fn monitor_with(requests: &[(u8, Call)]) -> TrafficMonitor {
+        let mut monitor = TrafficMonitor::new();
+        for &(floor, call) in requests {
+            monitor.record(&Request::new(floor, call));
+        }
+        monitor
+    }
+
+    #[test]
+    fn up_calls_at_the_lobby_are_up_peak() {
+        let mut monitor = monitor_with(&[(0, Call::HallUp), (0, Call::HallUp), (0, Call::HallUp), (2, Call::HallDown)]);
+        assert_eq!(monitor.update(&config()), Some(TrafficMode::UpPeak));
+        assert_eq!(monitor.mode(), TrafficMode::UpPeak);
+        assert_eq!(monitor.update(&config()), None);
+    }
+
+    #[test]
+    fn down_calls_are_down_peak() {
+        let mut monitor = monitor_with(&[(3, Call::HallDown), (2, Call::HallDown), (1, Call::HallDown), (1, Call::HallUp)]);
+        assert_eq!(monitor.update(&config()), Some(TrafficMode::DownPeak));
+    }
+
+    #[test]
+    fn mixed_or_few_calls_are_inter_floor() {
+        let mut monitor = monitor_with(&[(0, Call::HallUp), (3, Call::HallDown), (1, Call::HallUp), (2, Call::HallDown)]);
+        assert_eq!(monitor.update(&config()), None);
+        assert_eq!(monitor.mode(), TrafficMode::InterFloor);
+
+        let mut monitor = monitor_with(&[(0, Call::HallUp), (0, Call::HallUp), (0, Call::HallUp)]);
+        assert_eq!(monitor.update(&config()), None);
+    }
+
+    #[test]
+    fn a_peak_is_kept_until_the_share_drops_below_the_hysteresis() {
+        let mut monitor = monitor_with(&[(0, Call::HallUp), (0, Call::HallUp), (0, Call::HallUp), (2, Call::HallDown)]);
+        monitor.update(&config());
+        // 5 of 9 is below the peak share, but within the hysteresis
+        for (floor, call) in [(0, Call::HallUp), (0, Call::HallUp), (2, Call::HallDown), (3, Call::HallDown), (1, Call::HallUp)] {
+            monitor.record(&Request::new(floor, call));
+        }
+        assert_eq!(monitor.update(&config()), None);
+        monitor.record(&Request::new(1, Call::HallUp));
+        monitor.record(&Request::new(2, Call::HallUp));
+        assert_eq!(monitor.update(&config()), Some(TrafficMode::InterFloor));
+    }
+
+    #[test]
+    fn no_mode_is_detected_without_detection() {
+        let mut monitor = monitor_with(&[(0, Call::HallUp), (0, Call::HallUp), (0, Call::HallUp), (0, Call::HallUp)]);
+        assert_eq!(monitor.update(&TrafficConfig { detection: false, ..config() }), None);
+    }
+
+    #[test]
+    fn the_first_schedule_entry_covering_the_hour_is_used() {
+        let config = TrafficConfig {
+            schedule: vec![
+                TrafficScheduleEntry { start: 7, end: 10, mode: TrafficMode::UpPeak },
+                TrafficScheduleEntry { start: 8, end: 18, mode: TrafficMode::DownPeak },
+            ],
+            ..config()
+        };
+        assert_eq!(scheduled(&config, 6), None);
+        assert_eq!(scheduled(&config, 7), Some(TrafficMode::UpPeak));
+        assert_eq!(scheduled(&config, 9), Some(TrafficMode::UpPeak));
+        assert_eq!(scheduled(&config, 10), Some(TrafficMode::DownPeak));
+        assert_eq!(scheduled(&config, 18), None);
+    }
+
+    #[test]
+    fn an_entry_ending_before_it_starts_covers_the_night() {
+        let config = TrafficConfig {
+            schedule: vec![TrafficScheduleEntry { start: 22, end: 6, mode: TrafficMode::InterFloor }],
+            ..config()
+        };
+        assert_eq!(scheduled(&config, 21), None);
+        assert_eq!(scheduled(&config, 23), Some(TrafficMode::InterFloor));
+        assert_eq!(scheduled(&config, 0), Some(TrafficMode::InterFloor));
+        assert_eq!(scheduled(&config, 6), None);
+    }
+}
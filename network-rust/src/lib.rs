@@ -1,4 +1,6 @@
 pub mod udpnet {
     pub mod bcast;
     pub mod peers;
+    pub mod recording;
+    mod sock;
 }
@@ -1,16 +1,41 @@
 use cbc::RecvError;
 use cbc::SendError;
 use crossbeam_channel as cbc;
-use log::warn;
+use prometheus::{register_int_counter_vec, IntCounterVec};
+use tracing::{trace, warn};
 use serde::Deserialize;
 use socket2::Socket;
 
+use super::recording;
+
 use std::error;
 use std::str;
 use std::io;
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+
+static PACKETS_SENT: LazyLock<IntCounterVec> = LazyLock::new(|| register_int_counter_vec!(
+    "udp_packets_sent_total",
+    "Number of packets broadcast",
+    &["port"]
+).unwrap());
+
+static PACKETS_RECEIVED: LazyLock<IntCounterVec> = LazyLock::new(|| register_int_counter_vec!(
+    "udp_packets_received_total",
+    "Number of packets received and parsed",
+    &["port"]
+).unwrap());
+
+static PACKETS_DROPPED: LazyLock<IntCounterVec> = LazyLock::new(|| register_int_counter_vec!(
+    "udp_packets_dropped_total",
+    "Number of packets that could not be sent or parsed",
+    &["port", "reason"]
+).unwrap());
 
-#[path = "./sock.rs"]
-mod sock;
+use super::sock;
+
+/// Largest payload of a UDP packet, larger messages can not be received
+const MAX_PACKET_SIZE: usize = 65507;
 
 #[derive(Debug)]
 pub enum BcError<T> {
@@ -38,35 +63,132 @@ impl<T> From<RecvError> for BcError<T> {
     }
 }
 
+/// Where the packets of a receiver come from, the network or a replayed recording
+enum Source {
+    Socket(Socket),
+    Replay(cbc::Receiver<(SocketAddr, Vec<u8>)>),
+}
+
+impl Source {
+    fn new(port: u16) -> io::Result<Self> {
+        if recording::replaying() {
+            Ok(Source::Replay(recording::replayed_packets(port).1))
+        } else {
+            Ok(Source::Socket(sock::new_rx(port)?))
+        }
+    }
+
+    /// Receives the next packet on `port` into `buf`, adding it to the recording if recording
+    fn recv_from(&self, port: u16, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let (n, addr) = match self {
+            Source::Socket(s) => {
+                let (n, addr) = s.recv_from(buf)?;
+                let addr = addr.as_std()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "sender is not an IP address"))?;
+                (n, addr)
+            },
+            Source::Replay(packets) => {
+                let (addr, payload) = packets.recv()
+                    .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+                let n = payload.len().min(buf.len());
+                buf[..n].copy_from_slice(&payload[..n]);
+                (n, addr)
+            },
+        };
+        recording::record(port, addr, &buf[..n]);
+        Ok((n, addr))
+    }
+}
+
 pub fn tx<T: serde::Serialize>(port: u16, ch: cbc::Receiver<T>, localhost: bool) -> Result<(), BcError<T>> {
+    if recording::replaying() {
+        // The replayed node must not disturb the nodes running live
+        loop {
+            ch.recv()?;
+        }
+    }
     let (s, addr) = sock::new_tx(port, localhost)?;
+    let port_label = port.to_string();
     loop {
         let data = ch.recv()?;
         let serialized = serde_json::to_string(&data).unwrap();
-        if let Err(e) = s.send_to(serialized.as_bytes(), &addr) {
-            warn!("Unable to send packet, {}", e);
+        match s.send_to(serialized.as_bytes(), &addr) {
+            Ok(n) => {
+                PACKETS_SENT.with_label_values(&[port_label.as_str()]).inc();
+                trace!(port, bytes = n, "Sent packet");
+            },
+            Err(e) => {
+                PACKETS_DROPPED.with_label_values(&[port_label.as_str(), "send"]).inc();
+                warn!(port, "Unable to send packet, {}", e);
+            },
         }
     }
 }
 
 pub fn rx<T: serde::de::DeserializeOwned>(port: u16, ch: cbc::Sender<T>) -> Result<(), BcError<T>> {
-    let s = sock::new_rx(port)?;
+    let s = Source::new(port)?;
+
+    let mut buf = vec![0; MAX_PACKET_SIZE];
+    let port_label = port.to_string();
+
+    loop {
+        match parse_packet(&s, port, &mut buf) {
+            Ok((_, d)) => {
+                PACKETS_RECEIVED.with_label_values(&[port_label.as_str()]).inc();
+                ch.send(d)?
+            },
+            Err(e) => {
+                PACKETS_DROPPED.with_label_values(&[port_label.as_str(), "parse"]).inc();
+                warn!(port, "Received bad package got error: {}", e);
+            },
+        }
+    }
+}
 
-    let mut buf = [0; 1024];
+/// Like `rx`, but also passes on the address each packet was sent from
+pub fn rx_from<T: serde::de::DeserializeOwned>(
+    port: u16,
+    ch: cbc::Sender<(SocketAddr, T)>,
+) -> Result<(), BcError<(SocketAddr, T)>> {
+    let s = Source::new(port)?;
+
+    let mut buf = vec![0; MAX_PACKET_SIZE];
+    let port_label = port.to_string();
 
     loop {
-        match parse_packet(&s, &mut buf) {
-            Ok(d) => ch.send(d)?,
-            Err(e) => warn!("Received bad package got error: {}", e),
+        match parse_packet(&s, port, &mut buf) {
+            Ok(d) => {
+                PACKETS_RECEIVED.with_label_values(&[port_label.as_str()]).inc();
+                ch.send(d)?
+            },
+            Err(e) => {
+                PACKETS_DROPPED.with_label_values(&[port_label.as_str(), "parse"]).inc();
+                warn!(port, "Received bad package got error: {}", e);
+            },
         }
     }
 }
 
+/// Like `rx_from`, but passes on the packets as received without parsing them
+pub fn rx_raw(port: u16, ch: cbc::Sender<(SocketAddr, Vec<u8>)>) -> Result<(), BcError<(SocketAddr, Vec<u8>)>> {
+    let s = Source::new(port)?;
+
+    let mut buf = vec![0; MAX_PACKET_SIZE];
+    let port_label = port.to_string();
+
+    loop {
+        let (n, addr) = s.recv_from(port, &mut buf)?;
+        PACKETS_RECEIVED.with_label_values(&[port_label.as_str()]).inc();
+        ch.send((addr, buf[..n].to_vec()))?
+    }
+}
+
 fn parse_packet<'a, T: Deserialize<'a>>(
-    s: &'_ Socket,
-    buf: &'a mut [u8; 1024],
-) -> Result<T, Box<dyn error::Error>> {
-    let n = s.recv(buf)?;
+    s: &Source,
+    port: u16,
+    buf: &'a mut [u8],
+) -> Result<(SocketAddr, T), Box<dyn error::Error>> {
+    let (n, addr) = s.recv_from(port, buf)?;
     let msg = str::from_utf8(&buf[..n])?;
-    serde_json::from_str::<T>(&msg).map_err(|e| e.into())
+    serde_json::from_str::<T>(msg).map(|d| (addr, d)).map_err(|e| e.into())
 }
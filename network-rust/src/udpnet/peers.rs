@@ -3,10 +3,9 @@ use std::str;
 use std::time;
 
 use crossbeam_channel as cbc;
-use log::error;
+use tracing::error;
 
-#[path = "./sock.rs"]
-mod sock;
+use super::sock;
 
 #[derive(Debug)]
 pub struct PeerUpdate {
@@ -34,7 +33,7 @@ pub fn tx(port: u16, id: String, tx_enable: cbc::Receiver<bool>) -> std::io::Res
             recv(ticker) -> _ => {
                 if enabled {
                     if let Err(e) = s.send_to(id.as_bytes(), &addr) {
-                        error!("Sending failed: {}", e);
+                        error!(port, "Sending failed: {}", e);
                     }
                 }
             },
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel as cbc;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// A packet received from the network, stored as one JSON object per line in a recording
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedPacket {
+    /// Seconds since the recording started
+    pub time: f64,
+    pub port: u16,
+    pub from: SocketAddr,
+    pub payload: String,
+}
+
+struct Recorder {
+    started: Instant,
+    file: Mutex<File>,
+}
+
+type ReplayedPackets = (cbc::Sender<(SocketAddr, Vec<u8>)>, cbc::Receiver<(SocketAddr, Vec<u8>)>);
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+static REPLAY: OnceLock<Mutex<HashMap<u16, ReplayedPackets>>> = OnceLock::new();
+
+/// Records every packet received by the broadcast receivers of this program to
+/// `path`, replacing any previous recording. The time of each packet is counted
+/// from when this function is called.
+pub fn record_to(path: &Path) -> io::Result<()> {
+    if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        fs::create_dir_all(directory)?;
+    }
+    let recorder = Recorder {
+        started: Instant::now(),
+        file: Mutex::new(File::create(path)?),
+    };
+    RECORDER.set(recorder).map_err(|_| io::Error::new(io::ErrorKind::AlreadyExists, "already recording"))?;
+    info!(path = %path.display(), "Recording received packets");
+    Ok(())
+}
+
+/// Feeds the packets recorded to `path` to the broadcast receivers of this program
+/// instead of receiving packets from the network, at the same time after this
+/// function is called as they were received after the recording started. The
+/// broadcast senders send nothing while replaying.
+pub fn replay_from(path: &Path) -> io::Result<()> {
+    let packets = read_recording(path)?;
+    REPLAY.set(Mutex::new(HashMap::new()))
+        .map_err(|_| io::Error::new(io::ErrorKind::AlreadyExists, "already replaying"))?;
+    info!(path = %path.display(), packets = packets.len(), "Replaying recorded packets");
+    thread::Builder::new().name("replay".to_string()).spawn(move || {
+        let started = Instant::now();
+        for packet in packets {
+            let due = started + Duration::from_secs_f64(packet.time.max(0.0));
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+            replayed_packets(packet.port).0.send((packet.from, packet.payload.into_bytes())).ok();
+        }
+        info!("Replay finished");
+    })?;
+    Ok(())
+}
+
+/// Reads a recording, ordering the packets by time
+pub fn read_recording(path: &Path) -> io::Result<Vec<RecordedPacket>> {
+    let mut packets = Vec::new();
+    for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let packet: RecordedPacket = serde_json::from_str(line).map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} line {}: {}", path.display(), index + 1, e),
+        ))?;
+        packets.push(packet);
+    }
+    packets.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(packets)
+}
+
+/// Whether the packets are replayed from a recording instead of received from the network
+pub fn replaying() -> bool {
+    REPLAY.get().is_some()
+}
+
+/// Channel of the replayed packets on `port`, which holds the packets replayed
+/// before a receiver on the port was started
+pub(super) fn replayed_packets(port: u16) -> ReplayedPackets {
+    let mut ports = REPLAY.get().expect("not replaying").lock().unwrap();
+    ports.entry(port).or_insert_with(cbc::unbounded).clone()
+}
+
+/// Adds a received packet to the recording, if recording
+pub(super) fn record(port: u16, from: SocketAddr, payload: &[u8]) {
+    let recorder = match RECORDER.get() {
+        Some(recorder) => recorder,
+        None => return,
+    };
+    let packet = RecordedPacket {
+        time: recorder.started.elapsed().as_secs_f64(),
+        port,
+        from,
+        payload: String::from_utf8_lossy(payload).into_owned(),
+    };
+    let line = serde_json::to_string(&packet).unwrap();
+    if let Err(e) = writeln!(recorder.file.lock().unwrap(), "{}", line) {
+        warn!(port, "Unable to record packet, {}", e);
+    }
+}
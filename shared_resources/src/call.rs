@@ -5,6 +5,10 @@ pub enum Call {
     HallUp = 0,
     HallDown = 1,
     Cab = 2,
+    /// Hall call entered at a destination panel, the destination floor is
+    /// carried by the request. The elevator driver has no such panel, so
+    /// these are only placed through the master's API.
+    Destination = 3,
 }
 
 impl Call {
@@ -25,14 +29,17 @@ impl Call {
         2
     }
 
-    pub fn as_elev_constant(self) -> u8 {
+    /// The button of the call, destination panels have no button lamp on the elevator
+    pub fn as_elev_constant(self) -> Option<u8> {
         match self {
-            Call::HallUp => elev::HALL_UP,
-            Call::HallDown => elev::HALL_DOWN,
-            Call::Cab => elev::CAB,
+            Call::HallUp => Some(elev::HALL_UP),
+            Call::HallDown => Some(elev::HALL_DOWN),
+            Call::Cab => Some(elev::CAB),
+            Call::Destination => None,
         }
     }
 
+    /// The calls of the buttons on the elevator
     pub fn iter() -> impl Iterator<Item = Call> {
         [Call::HallUp, Call::HallDown, Call::Cab].iter().copied()
     }
@@ -0,0 +1,182 @@
+//! ----- CLI -----
+//! This module parses the command line arguments shared by the master,
+//! backup and slave binaries. Usage errors are reported by clap, which
+//! exits with a non-zero exit code.
+
+use std::path::PathBuf;
+
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+
+#[derive(ValueEnum, serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Start as the process pair backup and take over when the primary goes silent
+    Backup,
+    /// Take over immediately without waiting for a running primary
+    Primary,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Backup => "backup",
+            Role::Primary => "primary",
+        }
+    }
+}
+
+#[derive(ValueEnum, serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// Validate the configuration and print the configuration resolved for this node
+    Check,
+}
+
+/// Options selecting the configuration files, shared by every binary that reads the configuration
+#[derive(Args, Debug, Clone, Default)]
+pub struct ConfigArgs {
+    /// Path to the configuration file shared by all nodes [default: ../config.json, falling back to ../_config.json]
+    #[arg(long = "config", value_name = "PATH")]
+    pub file: Option<PathBuf>,
+
+    /// Path to a node specific configuration file, overriding the shared configuration file
+    #[arg(long = "node-config", value_name = "PATH")]
+    pub node_file: Option<PathBuf>,
+
+    /// Print every configuration value along with where it was set, and exit
+    #[arg(long = "print-config")]
+    pub print: bool,
+}
+
+#[derive(Parser, Debug, Clone, Default)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// Node number, selects what ports to use [default: 0]
+    #[arg(long, alias = "num", value_name = "ID")]
+    pub id: Option<u8>,
+
+    /// Port of the elevator server, only used by the slave [default: port from the configuration file]
+    #[arg(long, alias = "serverport", value_name = "PORT")]
+    pub server_port: Option<u16>,
+
+    /// Process pair role to start in [default: backup]
+    #[arg(long, value_enum)]
+    pub role: Option<Role>,
+
+    /// Disable the terminal status view and spawn the process pair without a terminal window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Most verbose level of messages to print [default: info]
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    /// Record every packet received from the network to this file, one JSON object per line
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Receive the packets recorded to this file instead of receiving from the network, and send nothing.
+    /// The process pair is not spawned
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+}
+
+impl From<ConfigArgs> for Cli {
+    /// Command line of a program that only reads the configuration, such as the dashboard
+    fn from(config: ConfigArgs) -> Self {
+        Cli { config, ..Cli::default() }
+    }
+}
+
+impl Cli {
+    /// Parses the command line arguments of the binary `name`, printing help
+    /// or usage errors and exiting if requested or the arguments are invalid.
+    pub fn parse_for(name: &'static str, about: &'static str) -> Self {
+        let matches = Cli::command()
+            .name(name)
+            .about(about)
+            .get_matches();
+        Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+    }
+
+    /// Whether this program spawns its process pair. A replayed node runs on its own,
+    /// as its process pair would take over from it as soon as the replay ends and it goes silent.
+    pub fn spawns_process_pair(&self) -> bool {
+        self.replay.is_none()
+    }
+
+    /// Arguments to start this binary's process pair with. The process pair
+    /// always starts as backup, regardless of this program's role, and reads
+    /// the same configuration layers as this program. It neither records nor
+    /// replays, so a process pair taking over does not replace the recording.
+    pub fn process_pair_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(file) = &self.config.file {
+            args.push("--config".to_string());
+            args.push(file.display().to_string());
+        }
+        if let Some(node_file) = &self.config.node_file {
+            args.push("--node-config".to_string());
+            args.push(node_file.display().to_string());
+        }
+        if let Some(id) = self.id {
+            args.push("--id".to_string());
+            args.push(id.to_string());
+        }
+        if let Some(server_port) = self.server_port {
+            args.push("--server-port".to_string());
+            args.push(server_port.to_string());
+        }
+        args.push("--role".to_string());
+        args.push(Role::Backup.as_str().to_string());
+        if self.headless {
+            args.push("--headless".to_string());
+        }
+        if let Some(log_level) = self.log_level {
+            args.push("--log-level".to_string());
+            args.push(log_level.as_str().to_string());
+        }
+        args
+    }
+}
+
+/// Quotes `arg` for a POSIX shell, which passes it on as a single argument
+/// whatever spaces or special characters it holds
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
@@ -0,0 +1,50 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::request::{Request, TraceId};
+
+/// Predicted arrival of the elevator assigned a hall request
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Arrival {
+    pub elevator: String,
+    /// Seconds until the elevator opens its doors at the floor of the request
+    pub seconds: f64,
+}
+
+/// Message broadcast from the master to the slaves with the active hall
+/// requests and those assigned to each elevator, keyed by elevator number,
+/// when the elevators are expected to arrive at them and where to park the
+/// elevators that have nothing to do. During an emergency recall no hall
+/// requests are assigned, and every elevator travels to the recall floor.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct CommandMessage {
+    /// Hall requests assigned to each elevator, indexed by floor and column
+    /// as given by `Request::column`
+    pub assigned_hall_requests: HashMap<String, Vec<Vec<bool>>>,
+    /// Every active hall request, indexed by floor and column, including
+    /// those waiting for an elevator while the elevators serve priority calls
+    #[serde(default)]
+    pub hall_requests: Vec<Vec<bool>>,
+    /// Hall requests cancelled by their passengers that the master has handled
+    /// lately, acknowledging the cancellations to the slaves sending them
+    #[serde(default)]
+    pub withdrawn_hall_requests: Vec<Request>,
+    /// Trace id of each active hall request, indexed by floor and column
+    pub trace_ids: Vec<Vec<Option<TraceId>>>,
+    /// Priority of each active hall request, indexed by floor and column
+    #[serde(default)]
+    pub priorities: Vec<Vec<u8>>,
+    /// Predicted arrival at each assigned hall request, indexed by floor and column
+    #[serde(default)]
+    pub arrivals: Vec<Vec<Option<Arrival>>>,
+    /// Floor to park each idle elevator at. Parking has a lower priority than
+    /// any request, and is abandoned as soon as the elevator gets one
+    #[serde(default)]
+    pub parking_floors: HashMap<String, u8>,
+    /// Floor every elevator is recalled to, while an emergency recall is active
+    #[serde(default)]
+    pub recall_floor: Option<u8>,
+    /// Elevators an operator has put in independent service, serving their
+    /// cab requests only
+    #[serde(default)]
+    pub independent: HashSet<String>,
+}
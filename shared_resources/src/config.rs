@@ -1,116 +1,1317 @@
-/// ----- CONFIG -----
-/// This module reads the configuration file to gather what ports
-/// to use and the number of floors.
+//! ----- CONFIG -----
+//! This module reads the configuration to gather what ports to use and
+//! the number of floors. The configuration is layered, where each layer
+//! overrides the previous one:
+//!  1. built-in defaults
+//!  2. the configuration file shared by all nodes
+//!  3. an optional node specific configuration file
+//!  4. `ELEV_<SECTION>_<KEY>` environment variables
+//!  5. command line arguments
+//!
+//! The merged configuration is validated before any node configuration is
+//! resolved from it, and the origin of every value is kept for `--print-config`.
 
+use std::fmt;
 use std::fs;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process;
+
+use serde_json::Value;
+
+use crate::call::Call;
+use crate::cli::{Cli, Command, ConfigArgs, ConfigCommand, LogLevel, Role};
+use crate::logging;
+use crate::request::Request;
+
+const DEFAULT_FILE_PATH: &str = "../config.json";
+const FALLBACK_FILE_PATH: &str = "../_config.json";
+const ENV_PREFIX: &str = "ELEV_";
+/// Settings that are never printed
+const SECRET_SETTINGS: [&str; 1] = ["api.token"];
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfigFile {
+    pub update_ports: Vec<u16>,
+    pub command_ports: Vec<u16>,
+    pub slave_pp_update_ports: Vec<u16>,
+    pub backup_update_ports: Vec<u16>,
+    pub backup_ack_ports: Vec<u16>,
+    pub master_pp_ports: Vec<u16>,
+    pub backup_pp_ports: Vec<u16>,
+    /// If set, all ports are derived from this port and the port lists above are ignored
+    pub base_port: Option<u16>,
+    /// Number of ports reserved for each kind of port when deriving ports, defaults to
+    /// the number of elevators rounded up to the nearest multiple of 10
+    pub block_size: Option<u16>,
+}
+
+impl Default for NetworkConfigFile {
+    fn default() -> Self {
+        NetworkConfigFile {
+            update_ports: vec![19730, 19731, 19732],
+            command_ports: vec![19740, 19741, 19742],
+            slave_pp_update_ports: vec![19750, 19751, 19752],
+            backup_update_ports: vec![19770],
+            backup_ack_ports: vec![19780],
+            master_pp_ports: vec![19790],
+            backup_pp_ports: vec![19800],
+            base_port: None,
+            block_size: None,
+        }
+    }
+}
+
+/// The kinds of ports used by the nodes, along with the block each kind
+/// is given when ports are derived from a base port. Block 3 is unused,
+/// which keeps the derived ports equal to the default port lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortKind {
+    Update = 0,
+    Command = 1,
+    SlavePPUpdate = 2,
+    BackupUpdate = 4,
+    BackupAck = 5,
+    MasterPP = 6,
+    BackupPP = 7,
+}
+
+impl PortKind {
+    pub fn iter() -> impl Iterator<Item = PortKind> {
+        [
+            PortKind::Update,
+            PortKind::Command,
+            PortKind::SlavePPUpdate,
+            PortKind::BackupUpdate,
+            PortKind::BackupAck,
+            PortKind::MasterPP,
+            PortKind::BackupPP,
+        ].iter().copied()
+    }
+
+    /// Name of the port list holding this kind of port in the configuration file
+    pub fn list_name(self) -> &'static str {
+        match self {
+            PortKind::Update => "update_ports",
+            PortKind::Command => "command_ports",
+            PortKind::SlavePPUpdate => "slave_pp_update_ports",
+            PortKind::BackupUpdate => "backup_update_ports",
+            PortKind::BackupAck => "backup_ack_ports",
+            PortKind::MasterPP => "master_pp_ports",
+            PortKind::BackupPP => "backup_pp_ports",
+        }
+    }
+
+    /// Whether each elevator has its own port of this kind, or a single port is shared
+    pub fn per_elevator(self) -> bool {
+        matches!(self, PortKind::Update | PortKind::Command | PortKind::SlavePPUpdate)
+    }
+}
+
+/// The ports of every node, resolved from the network configuration
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct PortTable {
+    pub update_ports: Vec<u16>,
+    pub command_ports: Vec<u16>,
+    pub slave_pp_update_ports: Vec<u16>,
+    pub backup_update_port: u16,
+    pub backup_ack_port: u16,
+    pub master_pp_port: u16,
+    pub backup_pp_port: u16,
+}
+
+impl PortTable {
+    pub fn ports(&self, kind: PortKind) -> Vec<u16> {
+        match kind {
+            PortKind::Update => self.update_ports.clone(),
+            PortKind::Command => self.command_ports.clone(),
+            PortKind::SlavePPUpdate => self.slave_pp_update_ports.clone(),
+            PortKind::BackupUpdate => vec![self.backup_update_port],
+            PortKind::BackupAck => vec![self.backup_ack_port],
+            PortKind::MasterPP => vec![self.master_pp_port],
+            PortKind::BackupPP => vec![self.backup_pp_port],
+        }
+    }
+}
+
+impl NetworkConfigFile {
+    fn port_list(&self, kind: PortKind) -> &Vec<u16> {
+        match kind {
+            PortKind::Update => &self.update_ports,
+            PortKind::Command => &self.command_ports,
+            PortKind::SlavePPUpdate => &self.slave_pp_update_ports,
+            PortKind::BackupUpdate => &self.backup_update_ports,
+            PortKind::BackupAck => &self.backup_ack_ports,
+            PortKind::MasterPP => &self.master_pp_ports,
+            PortKind::BackupPP => &self.backup_pp_ports,
+        }
+    }
+
+    fn block_size(&self, num_elevators: u8) -> u32 {
+        match self.block_size {
+            Some(block_size) => block_size as u32,
+            None => (num_elevators as u32).div_ceil(10).max(1) * 10,
+        }
+    }
+
+    /// Resolves the ports of all nodes, either from the port lists or derived from
+    /// `base_port` as `base_port + block * block_size + elevator number`.
+    pub fn port_table(&self, num_elevators: u8) -> Result<PortTable, Vec<ConfigProblem>> {
+        let mut problems = Vec::new();
+        let mut resolved: Vec<Vec<u16>> = Vec::new();
+
+        for kind in PortKind::iter() {
+            let required = if kind.per_elevator() { num_elevators as usize } else { 1 };
+            let ports = match self.base_port {
+                Some(base_port) => {
+                    let block_size = self.block_size(num_elevators);
+                    let block_start = base_port as u32 + kind as u32 * block_size;
+                    let ports: Vec<u32> = (0..required as u32).map(|index| block_start + index).collect();
+                    match ports.iter().find(|&&port| port > u16::MAX as u32) {
+                        Some(&port) => {
+                            problems.push(ConfigProblem::PortOutOfRange { list: kind.list_name(), port });
+                            Vec::new()
+                        },
+                        None => ports.into_iter().map(|port| port as u16).collect(),
+                    }
+                },
+                None => {
+                    let ports = self.port_list(kind);
+                    if ports.len() < required {
+                        problems.push(ConfigProblem::TooFewPorts {
+                            list: kind.list_name(),
+                            required,
+                            provided: ports.len(),
+                        });
+                    }
+                    ports.iter().take(required).copied().collect()
+                },
+            };
+            resolved.push(ports);
+        }
+        if let Some(block_size) = self.block_size {
+            if self.base_port.is_some() && block_size < num_elevators as u16 {
+                problems.push(ConfigProblem::BlockTooSmall { block_size, num_elevators });
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        Ok(PortTable {
+            update_ports: resolved[0].clone(),
+            command_ports: resolved[1].clone(),
+            slave_pp_update_ports: resolved[2].clone(),
+            backup_update_port: resolved[3][0],
+            backup_ack_port: resolved[4][0],
+            master_pp_port: resolved[5][0],
+            backup_pp_port: resolved[6][0],
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfigFile {
+    pub port: u16,
+}
+
+impl Default for ServerConfigFile {
+    fn default() -> Self {
+        ServerConfigFile { port: 15657 }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ElevatorConfigFile {
+    pub num_floors: u8,
+    pub num_elevators: u8,
+    /// Floors served by each elevator, keyed by elevator number. Elevators
+    /// left out serve every floor.
+    pub served_floors: BTreeMap<u8, Vec<u8>>,
+    /// If set, seconds within which the cab buttons of the bottom and top
+    /// floors must both be pressed to switch independent service at the car
+    pub independent_combination_window: Option<f64>,
+}
+
+impl Default for ElevatorConfigFile {
+    fn default() -> Self {
+        ElevatorConfigFile {
+            num_floors: 4,
+            num_elevators: 3,
+            served_floors: BTreeMap::new(),
+            independent_combination_window: None,
+        }
+    }
+}
+
+impl ElevatorConfigFile {
+    /// Whether each floor is served by the given elevator, indexed by floor
+    pub fn served_floors(&self, elevnum: u8) -> Vec<bool> {
+        match self.served_floors.get(&elevnum) {
+            Some(floors) => (0..self.num_floors).map(|floor| floors.contains(&floor)).collect(),
+            None => vec![true; self.num_floors as usize],
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssignerStrategy {
+    /// Call the hall_request_assigner executable
+    Executable,
+    /// Assign each hall request to the elevator closest to serving it
+    Nearest,
+    /// Like nearest, but give the passengers of destination calls going to
+    /// the same floor to the same elevator
+    Destination,
+}
+
+impl AssignerStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssignerStrategy::Executable => "executable",
+            AssignerStrategy::Nearest => "nearest",
+            AssignerStrategy::Destination => "destination",
+        }
+    }
+}
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
 pub struct HRAConfigFile {
-    exec_folder_path: String,
-    operating_systems: HashMap<String, String>,
+    pub strategy: AssignerStrategy,
+    pub exec_folder_path: String,
+    pub operating_systems: HashMap<String, String>,
+}
+
+impl Default for HRAConfigFile {
+    fn default() -> Self {
+        HRAConfigFile {
+            strategy: AssignerStrategy::Executable,
+            exec_folder_path: String::from("hra_execs/"),
+            operating_systems: HashMap::from([
+                (String::from("linux"), String::from("hall_request_assigner")),
+                (String::from("macos"), String::from("hall_request_assigner_macos")),
+                (String::from("windows"), String::from("hall_request_assigner.exe")),
+            ]),
+        }
+    }
+}
+
+/// Durations in seconds
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfigFile {
+    pub door_open_duration: f64,
+    /// Time the car takes to travel from one floor to the next, used to
+    /// estimate when the elevators arrive at the hall requests
+    pub floor_travel_duration: f64,
+    pub slave_timeout: f64,
+    pub master_timeout: f64,
+    pub backup_timeout: f64,
+    pub master_request_buffer_timeout: f64,
+    pub slave_request_buffer_timeout: f64,
+}
+
+impl Default for TimingConfigFile {
+    fn default() -> Self {
+        TimingConfigFile {
+            door_open_duration: 3.0,
+            floor_travel_duration: 2.5,
+            slave_timeout: 4.0,
+            master_timeout: 3.0,
+            backup_timeout: 3.0,
+            master_request_buffer_timeout: 2.0,
+            slave_request_buffer_timeout: 5.0,
+        }
+    }
+}
+
+impl TimingConfigFile {
+    fn durations(&self) -> [(&'static str, f64); 7] {
+        [
+            ("timing.door_open_duration", self.door_open_duration),
+            ("timing.floor_travel_duration", self.floor_travel_duration),
+            ("timing.slave_timeout", self.slave_timeout),
+            ("timing.master_timeout", self.master_timeout),
+            ("timing.backup_timeout", self.backup_timeout),
+            ("timing.master_request_buffer_timeout", self.master_request_buffer_timeout),
+            ("timing.slave_request_buffer_timeout", self.slave_request_buffer_timeout),
+        ]
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfigFile {
+    pub id: u8,
+    pub role: Role,
+    pub headless: bool,
+    pub log_level: LogLevel,
+}
+
+impl Default for NodeConfigFile {
+    fn default() -> Self {
+        NodeConfigFile {
+            id: 0,
+            role: Role::Backup,
+            headless: false,
+            log_level: LogLevel::Info,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfigFile {
+    pub directory: String,
+    pub filter: String,
 }
 
+impl Default for LoggingConfigFile {
+    fn default() -> Self {
+        LoggingConfigFile {
+            directory: String::from("logs"),
+            filter: String::new(),
+        }
+    }
+}
+
+/// Metrics are served on `base_port` by the master, `base_port + 1` by the
+/// backup and `base_port + 10 + elevator number` by the slaves.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfigFile {
+    pub enabled: bool,
+    pub address: String,
+    pub base_port: u16,
+}
+
+impl Default for MetricsConfigFile {
+    fn default() -> Self {
+        MetricsConfigFile {
+            enabled: true,
+            address: String::from("127.0.0.1"),
+            base_port: 9270,
+        }
+    }
+}
+
+impl MetricsConfigFile {
+    const BACKUP_OFFSET: u32 = 1;
+    const SLAVE_OFFSET: u32 = 10;
+
+    fn port(&self, offset: u32) -> u32 {
+        self.base_port as u32 + offset
+    }
+
+    fn master_port(&self) -> u16 {
+        self.base_port
+    }
+
+    fn backup_port(&self) -> u16 {
+        self.port(Self::BACKUP_OFFSET) as u16
+    }
+
+    fn slave_port(&self, elevnum: u8) -> u16 {
+        self.port(Self::SLAVE_OFFSET + elevnum as u32) as u16
+    }
+}
+
+/// HTTP API served by the master
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfigFile {
+    pub enabled: bool,
+    pub address: String,
+    pub port: u16,
+    /// Bearer token required by operator commands, which are disabled if not set
+    pub token: Option<String>,
+}
+
+impl Default for ApiConfigFile {
+    fn default() -> Self {
+        ApiConfigFile {
+            enabled: true,
+            address: String::from("127.0.0.1"),
+            port: 9260,
+            token: None,
+        }
+    }
+}
+
+/// Journal of the significant events on the master, written to
+/// `<directory>/master-<id>.jsonl` and rotated when it reaches `max_file_size`
+/// bytes, keeping at most `max_files` files including the current one.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct JournalConfigFile {
+    pub enabled: bool,
+    pub directory: String,
+    pub max_file_size: u64,
+    pub max_files: u32,
+}
+
+impl Default for JournalConfigFile {
+    fn default() -> Self {
+        JournalConfigFile {
+            enabled: true,
+            directory: String::from("journal"),
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// Parking of the idle elevators, where they are parked depends on the traffic mode
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParkingConfigFile {
+    pub enabled: bool,
+}
+
+impl Default for ParkingConfigFile {
+    fn default() -> Self {
+        ParkingConfigFile {
+            enabled: true,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficMode {
+    /// Passengers travel between all floors
+    InterFloor,
+    /// Passengers arrive at the lobby and travel up
+    UpPeak,
+    /// Passengers leave the building, travelling down to the lobby
+    DownPeak,
+}
+
+impl TrafficMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TrafficMode::InterFloor => "inter_floor",
+            TrafficMode::UpPeak => "up_peak",
+            TrafficMode::DownPeak => "down_peak",
+        }
+    }
+}
+
+/// The traffic mode to use from `start` up to `end` o'clock, past midnight if
+/// `end` is before `start`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TrafficScheduleEntry {
+    pub start: u8,
+    pub end: u8,
+    pub mode: TrafficMode,
+}
+
+/// Traffic pattern detection on the master. The first `schedule` entry covering
+/// the current hour sets the traffic mode. Otherwise, if `detection` is enabled,
+/// the mode is detected from the hall requests received in the last `window`
+/// seconds, once there are at least `min_requests` of them. When the share of
+/// up calls at `lobby_floor` or the share of down calls reaches `peak_share`,
+/// the traffic is up-peak or down-peak respectively.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrafficConfigFile {
+    pub lobby_floor: u8,
+    pub detection: bool,
+    pub window: f64,
+    pub min_requests: usize,
+    pub peak_share: f64,
+    pub schedule: Vec<TrafficScheduleEntry>,
+}
+
+impl Default for TrafficConfigFile {
+    fn default() -> Self {
+        TrafficConfigFile {
+            lobby_floor: 0,
+            detection: true,
+            window: 300.0,
+            min_requests: 8,
+            peak_share: 0.6,
+            schedule: Vec::new(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadSensor {
+    /// The car is always reported empty
+    None,
+    /// The load is estimated as one passenger per cab request
+    Estimated,
+    /// The load is read from `<directory>/elevator-<id>`, where the load
+    /// sensor writes it in kilograms
+    File,
+}
+
+/// Load weighing on the slaves, in kilograms. A car loaded to at least
+/// `full_share` of its `capacity` is full, and passes the hall requests on its
+/// way while it has passengers to drop off.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadConfigFile {
+    pub sensor: LoadSensor,
+    pub directory: String,
+    pub capacity: f64,
+    pub passenger_weight: f64,
+    pub full_share: f64,
+}
+
+impl Default for LoadConfigFile {
+    fn default() -> Self {
+        LoadConfigFile {
+            sensor: LoadSensor::Estimated,
+            directory: String::from("load"),
+            capacity: 630.0,
+            passenger_weight: 75.0,
+            full_share: 0.8,
+        }
+    }
+}
+
+/// Emergency recall of every elevator to `floor`. Setting `active` starts a
+/// recall, as an input from the fire alarm of the building would, and an
+/// operator can start and reset recalls through the API as well.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecallConfigFile {
+    pub floor: u8,
+    pub active: bool,
+}
+
+/// Priority level of a single hall button, overriding the level of its floor
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonPriority {
+    pub floor: u8,
+    pub call: Call,
+    pub level: u8,
+}
+
+/// Priority of the hall calls at floors that must be served first, such as the
+/// emergency department of a hospital. `floors` gives every hall call at a
+/// floor a priority level, and `buttons` gives single hall buttons a level.
+/// Calls without a level have priority 0, and higher levels are served first.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityConfigFile {
+    pub floors: BTreeMap<u8, u8>,
+    pub buttons: Vec<ButtonPriority>,
+}
+
+/// Cancelling the hall calls of passengers who have left, in seconds. Holding
+/// a hall button for `long_press` cancels its hall call when it is released,
+/// and the master cancels the hall calls stored for longer than `max_age`.
+/// Either is turned off when not set.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CancelConfigFile {
+    pub long_press: Option<f64>,
+    pub max_age: Option<f64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
 pub struct ConfigFile {
-    pub network: HashMap<String, Vec<u16>>,
-    pub server: HashMap<String, u16>,
-    pub elevator: HashMap<String, u8>,
+    pub node: NodeConfigFile,
+    pub network: NetworkConfigFile,
+    pub server: ServerConfigFile,
+    pub elevator: ElevatorConfigFile,
+    pub timing: TimingConfigFile,
     pub hall_request_assigner: HRAConfigFile,
+    pub logging: LoggingConfigFile,
+    pub metrics: MetricsConfigFile,
+    pub api: ApiConfigFile,
+    pub journal: JournalConfigFile,
+    pub parking: ParkingConfigFile,
+    pub traffic: TrafficConfigFile,
+    pub load: LoadConfigFile,
+    pub recall: RecallConfigFile,
+    pub priority: PriorityConfigFile,
+    pub cancel: CancelConfigFile,
 }
 
-#[derive(Debug, Clone)]
-pub struct NetworkConfig {
-    pub update_port: u16,
-    pub command_port: u16,
-    pub pp_update_port: u16,
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigProblem {
+    NoFloors,
+    NoElevators,
+    TooFewPorts { list: &'static str, required: usize, provided: usize },
+    PortOutOfRange { list: &'static str, port: u32 },
+    MetricsPortOutOfRange { port: u32 },
+    BlockTooSmall { block_size: u16, num_elevators: u8 },
+    DuplicatePort { port: u16, lists: Vec<&'static str> },
+    MissingAssigner { os: String },
+    NonPositiveDuration { name: &'static str },
+    IdOutOfRange { id: u8, num_elevators: u8 },
+    InvalidLogFilter { error: String },
+    InvalidAddress { name: &'static str, address: String },
+    EmptyApiToken,
+    JournalLimitTooLow { name: &'static str },
+    LobbyOutOfRange { lobby_floor: u8, num_floors: u8 },
+    HourOutOfRange { entry: usize, name: &'static str, hour: u8 },
+    EmptyScheduleEntry { entry: usize },
+    InvalidPeakShare { peak_share: f64 },
+    ServedElevatorOutOfRange { elevnum: u8, num_elevators: u8 },
+    ServedFloorOutOfRange { elevnum: u8, floor: u8, num_floors: u8 },
+    NoServedFloors { elevnum: u8 },
+    NonPositiveWeight { name: &'static str },
+    InvalidFullShare { full_share: f64 },
+    RecallFloorOutOfRange { floor: u8, num_floors: u8 },
+    PriorityFloorOutOfRange { name: String, floor: u8, num_floors: u8 },
+    PriorityCabButton { entry: usize },
 }
 
-#[derive(Debug, Clone)]
-pub struct ServerConfig {
-    pub port: u16,
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblem::NoFloors => write!(f, "elevator.num_floors must be at least 1"),
+            ConfigProblem::NoElevators => write!(f, "elevator.num_elevators must be at least 1"),
+            ConfigProblem::TooFewPorts { list, required, provided } =>
+                write!(f, "network.{} has {} port(s), but {} are required", list, provided, required),
+            ConfigProblem::PortOutOfRange { list, port } =>
+                write!(f, "derived port {} for network.{} is out of range, lower network.base_port", port, list),
+            ConfigProblem::MetricsPortOutOfRange { port } =>
+                write!(f, "metrics port {} is out of range, lower metrics.base_port", port),
+            ConfigProblem::BlockTooSmall { block_size, num_elevators } =>
+                write!(f, "network.block_size {} is too small for {} elevator(s)", block_size, num_elevators),
+            ConfigProblem::DuplicatePort { port, lists } =>
+                write!(f, "port {} is used more than once, in {}", port, lists.join(", ")),
+            ConfigProblem::MissingAssigner { os } =>
+                write!(f, "hall_request_assigner.operating_systems has no executable for {}", os),
+            ConfigProblem::NonPositiveDuration { name } =>
+                write!(f, "{} must be a positive number of seconds", name),
+            ConfigProblem::IdOutOfRange { id, num_elevators } =>
+                write!(f, "id {} is out of range for {} elevator(s)", id, num_elevators),
+            ConfigProblem::InvalidLogFilter { error } =>
+                write!(f, "logging.filter is invalid: {}", error),
+            ConfigProblem::InvalidAddress { name, address } =>
+                write!(f, "{} \"{}\" is not a valid IP address", name, address),
+            ConfigProblem::EmptyApiToken =>
+                write!(f, "api.token must not be empty, leave it out to disable operator commands"),
+            ConfigProblem::JournalLimitTooLow { name } =>
+                write!(f, "journal.{} must be at least 1", name),
+            ConfigProblem::LobbyOutOfRange { lobby_floor, num_floors } =>
+                write!(f, "traffic.lobby_floor {} is out of range for {} floor(s)", lobby_floor, num_floors),
+            ConfigProblem::HourOutOfRange { entry, name, hour } =>
+                write!(f, "traffic.schedule[{}].{} {} is not an hour from 0 to 24", entry, name, hour),
+            ConfigProblem::EmptyScheduleEntry { entry } =>
+                write!(f, "traffic.schedule[{}] must end at another hour than it starts", entry),
+            ConfigProblem::InvalidPeakShare { peak_share } =>
+                write!(f, "traffic.peak_share {} must be above 0 and at most 1", peak_share),
+            ConfigProblem::ServedElevatorOutOfRange { elevnum, num_elevators } =>
+                write!(f, "elevator.served_floors has elevator {} which is out of range for {} elevator(s)", elevnum, num_elevators),
+            ConfigProblem::ServedFloorOutOfRange { elevnum, floor, num_floors } =>
+                write!(f, "elevator.served_floors.{} has floor {} which is out of range for {} floor(s)", elevnum, floor, num_floors),
+            ConfigProblem::NoServedFloors { elevnum } =>
+                write!(f, "elevator.served_floors.{} must have at least 1 floor", elevnum),
+            ConfigProblem::NonPositiveWeight { name } =>
+                write!(f, "{} must be a positive number of kilograms", name),
+            ConfigProblem::InvalidFullShare { full_share } =>
+                write!(f, "load.full_share {} must be above 0 and at most 1", full_share),
+            ConfigProblem::RecallFloorOutOfRange { floor, num_floors } =>
+                write!(f, "recall.floor {} is out of range for {} floor(s)", floor, num_floors),
+            ConfigProblem::PriorityFloorOutOfRange { name, floor, num_floors } =>
+                write!(f, "{} has floor {} which is out of range for {} floor(s)", name, floor, num_floors),
+            ConfigProblem::PriorityCabButton { entry } =>
+                write!(f, "priority.buttons[{}].call must be a hall call, cab calls have no priority", entry),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct ElevatorConfig {
-    pub num_floors: u8,
+impl ConfigFile {
+    /// Checks the configuration for problems, reporting all of them at once
+    pub fn validate(&self) -> Result<(), Vec<ConfigProblem>> {
+        let mut problems = Vec::new();
+
+        if self.elevator.num_floors == 0 {
+            problems.push(ConfigProblem::NoFloors);
+        }
+        if self.elevator.num_elevators == 0 {
+            problems.push(ConfigProblem::NoElevators);
+        }
+        for (&elevnum, floors) in &self.elevator.served_floors {
+            if elevnum >= self.elevator.num_elevators {
+                problems.push(ConfigProblem::ServedElevatorOutOfRange { elevnum, num_elevators: self.elevator.num_elevators });
+            }
+            if floors.is_empty() {
+                problems.push(ConfigProblem::NoServedFloors { elevnum });
+            }
+            for &floor in floors.iter().filter(|&&floor| floor >= self.elevator.num_floors) {
+                problems.push(ConfigProblem::ServedFloorOutOfRange { elevnum, floor, num_floors: self.elevator.num_floors });
+            }
+        }
+
+        let mut used_ports = vec![("server.port", self.server.port)];
+        match self.network.port_table(self.elevator.num_elevators) {
+            Ok(port_table) => {
+                for kind in PortKind::iter() {
+                    used_ports.extend(port_table.ports(kind).into_iter().map(|port| (kind.list_name(), port)));
+                }
+            },
+            Err(port_problems) => problems.extend(port_problems),
+        }
+        if self.metrics.enabled {
+            let last_port = self.metrics.port(MetricsConfigFile::SLAVE_OFFSET + self.elevator.num_elevators as u32) - 1;
+            if self.metrics.address.parse::<IpAddr>().is_err() {
+                problems.push(ConfigProblem::InvalidAddress { name: "metrics.address", address: self.metrics.address.clone() });
+            }
+            if last_port > u16::MAX as u32 {
+                problems.push(ConfigProblem::MetricsPortOutOfRange { port: last_port });
+            } else {
+                used_ports.push(("metrics", self.metrics.master_port()));
+                used_ports.push(("metrics", self.metrics.backup_port()));
+                for elevnum in 0..self.elevator.num_elevators {
+                    used_ports.push(("metrics", self.metrics.slave_port(elevnum)));
+                }
+            }
+        }
+        if self.api.enabled {
+            used_ports.push(("api.port", self.api.port));
+            if self.api.token.as_ref().is_some_and(|token| token.is_empty()) {
+                problems.push(ConfigProblem::EmptyApiToken);
+            }
+            if self.api.address.parse::<IpAddr>().is_err() {
+                problems.push(ConfigProblem::InvalidAddress { name: "api.address", address: self.api.address.clone() });
+            }
+        }
+        if self.journal.enabled {
+            if self.journal.max_file_size == 0 {
+                problems.push(ConfigProblem::JournalLimitTooLow { name: "max_file_size" });
+            }
+            if self.journal.max_files == 0 {
+                problems.push(ConfigProblem::JournalLimitTooLow { name: "max_files" });
+            }
+        }
+        if self.elevator.num_floors > 0 && self.traffic.lobby_floor >= self.elevator.num_floors {
+            problems.push(ConfigProblem::LobbyOutOfRange {
+                lobby_floor: self.traffic.lobby_floor,
+                num_floors: self.elevator.num_floors,
+            });
+        }
+        for (entry, schedule_entry) in self.traffic.schedule.iter().enumerate() {
+            for (name, hour) in [("start", schedule_entry.start), ("end", schedule_entry.end)] {
+                if hour > 24 {
+                    problems.push(ConfigProblem::HourOutOfRange { entry, name, hour });
+                }
+            }
+            // Midnight is both 0 and 24 o'clock, an entry from 0 to 24 covers the whole day
+            if schedule_entry.start % 24 == schedule_entry.end % 24 && schedule_entry.start >= schedule_entry.end {
+                problems.push(ConfigProblem::EmptyScheduleEntry { entry });
+            }
+        }
+        for (name, duration) in [("cancel.long_press", self.cancel.long_press), ("cancel.max_age", self.cancel.max_age)] {
+            if duration.is_some_and(|duration| duration.is_nan() || duration <= 0.0) {
+                problems.push(ConfigProblem::NonPositiveDuration { name });
+            }
+        }
+        if self.traffic.detection {
+            if self.traffic.window.is_nan() || self.traffic.window <= 0.0 {
+                problems.push(ConfigProblem::NonPositiveDuration { name: "traffic.window" });
+            }
+            if !(self.traffic.peak_share > 0.0 && self.traffic.peak_share <= 1.0) {
+                problems.push(ConfigProblem::InvalidPeakShare { peak_share: self.traffic.peak_share });
+            }
+        }
+        if self.elevator.num_floors > 0 && self.recall.floor >= self.elevator.num_floors {
+            problems.push(ConfigProblem::RecallFloorOutOfRange { floor: self.recall.floor, num_floors: self.elevator.num_floors });
+        }
+        if self.elevator.num_floors > 0 {
+            let num_floors = self.elevator.num_floors;
+            for &floor in self.priority.floors.keys().filter(|&&floor| floor >= num_floors) {
+                problems.push(ConfigProblem::PriorityFloorOutOfRange { name: String::from("priority.floors"), floor, num_floors });
+            }
+            for (entry, button) in self.priority.buttons.iter().enumerate().filter(|(_, button)| button.floor >= num_floors) {
+                problems.push(ConfigProblem::PriorityFloorOutOfRange { name: format!("priority.buttons[{}]", entry), floor: button.floor, num_floors });
+            }
+        }
+        for (entry, button) in self.priority.buttons.iter().enumerate() {
+            if matches!(button.call, Call::Cab) {
+                problems.push(ConfigProblem::PriorityCabButton { entry });
+            }
+        }
+        for (name, weight) in [("load.capacity", self.load.capacity), ("load.passenger_weight", self.load.passenger_weight)] {
+            if weight.is_nan() || weight <= 0.0 {
+                problems.push(ConfigProblem::NonPositiveWeight { name });
+            }
+        }
+        if !(self.load.full_share > 0.0 && self.load.full_share <= 1.0) {
+            problems.push(ConfigProblem::InvalidFullShare { full_share: self.load.full_share });
+        }
+        let mut reported_ports = Vec::new();
+        for &(_, port) in &used_ports {
+            let lists: Vec<&'static str> = used_ports.iter()
+                .filter(|(_, other)| *other == port)
+                .map(|(list, _)| *list)
+                .collect();
+            if lists.len() > 1 && !reported_ports.contains(&port) {
+                reported_ports.push(port);
+                problems.push(ConfigProblem::DuplicatePort { port, lists });
+            }
+        }
+
+        if !self.hall_request_assigner.operating_systems.contains_key(env::consts::OS) {
+            problems.push(ConfigProblem::MissingAssigner { os: env::consts::OS.to_string() });
+        }
+
+        for (name, duration) in self.timing.durations() {
+            if duration.is_nan() || duration <= 0.0 {
+                problems.push(ConfigProblem::NonPositiveDuration { name });
+            }
+        }
+        if self.elevator.independent_combination_window.is_some_and(|window| window.is_nan() || window <= 0.0) {
+            problems.push(ConfigProblem::NonPositiveDuration { name: "elevator.independent_combination_window" });
+        }
+
+        if let Err(e) = logging::filter(self.node.log_level, &self.logging.filter) {
+            problems.push(ConfigProblem::InvalidLogFilter { error: e.to_string() });
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    /// Ports of all nodes, only to be called on a validated configuration
+    pub fn port_table(&self) -> PortTable {
+        self.network.port_table(self.elevator.num_elevators).expect("configuration is not validated")
+    }
 }
 
-fn read_config_file() -> Result<ConfigFile, serde_json::Error> {
-    let file_path = "../config.json";
-    let fallback_file_path = "../_config.json";
-    let config_contents = match fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(_) => {
-            println!("No configuration file provided, using default settings...");
-            fs::read_to_string(fallback_file_path).unwrap()
-        },
-    };
-    serde_json::from_str(&config_contents)
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, error: std::io::Error },
+    Parse { origin: Origin, error: serde_json::Error },
+    Invalid(Vec<ConfigProblem>),
 }
 
-fn parse_env_args(defaultport: u16) -> (u8, u16) {
-    let (mut num, mut serverport) = (0, defaultport);
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, error } =>
+                write!(f, "could not read configuration file {}: {}", path.display(), error),
+            ConfigError::Parse { origin, error } =>
+                write!(f, "invalid configuration from {}: {}", origin, error),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    Default,
+    File(PathBuf),
+    NodeFile(PathBuf),
+    Environment(String),
+    CommandLine(&'static str),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::File(path) => write!(f, "file {}", path.display()),
+            Origin::NodeFile(path) => write!(f, "node file {}", path.display()),
+            Origin::Environment(variable) => write!(f, "environment variable {}", variable),
+            Origin::CommandLine(argument) => write!(f, "command line argument {}", argument),
+        }
+    }
+}
+
+/// The merged configuration, along with the origin of every value in it,
+/// keyed by the value's dotted path, e.g. `network.update_ports`.
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    pub config_file: ConfigFile,
+    pub origins: BTreeMap<String, Origin>,
+}
+
+impl LayeredConfig {
+    /// Reads and merges all configuration layers. Each layer is parsed on its
+    /// own first, so that errors are reported with the layer they came from.
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        let mut merged = serde_json::to_value(ConfigFile::default()).unwrap();
+        let mut origins = BTreeMap::new();
+        record_origins(&merged, String::new(), &Origin::Default, &mut origins);
+
+        if let Some(file_path) = shared_file_path(cli) {
+            let layer = read_layer_file(&file_path, Origin::File(file_path.clone()))?;
+            merge_layer(&mut merged, layer, String::new(), &Origin::File(file_path), &mut origins);
+        }
+        if let Some(file_path) = &cli.config.node_file {
+            let layer = read_layer_file(file_path, Origin::NodeFile(file_path.clone()))?;
+            merge_layer(&mut merged, layer, String::new(), &Origin::NodeFile(file_path.clone()), &mut origins);
+        }
 
-    let args: Vec<String> = env::args().collect();
-    for arg_pair in args.rchunks_exact(2) {
-        match arg_pair[0].as_str() {
-            "--num" => {
-                num = match arg_pair[1].parse::<u8>() {
-                    Ok(num) => num,
-                    Err(_) => {
-                        println!("num {} is not a number, skipping...", arg_pair[1]);
-                        num
+        for (variable, value) in env::vars() {
+            if let Some(layer) = env_layer(&variable, &value) {
+                let origin = Origin::Environment(variable.clone());
+                let layer = match parse_layer(&layer, &origin) {
+                    Ok(_) => layer,
+                    // A value such as `ELEV_API_TOKEN=12345` looks like a number, but is meant for a
+                    // text setting, so it is taken as a string when it does not fit the setting as parsed
+                    Err(error) => {
+                        let text_layer = env_layer(&variable, &Value::String(value).to_string()).unwrap();
+                        parse_layer(&text_layer, &origin).map_err(|_| error)?;
+                        text_layer
                     },
                 };
-            },
-            "--serverport" => {
-                serverport = match arg_pair[1].parse::<u16>() {
-                    Ok(num) => num,
-                    Err(_) => {
-                        println!("port {} is not a number, skipping...", arg_pair[1]);
-                        serverport
+                merge_layer(&mut merged, layer, String::new(), &origin, &mut origins);
+            }
+        }
+
+        for (argument, layer) in cli_layers(cli) {
+            merge_layer(&mut merged, layer, String::new(), &Origin::CommandLine(argument), &mut origins);
+        }
+
+        let config_file = parse_layer(&merged, &Origin::Default)?;
+        Ok(LayeredConfig { config_file, origins })
+    }
+
+    /// Loads all configuration layers and validates the result for a node of type `T`
+    pub fn load_validated<T: ResolvedConfig>(cli: &Cli) -> Result<Self, ConfigError> {
+        let layered = LayeredConfig::load(cli)?;
+        let mut problems = match layered.config_file.validate() {
+            Ok(()) => Vec::new(),
+            Err(problems) => problems,
+        };
+        problems.extend(T::check_node(&layered.config_file));
+        if problems.is_empty() { Ok(layered) } else { Err(ConfigError::Invalid(problems)) }
+    }
+
+    pub fn uses_defaults_only(&self) -> bool {
+        self.origins.values().all(|origin| *origin == Origin::Default)
+    }
+
+    /// Prints every configuration value along with its origin
+    pub fn print(&self) {
+        let merged = serde_json::to_value(&self.config_file).unwrap();
+        let mut values = BTreeMap::new();
+        collect_leaves(&merged, String::new(), &mut values);
+        let path_width = values.keys().map(|path| path.len()).max().unwrap_or(0);
+        let value_width = values.values().map(|value| value.to_string().len()).max().unwrap_or(0);
+        for (path, mut value) in values {
+            let origin = self.origins.get(&path).unwrap_or(&Origin::Default);
+            if SECRET_SETTINGS.contains(&path.as_str()) && !value.is_null() {
+                value = Value::String(String::from("<hidden>"));
+            }
+            println!("{:<path_width$} = {:<value_width$}  ({})", path, value.to_string(), origin);
+        }
+    }
+}
+
+/// Path of the configuration file shared by all nodes, if any
+pub fn shared_file_path(cli: &Cli) -> Option<PathBuf> {
+    match &cli.config.file {
+        Some(file_path) => Some(file_path.clone()),
+        None => [PathBuf::from(DEFAULT_FILE_PATH), PathBuf::from(FALLBACK_FILE_PATH)]
+            .into_iter()
+            .find(|path| path.exists()),
+    }
+}
+
+fn read_layer_file(file_path: &PathBuf, origin: Origin) -> Result<Value, ConfigError> {
+    let contents = fs::read_to_string(file_path)
+        .map_err(|e| ConfigError::Read { path: file_path.clone(), error: e })?;
+    let layer = serde_json::from_str(&contents)
+        .map_err(|e| ConfigError::Parse { origin: origin.clone(), error: e })?;
+    parse_layer(&layer, &origin)?;
+    Ok(layer)
+}
+
+fn parse_layer(layer: &Value, origin: &Origin) -> Result<ConfigFile, ConfigError> {
+    serde_json::from_value(layer.clone()).map_err(|e| ConfigError::Parse { origin: origin.clone(), error: e })
+}
+
+/// Maps an environment variable such as `ELEV_ELEVATOR_NUM_FLOORS=4` to the
+/// layer `{"elevator": {"num_floors": 4}}`. Values are parsed as JSON if
+/// possible, comma separated values as lists and anything else as a string.
+fn env_layer(variable: &str, value: &str) -> Option<Value> {
+    const SECTIONS: [&str; 16] = ["node", "network", "server", "elevator", "timing", "hall_request_assigner", "logging", "metrics", "api", "journal", "parking", "traffic", "load", "recall", "priority", "cancel"];
+    let name = variable.strip_prefix(ENV_PREFIX)?.to_lowercase();
+    let section = SECTIONS.iter().find(|section| name.starts_with(&(section.to_string() + "_")))?;
+    let key = name[section.len() + 1..].to_string();
+
+    let parse_value = |value: &str| serde_json::from_str(value.trim())
+        .unwrap_or_else(|_| Value::String(value.trim().to_string()));
+    let value = match serde_json::from_str(value) {
+        Ok(value) => value,
+        Err(_) if value.contains(',') => Value::Array(value.split(',').map(parse_value).collect()),
+        Err(_) => Value::String(value.to_string()),
+    };
+
+    let mut section_layer = serde_json::Map::new();
+    section_layer.insert(key, value);
+    let mut layer = serde_json::Map::new();
+    layer.insert(section.to_string(), Value::Object(section_layer));
+    Some(Value::Object(layer))
+}
+
+fn cli_layers(cli: &Cli) -> Vec<(&'static str, Value)> {
+    let mut layers = Vec::new();
+    if let Some(id) = cli.id {
+        layers.push(("--id", serde_json::json!({ "node": { "id": id } })));
+    }
+    if let Some(server_port) = cli.server_port {
+        layers.push(("--server-port", serde_json::json!({ "server": { "port": server_port } })));
+    }
+    if let Some(role) = cli.role {
+        layers.push(("--role", serde_json::json!({ "node": { "role": role } })));
+    }
+    if cli.headless {
+        layers.push(("--headless", serde_json::json!({ "node": { "headless": true } })));
+    }
+    if let Some(log_level) = cli.log_level {
+        layers.push(("--log-level", serde_json::json!({ "node": { "log_level": log_level } })));
+    }
+    layers
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { path.to_string() + "." + key }
+}
+
+/// Merges `layer` into `merged`. Objects are merged key by key, while any
+/// other value, including lists, replaces the previous value as a whole.
+fn merge_layer(merged: &mut Value, layer: Value, path: String, origin: &Origin, origins: &mut BTreeMap<String, Origin>) {
+    match (merged, layer) {
+        (Value::Object(merged), Value::Object(layer)) => {
+            for (key, value) in layer {
+                let path = join_path(&path, &key);
+                match merged.get_mut(&key) {
+                    Some(merged_value) => merge_layer(merged_value, value, path, origin, origins),
+                    None => {
+                        record_origins(&value, path, origin, origins);
+                        merged.insert(key, value);
                     },
-                };
+                }
+            }
+        },
+        (merged, layer) => {
+            origins.retain(|other, _| !(other == &path || other.starts_with(&(path.clone() + "."))));
+            record_origins(&layer, path, origin, origins);
+            *merged = layer;
+        },
+    }
+}
+
+fn record_origins(value: &Value, path: String, origin: &Origin, origins: &mut BTreeMap<String, Origin>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                record_origins(value, join_path(&path, key), origin, origins);
+            }
+        },
+        _ => { origins.insert(path, origin.clone()); },
+    }
+}
+
+pub(crate) fn collect_leaves(value: &Value, path: String, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                collect_leaves(value, join_path(&path, key), leaves);
+            }
+        },
+        _ => { leaves.insert(path, value.clone()); },
+    }
+}
+
+/// The configuration of a single kind of node, resolved from a validated configuration file
+pub trait ResolvedConfig: serde::Serialize + Clone + Send + 'static {
+    /// Problems specific to this kind of node, in addition to the ones found by `ConfigFile::validate`
+    fn check_node(_config_file: &ConfigFile) -> Vec<ConfigProblem> {
+        Vec::new()
+    }
+
+    fn resolve(config_file: &ConfigFile) -> Self;
+}
+
+/// Loads and validates the configuration and resolves the configuration of a single node.
+/// If `--print-config` or the `config check` subcommand was given, the configuration is
+/// printed and the program exits. Exits with a non-zero exit code if the configuration is invalid.
+fn get_or_exit<T: ResolvedConfig>(cli: &Cli) -> T {
+    let layered = match LayeredConfig::load_validated::<T>(cli) {
+        Ok(layered) => layered,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        },
+    };
+    if cli.config.print {
+        layered.print();
+        process::exit(0);
+    }
+    let config = T::resolve(&layered.config_file);
+    if matches!(cli.command, Some(Command::Config { action: ConfigCommand::Check })) {
+        println!("{}", serde_json::to_string_pretty(&config).unwrap());
+        println!("Configuration is valid");
+        process::exit(0);
+    }
+    if layered.uses_defaults_only() && layered.config_file.node.log_level >= LogLevel::Info {
+        println!("No configuration file provided, using default settings...");
+    }
+    config
+}
+
+fn check_elevator_id(id: u8, config_file: &ConfigFile) -> Vec<ConfigProblem> {
+    if config_file.elevator.num_elevators > 0 && id >= config_file.elevator.num_elevators {
+        vec![ConfigProblem::IdOutOfRange { id, num_elevators: config_file.elevator.num_elevators }]
+    } else {
+        Vec::new()
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct NodeConfig {
+    pub id: u8,
+    pub role: Role,
+    pub headless: bool,
+    pub log_level: LogLevel,
+    pub logging: LoggingConfig,
+}
+
+impl NodeConfig {
+    pub fn from_file(config_file: &ConfigFile) -> Self {
+        NodeConfig {
+            id: config_file.node.id,
+            role: config_file.node.role,
+            headless: config_file.node.headless,
+            log_level: config_file.node.log_level,
+            logging: LoggingConfig {
+                directory: PathBuf::from(&config_file.logging.directory),
+                filter: config_file.logging.filter.clone(),
             },
-            _ => {println!("illegal argument {}, skipping...", arg_pair[0]);},
         }
     }
-    (num, serverport)
 }
 
-#[derive(Debug, Clone)]
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub port: u16,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct LoggingConfig {
+    pub directory: PathBuf,
+    pub filter: String,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct NetworkConfig {
+    pub update_port: u16,
+    pub command_port: u16,
+    pub pp_update_port: u16,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ServerConfig {
+    pub port: u16,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ElevatorConfig {
+    pub num_floors: u8,
+}
+
+/// Durations in seconds
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SlaveTimingConfig {
+    pub door_open_duration: f64,
+    pub master_timeout: f64,
+    pub request_buffer_timeout: f64,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
 pub struct SlaveConfig {
+    pub node: NodeConfig,
     pub elevnum: u8,
     pub network: NetworkConfig,
     pub server: ServerConfig,
     pub elevator: ElevatorConfig,
+    /// Whether each floor is served by this elevator, indexed by floor
+    pub served_floors: Vec<bool>,
+    /// Seconds within which the independent service button combination must
+    /// be pressed, or `None` if the combination is off
+    pub independent_combination_window: Option<f64>,
+    pub load: LoadConfig,
+    pub priority: PriorityConfig,
+    pub cancel: CancelConfig,
+    pub timing: SlaveTimingConfig,
+    pub metrics: MetricsConfig,
 }
 
 impl SlaveConfig {
-    pub fn get() -> Self {
-        let config_file = read_config_file().unwrap();
-        let (elevnum, serverport) = parse_env_args(config_file.server["port"]);
-        
+    pub fn get(cli: &Cli) -> Self {
+        get_or_exit(cli)
+    }
+}
+
+impl ResolvedConfig for SlaveConfig {
+    fn check_node(config_file: &ConfigFile) -> Vec<ConfigProblem> {
+        check_elevator_id(config_file.node.id, config_file)
+    }
+
+    fn resolve(config_file: &ConfigFile) -> Self {
+        let elevnum = config_file.node.id;
+        let port_table = config_file.port_table();
         SlaveConfig {
-            elevnum: elevnum,
-            network: NetworkConfig { 
-                update_port: config_file.network["update_ports"][elevnum as usize], 
-                command_port: config_file.network["command_ports"][elevnum as usize],
-                pp_update_port: config_file.network["slave_pp_update_ports"][elevnum as usize],
+            node: NodeConfig::from_file(config_file),
+            elevnum,
+            network: NetworkConfig {
+                update_port: port_table.update_ports[elevnum as usize],
+                command_port: port_table.command_ports[elevnum as usize],
+                pp_update_port: port_table.slave_pp_update_ports[elevnum as usize],
+            },
+            server: ServerConfig {
+                port: config_file.server.port,
+            },
+            elevator: ElevatorConfig {
+                num_floors: config_file.elevator.num_floors,
             },
-            server: ServerConfig { 
-                port: serverport,
+            served_floors: config_file.elevator.served_floors(elevnum),
+            independent_combination_window: config_file.elevator.independent_combination_window,
+            load: LoadConfig {
+                sensor: config_file.load.sensor,
+                file: PathBuf::from(&config_file.load.directory).join(format!("elevator-{}", elevnum)),
+                capacity: config_file.load.capacity,
+                passenger_weight: config_file.load.passenger_weight,
+                full_share: config_file.load.full_share,
             },
-            elevator: ElevatorConfig { 
-                num_floors: config_file.elevator["num_floors"], 
+            priority: PriorityConfig::from_file(config_file),
+            cancel: CancelConfig::from_file(config_file),
+            timing: SlaveTimingConfig {
+                door_open_duration: config_file.timing.door_open_duration,
+                master_timeout: config_file.timing.master_timeout,
+                request_buffer_timeout: config_file.timing.slave_request_buffer_timeout,
+            },
+            metrics: MetricsConfig {
+                enabled: config_file.metrics.enabled,
+                address: config_file.metrics.address.clone(),
+                port: config_file.metrics.slave_port(elevnum),
             },
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(serde::Serialize, Debug, Clone)]
 pub struct MasterNetworkConfig {
     pub update_ports: Vec<u16>,
     pub command_ports: Vec<u16>,
@@ -119,67 +1320,692 @@ pub struct MasterNetworkConfig {
     pub pp_port: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(serde::Serialize, Debug, Clone)]
 pub struct HallRequestAssignerConfig {
+    pub strategy: AssignerStrategy,
     pub exec_path: String,
 }
 
-#[derive(Debug, Clone)]
+/// Durations in seconds
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct MasterTimingConfig {
+    pub door_open_duration: f64,
+    pub floor_travel_duration: f64,
+    pub slave_timeout: f64,
+    pub backup_timeout: f64,
+    pub request_buffer_timeout: f64,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub port: u16,
+    #[serde(serialize_with = "serialize_secret")]
+    pub token: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    pub directory: PathBuf,
+    pub max_file_size: u64,
+    pub max_files: u32,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ParkingConfig {
+    pub enabled: bool,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct RecallConfig {
+    pub floor: u8,
+    pub active: bool,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct CancelConfig {
+    pub long_press: Option<f64>,
+    pub max_age: Option<f64>,
+}
+
+impl CancelConfig {
+    fn from_file(config_file: &ConfigFile) -> Self {
+        CancelConfig {
+            long_press: config_file.cancel.long_press,
+            max_age: config_file.cancel.max_age,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct PriorityConfig {
+    pub floors: BTreeMap<u8, u8>,
+    pub buttons: Vec<ButtonPriority>,
+}
+
+impl PriorityConfig {
+    fn from_file(config_file: &ConfigFile) -> Self {
+        PriorityConfig {
+            floors: config_file.priority.floors.clone(),
+            buttons: config_file.priority.buttons.clone(),
+        }
+    }
+
+    /// Priority level of a hall request, given by its button or else by its
+    /// floor. Destination calls have the level of their floor.
+    pub fn level(&self, request: &Request) -> u8 {
+        self.buttons.iter()
+            .find(|button| button.floor == request.floor && button.call as u8 == request.call as u8)
+            .map(|button| button.level)
+            .or_else(|| self.floors.get(&request.floor).copied())
+            .unwrap_or(0)
+    }
+}
+
+/// Weights in kilograms
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct LoadConfig {
+    pub sensor: LoadSensor,
+    /// File the `file` load sensor is read from
+    pub file: PathBuf,
+    pub capacity: f64,
+    pub passenger_weight: f64,
+    pub full_share: f64,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct TrafficConfig {
+    pub lobby_floor: u8,
+    pub detection: bool,
+    /// Duration in seconds
+    pub window: f64,
+    pub min_requests: usize,
+    pub peak_share: f64,
+    pub schedule: Vec<TrafficScheduleEntry>,
+}
+
+fn serialize_secret<S: serde::Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str("<hidden>"),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
 pub struct MasterConfig {
+    pub node: NodeConfig,
     pub network: MasterNetworkConfig,
     pub elevator: ElevatorConfig,
+    pub timing: MasterTimingConfig,
     pub hall_request_assigner: HallRequestAssignerConfig,
+    pub metrics: MetricsConfig,
+    pub api: ApiConfig,
+    pub journal: JournalConfig,
+    pub parking: ParkingConfig,
+    pub traffic: TrafficConfig,
+    pub recall: RecallConfig,
+    pub priority: PriorityConfig,
+    pub cancel: CancelConfig,
 }
 
 impl MasterConfig {
-    pub fn get() -> Self {
-        let config_file = read_config_file().unwrap();
+    pub fn get(cli: &Cli) -> Self {
+        get_or_exit(cli)
+    }
+}
+
+impl ResolvedConfig for MasterConfig {
+    fn resolve(config_file: &ConfigFile) -> Self {
+        let port_table = config_file.port_table();
         let exec_path = config_file.hall_request_assigner.exec_folder_path.clone()
             + &config_file.hall_request_assigner.operating_systems[env::consts::OS];
 
         MasterConfig {
-            network: MasterNetworkConfig { 
-                update_ports: config_file.network["update_ports"].to_vec(),
-                command_ports: config_file.network["command_ports"].to_vec(),
-                backup_update_port: config_file.network["backup_update_ports"][0],
-                backup_ack_port: config_file.network["backup_ack_ports"][0],
-                pp_port: config_file.network["master_pp_ports"][0],
+            node: NodeConfig::from_file(config_file),
+            network: MasterNetworkConfig {
+                update_ports: port_table.update_ports,
+                command_ports: port_table.command_ports,
+                backup_update_port: port_table.backup_update_port,
+                backup_ack_port: port_table.backup_ack_port,
+                pp_port: port_table.master_pp_port,
             },
-            elevator: ElevatorConfig { 
-                num_floors: config_file.elevator["num_floors"], 
+            elevator: ElevatorConfig {
+                num_floors: config_file.elevator.num_floors,
             },
-            hall_request_assigner: HallRequestAssignerConfig { 
-                exec_path: exec_path,
-            }
+            timing: MasterTimingConfig {
+                door_open_duration: config_file.timing.door_open_duration,
+                floor_travel_duration: config_file.timing.floor_travel_duration,
+                slave_timeout: config_file.timing.slave_timeout,
+                backup_timeout: config_file.timing.backup_timeout,
+                request_buffer_timeout: config_file.timing.master_request_buffer_timeout,
+            },
+            hall_request_assigner: HallRequestAssignerConfig {
+                strategy: config_file.hall_request_assigner.strategy,
+                exec_path,
+            },
+            metrics: MetricsConfig {
+                enabled: config_file.metrics.enabled,
+                address: config_file.metrics.address.clone(),
+                port: config_file.metrics.master_port(),
+            },
+            api: ApiConfig {
+                enabled: config_file.api.enabled,
+                address: config_file.api.address.clone(),
+                port: config_file.api.port,
+                token: config_file.api.token.clone(),
+            },
+            journal: JournalConfig {
+                enabled: config_file.journal.enabled,
+                directory: PathBuf::from(&config_file.journal.directory),
+                max_file_size: config_file.journal.max_file_size,
+                max_files: config_file.journal.max_files,
+            },
+            parking: ParkingConfig {
+                enabled: config_file.parking.enabled,
+            },
+            traffic: TrafficConfig {
+                lobby_floor: config_file.traffic.lobby_floor,
+                detection: config_file.traffic.detection,
+                window: config_file.traffic.window,
+                min_requests: config_file.traffic.min_requests,
+                peak_share: config_file.traffic.peak_share,
+                schedule: config_file.traffic.schedule.clone(),
+            },
+            recall: RecallConfig {
+                floor: config_file.recall.floor,
+                active: config_file.recall.active,
+            },
+            priority: PriorityConfig::from_file(config_file),
+            cancel: CancelConfig::from_file(config_file),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(serde::Serialize, Debug, Clone)]
 pub struct BackupNetworkConfig {
     pub backup_update_port: u16,
     pub backup_ack_port: u16,
     pub pp_port: u16,
 }
 
-#[derive(Debug, Clone)]
+#[derive(serde::Serialize, Debug, Clone)]
 pub struct BackupConfig {
+    pub node: NodeConfig,
     pub network: BackupNetworkConfig,
     pub elevator: ElevatorConfig,
+    pub metrics: MetricsConfig,
 }
 
 impl BackupConfig {
-    pub fn get() -> Self {
-        let config_file = read_config_file().unwrap();
+    pub fn get(cli: &Cli) -> Self {
+        get_or_exit(cli)
+    }
+}
+
+impl ResolvedConfig for BackupConfig {
+    fn resolve(config_file: &ConfigFile) -> Self {
+        let port_table = config_file.port_table();
         BackupConfig {
-            network: BackupNetworkConfig { 
-                backup_update_port: config_file.network["backup_update_ports"][0],
-                backup_ack_port: config_file.network["backup_ack_ports"][0],
-                pp_port: config_file.network["backup_pp_ports"][0],
+            node: NodeConfig::from_file(config_file),
+            network: BackupNetworkConfig {
+                backup_update_port: port_table.backup_update_port,
+                backup_ack_port: port_table.backup_ack_port,
+                pp_port: port_table.backup_pp_port,
+            },
+            elevator: ElevatorConfig {
+                num_floors: config_file.elevator.num_floors,
+            },
+            metrics: MetricsConfig {
+                enabled: config_file.metrics.enabled,
+                address: config_file.metrics.address.clone(),
+                port: config_file.metrics.backup_port(),
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct DashboardNetworkConfig {
+    pub update_ports: Vec<u16>,
+    pub command_ports: Vec<u16>,
+    pub backup_update_port: u16,
+    pub backup_ack_port: u16,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct DashboardElevatorConfig {
+    pub num_floors: u8,
+    pub num_elevators: u8,
+}
+
+/// Durations in seconds
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct DashboardTimingConfig {
+    pub slave_timeout: f64,
+    pub master_timeout: f64,
+    pub backup_timeout: f64,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct DashboardMetricsConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub master_port: u16,
+    pub backup_port: u16,
+    pub slave_ports: Vec<u16>,
+}
+
+/// Configuration of the dashboard, which watches every node rather than being one
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct DashboardConfig {
+    pub network: DashboardNetworkConfig,
+    pub elevator: DashboardElevatorConfig,
+    pub timing: DashboardTimingConfig,
+    pub metrics: DashboardMetricsConfig,
+}
+
+impl DashboardConfig {
+    pub fn get(config_args: &ConfigArgs) -> Self {
+        get_or_exit(&Cli::from(config_args.clone()))
+    }
+}
+
+impl ResolvedConfig for DashboardConfig {
+    fn resolve(config_file: &ConfigFile) -> Self {
+        let port_table = config_file.port_table();
+        DashboardConfig {
+            network: DashboardNetworkConfig {
+                update_ports: port_table.update_ports,
+                command_ports: port_table.command_ports,
+                backup_update_port: port_table.backup_update_port,
+                backup_ack_port: port_table.backup_ack_port,
+            },
+            elevator: DashboardElevatorConfig {
+                num_floors: config_file.elevator.num_floors,
+                num_elevators: config_file.elevator.num_elevators,
+            },
+            timing: DashboardTimingConfig {
+                slave_timeout: config_file.timing.slave_timeout,
+                master_timeout: config_file.timing.master_timeout,
+                backup_timeout: config_file.timing.backup_timeout,
             },
-            elevator: ElevatorConfig { 
-                num_floors: config_file.elevator["num_floors"], 
+            metrics: DashboardMetricsConfig {
+                enabled: config_file.metrics.enabled,
+                address: config_file.metrics.address.clone(),
+                master_port: config_file.metrics.master_port(),
+                backup_port: config_file.metrics.backup_port(),
+                slave_ports: (0..config_file.elevator.num_elevators)
+                    .map(|elevnum| config_file.metrics.slave_port(elevnum))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Configuration of the network sniffer, which listens to the ports of every node
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct SnifferConfig {
+    pub network: PortTable,
+    pub elevator: ElevatorConfig,
+}
+
+impl SnifferConfig {
+    pub fn get(config_args: &ConfigArgs) -> Self {
+        get_or_exit(&Cli::from(config_args.clone()))
+    }
+}
+
+impl ResolvedConfig for SnifferConfig {
+    fn resolve(config_file: &ConfigFile) -> Self {
+        SnifferConfig {
+            network: config_file.port_table(),
+            elevator: ElevatorConfig {
+                num_floors: config_file.elevator.num_floors,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn problems(change: impl FnOnce(&mut ConfigFile)) -> Vec<ConfigProblem> {
+        let mut config_file = ConfigFile::default();
+        change(&mut config_file);
+        config_file.validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn default_configuration_is_valid() {
+        assert_eq!(ConfigFile::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn layers_override_the_previous_layers() {
+        let directory = env::temp_dir().join(format!("elevator-config-test-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let file = directory.join("config.json");
+        let node_file = directory.join("node.json");
+        fs::write(&file, r#"{
+            "elevator": { "num_floors": 5, "num_elevators": 4 },
+            "server": { "port": 15000 }
+        }"#).unwrap();
+        fs::write(&node_file, r#"{
+            "elevator": { "num_floors": 6 },
+            "server": { "port": 15001 },
+            "node": { "id": 1 }
+        }"#).unwrap();
+        env::set_var("ELEV_ELEVATOR_NUM_FLOORS", "7");
+        env::set_var("ELEV_NODE_ID", "2");
+        let cli = Cli::parse_from([
+            "master",
+            "--config", file.to_str().unwrap(),
+            "--node-config", node_file.to_str().unwrap(),
+            "--id", "3",
+        ]);
+
+        let layered = LayeredConfig::load(&cli);
+        env::remove_var("ELEV_ELEVATOR_NUM_FLOORS");
+        env::remove_var("ELEV_NODE_ID");
+        fs::remove_dir_all(&directory).unwrap();
+        let layered = layered.unwrap();
+
+        let config_file = &layered.config_file;
+        assert_eq!(config_file.network.update_ports, NetworkConfigFile::default().update_ports);
+        assert_eq!(layered.origins["network.update_ports"], Origin::Default);
+        assert_eq!(config_file.elevator.num_elevators, 4);
+        assert_eq!(layered.origins["elevator.num_elevators"], Origin::File(file));
+        assert_eq!(config_file.server.port, 15001);
+        assert_eq!(layered.origins["server.port"], Origin::NodeFile(node_file));
+        assert_eq!(config_file.elevator.num_floors, 7);
+        assert_eq!(layered.origins["elevator.num_floors"], Origin::Environment(String::from("ELEV_ELEVATOR_NUM_FLOORS")));
+        assert_eq!(config_file.node.id, 3);
+        assert_eq!(layered.origins["node.id"], Origin::CommandLine("--id"));
+    }
+
+    #[test]
+    fn environment_values_are_read_as_the_type_of_their_setting() {
+        env::set_var("ELEV_HALL_REQUEST_ASSIGNER_EXEC_FOLDER_PATH", "12345");
+        env::set_var("ELEV_NETWORK_BACKUP_ACK_PORTS", "19781,19782");
+        let layered = LayeredConfig::load(&Cli::parse_from(["master"]));
+        env::remove_var("ELEV_HALL_REQUEST_ASSIGNER_EXEC_FOLDER_PATH");
+        env::remove_var("ELEV_NETWORK_BACKUP_ACK_PORTS");
+        let config_file = layered.unwrap().config_file;
+
+        assert_eq!(config_file.hall_request_assigner.exec_folder_path, "12345");
+        assert_eq!(config_file.network.backup_ack_ports, [19781, 19782]);
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert!(serde_json::from_str::<ConfigFile>(r#"{ "elevator": { "floors": 6 } }"#).is_err());
+        assert!(serde_json::from_str::<ConfigFile>(r#"{ "elevators": {} }"#).is_err());
+    }
+
+    #[test]
+    fn elevator_counts_must_be_positive() {
+        assert!(problems(|config| config.elevator.num_floors = 0).contains(&ConfigProblem::NoFloors));
+        assert!(problems(|config| config.elevator.num_elevators = 0).contains(&ConfigProblem::NoElevators));
+    }
+
+    #[test]
+    fn port_problems_are_reported() {
+        assert_eq!(problems(|config| config.network.update_ports = vec![19730]), [
+            ConfigProblem::TooFewPorts { list: "update_ports", required: 3, provided: 1 },
+        ]);
+        assert_eq!(problems(|config| config.network.backup_ack_ports = vec![15657]), [
+            ConfigProblem::DuplicatePort { port: 15657, lists: vec!["server.port", "backup_ack_ports"] },
+        ]);
+        assert!(problems(|config| config.network.base_port = Some(65530)).contains(
+            &ConfigProblem::PortOutOfRange { list: "command_ports", port: 65540 }
+        ));
+        assert_eq!(problems(|config| {
+            config.network.base_port = Some(20000);
+            config.network.block_size = Some(2);
+        }), [
+            ConfigProblem::BlockTooSmall { block_size: 2, num_elevators: 3 },
+        ]);
+    }
+
+    #[test]
+    fn metrics_ports_follow_the_base_port() {
+        let metrics = MetricsConfigFile { base_port: 9000, ..MetricsConfigFile::default() };
+        assert_eq!(metrics.master_port(), 9000);
+        assert_eq!(metrics.backup_port(), 9001);
+        assert_eq!((0..3).map(|elevnum| metrics.slave_port(elevnum)).collect::<Vec<u16>>(), [9010, 9011, 9012]);
+        assert_eq!(problems(|config| config.metrics.base_port = 65530), [
+            ConfigProblem::MetricsPortOutOfRange { port: 65542 },
+        ]);
+    }
+
+    #[test]
+    fn ports_are_derived_from_the_base_port() {
+        let network = NetworkConfigFile { base_port: Some(19730), ..NetworkConfigFile::default() };
+        let port_table = network.port_table(3).unwrap();
+        let defaults = NetworkConfigFile::default().port_table(3).unwrap();
+        for kind in PortKind::iter() {
+            assert_eq!(port_table.ports(kind), defaults.ports(kind), "{} differ from the default ports", kind.list_name());
+        }
+    }
+
+    #[test]
+    fn block_3_is_left_unused() {
+        let network = NetworkConfigFile { base_port: Some(20000), block_size: Some(5), ..NetworkConfigFile::default() };
+        let port_table = network.port_table(3).unwrap();
+        assert_eq!(port_table.update_ports, [20000, 20001, 20002]);
+        assert_eq!(port_table.command_ports, [20005, 20006, 20007]);
+        assert_eq!(port_table.slave_pp_update_ports, [20010, 20011, 20012]);
+        assert_eq!(port_table.backup_update_port, 20020);
+        assert_eq!(port_table.backup_ack_port, 20025);
+        assert_eq!(port_table.master_pp_port, 20030);
+        assert_eq!(port_table.backup_pp_port, 20035);
+    }
+
+    #[test]
+    fn block_size_is_rounded_up_to_fit_the_elevators() {
+        let network = NetworkConfigFile { base_port: Some(20000), ..NetworkConfigFile::default() };
+        let port_table = network.port_table(12).unwrap();
+        assert_eq!(port_table.update_ports, (20000..20012).collect::<Vec<u16>>());
+        assert_eq!(port_table.command_ports[0], 20020);
+        assert_eq!(port_table.backup_update_port, 20080);
+    }
+
+    #[test]
+    fn missing_assigner_is_reported() {
+        assert_eq!(problems(|config| config.hall_request_assigner.operating_systems.clear()), [
+            ConfigProblem::MissingAssigner { os: env::consts::OS.to_string() },
+        ]);
+    }
+
+    #[test]
+    fn durations_must_be_positive() {
+        assert_eq!(problems(|config| config.timing.door_open_duration = 0.0), [
+            ConfigProblem::NonPositiveDuration { name: "timing.door_open_duration" },
+        ]);
+        assert_eq!(problems(|config| config.timing.slave_timeout = f64::NAN), [
+            ConfigProblem::NonPositiveDuration { name: "timing.slave_timeout" },
+        ]);
+    }
+
+    #[test]
+    fn log_filter_must_parse() {
+        assert!(matches!(
+            problems(|config| config.logging.filter = String::from("slave=loud"))[..],
+            [ConfigProblem::InvalidLogFilter { .. }],
+        ));
+        assert_eq!(problems(|config| config.logging.filter = String::from("slave::modules::fsm=debug")), []);
+    }
+
+    #[test]
+    fn api_address_must_be_an_ip_address() {
+        assert_eq!(
+            problems(|config| config.api.address = String::from("localhost")),
+            [ConfigProblem::InvalidAddress { name: "api.address", address: String::from("localhost") }],
+        );
+        assert_eq!(problems(|config| {
+            config.api.enabled = false;
+            config.api.address = String::from("localhost");
+        }), []);
+    }
+
+    #[test]
+    fn api_token_must_not_be_empty() {
+        assert_eq!(problems(|config| config.api.token = Some(String::new())), [ConfigProblem::EmptyApiToken]);
+        assert_eq!(problems(|config| config.api.token = Some(String::from("secret"))), []);
+    }
+
+    #[test]
+    fn journal_limits_must_be_at_least_one() {
+        assert_eq!(problems(|config| {
+            config.journal.max_file_size = 0;
+            config.journal.max_files = 0;
+        }), [
+            ConfigProblem::JournalLimitTooLow { name: "max_file_size" },
+            ConfigProblem::JournalLimitTooLow { name: "max_files" },
+        ]);
+        assert_eq!(problems(|config| {
+            config.journal.enabled = false;
+            config.journal.max_files = 0;
+        }), []);
+    }
+
+    #[test]
+    fn traffic_settings_must_fit_the_building_and_the_day() {
+        assert_eq!(problems(|config| {
+            config.traffic.lobby_floor = 4;
+            config.traffic.schedule = vec![
+                TrafficScheduleEntry { start: 22, end: 24, mode: TrafficMode::DownPeak },
+                TrafficScheduleEntry { start: 7, end: 25, mode: TrafficMode::UpPeak },
+            ];
+        }), [
+            ConfigProblem::LobbyOutOfRange { lobby_floor: 4, num_floors: 4 },
+            ConfigProblem::HourOutOfRange { entry: 1, name: "end", hour: 25 },
+        ]);
+    }
+
+    #[test]
+    fn schedule_entries_must_cover_some_hours() {
+        let entry = |start, end| TrafficScheduleEntry { start, end, mode: TrafficMode::UpPeak };
+        assert_eq!(problems(|config| config.traffic.schedule = vec![entry(7, 7), entry(24, 0)]), [
+            ConfigProblem::EmptyScheduleEntry { entry: 0 },
+            ConfigProblem::EmptyScheduleEntry { entry: 1 },
+        ]);
+        assert_eq!(problems(|config| config.traffic.schedule = vec![entry(0, 24), entry(22, 6)]), []);
+    }
+
+    #[test]
+    fn traffic_detection_settings_are_checked_when_detecting() {
+        assert_eq!(problems(|config| {
+            config.traffic.window = 0.0;
+            config.traffic.peak_share = 1.5;
+        }), [
+            ConfigProblem::NonPositiveDuration { name: "traffic.window" },
+            ConfigProblem::InvalidPeakShare { peak_share: 1.5 },
+        ]);
+        assert_eq!(problems(|config| config.traffic.peak_share = 0.0), [ConfigProblem::InvalidPeakShare { peak_share: 0.0 }]);
+        assert_eq!(problems(|config| {
+            config.traffic.detection = false;
+            config.traffic.window = 0.0;
+            config.traffic.peak_share = 1.5;
+        }), []);
+    }
+
+    #[test]
+    fn served_floors_must_be_in_the_building() {
+        assert_eq!(problems(|config| {
+            config.elevator.served_floors = BTreeMap::from([(0, vec![0, 4]), (1, vec![]), (3, vec![1])]);
+        }), [
+            ConfigProblem::ServedFloorOutOfRange { elevnum: 0, floor: 4, num_floors: 4 },
+            ConfigProblem::NoServedFloors { elevnum: 1 },
+            ConfigProblem::ServedElevatorOutOfRange { elevnum: 3, num_elevators: 3 },
+        ]);
+        assert_eq!(problems(|config| config.elevator.served_floors = BTreeMap::from([(2, vec![0, 3])])), []);
+    }
+
+    #[test]
+    fn elevators_left_out_of_served_floors_serve_every_floor() {
+        let elevator = ElevatorConfigFile {
+            served_floors: BTreeMap::from([(1, vec![0, 2])]),
+            ..ElevatorConfigFile::default()
+        };
+        assert_eq!(elevator.served_floors(0), [true, true, true, true]);
+        assert_eq!(elevator.served_floors(1), [true, false, true, false]);
+    }
+
+    #[test]
+    fn load_settings_must_be_positive_weights_and_a_share() {
+        assert_eq!(problems(|config| {
+            config.load.capacity = 0.0;
+            config.load.passenger_weight = f64::NAN;
+            config.load.full_share = 1.5;
+        }), [
+            ConfigProblem::NonPositiveWeight { name: "load.capacity" },
+            ConfigProblem::NonPositiveWeight { name: "load.passenger_weight" },
+            ConfigProblem::InvalidFullShare { full_share: 1.5 },
+        ]);
+        assert_eq!(problems(|config| config.load.full_share = 1.0), []);
+    }
+
+    #[test]
+    fn recall_floor_must_be_in_the_building() {
+        assert_eq!(problems(|config| config.recall.floor = 4), [ConfigProblem::RecallFloorOutOfRange { floor: 4, num_floors: 4 }]);
+        assert_eq!(problems(|config| config.recall.floor = 3), []);
+    }
+
+    #[test]
+    fn independent_combination_is_off_unless_given_a_positive_window() {
+        assert_eq!(ConfigFile::default().elevator.independent_combination_window, None);
+        assert_eq!(problems(|config| config.elevator.independent_combination_window = Some(0.0)), [
+            ConfigProblem::NonPositiveDuration { name: "elevator.independent_combination_window" },
+        ]);
+        assert_eq!(problems(|config| config.elevator.independent_combination_window = Some(1.0)), []);
+    }
+
+    #[test]
+    fn priorities_must_be_hall_calls_in_the_building() {
+        let button = |floor, call| ButtonPriority { floor, call, level: 1 };
+        assert_eq!(problems(|config| {
+            config.priority.floors = BTreeMap::from([(1, 2), (4, 1)]);
+            config.priority.buttons = vec![button(5, Call::HallUp), button(2, Call::Cab)];
+        }), [
+            ConfigProblem::PriorityFloorOutOfRange { name: String::from("priority.floors"), floor: 4, num_floors: 4 },
+            ConfigProblem::PriorityFloorOutOfRange { name: String::from("priority.buttons[0]"), floor: 5, num_floors: 4 },
+            ConfigProblem::PriorityCabButton { entry: 1 },
+        ]);
+    }
+
+    #[test]
+    fn priority_of_a_button_overrides_the_priority_of_its_floor() {
+        let priority = PriorityConfig {
+            floors: BTreeMap::from([(1, 2)]),
+            buttons: vec![ButtonPriority { floor: 1, call: Call::HallDown, level: 3 }],
+        };
+        assert_eq!(priority.level(&Request::new(1, Call::HallDown)), 3);
+        assert_eq!(priority.level(&Request::new(1, Call::HallUp)), 2);
+        assert_eq!(priority.level(&Request::destination(1, 3)), 2);
+        assert_eq!(priority.level(&Request::new(0, Call::HallUp)), 0);
+    }
+
+    #[test]
+    fn cancel_durations_are_off_or_positive() {
+        assert_eq!(problems(|config| {
+            config.cancel.long_press = Some(0.0);
+            config.cancel.max_age = Some(-1.0);
+        }), [
+            ConfigProblem::NonPositiveDuration { name: "cancel.long_press" },
+            ConfigProblem::NonPositiveDuration { name: "cancel.max_age" },
+        ]);
+        assert_eq!(problems(|config| config.cancel.long_press = Some(0.5)), []);
+    }
+
+    #[test]
+    fn node_id_must_be_an_elevator() {
+        let config_file = ConfigFile::default();
+        assert_eq!(check_elevator_id(2, &config_file), []);
+        assert_eq!(check_elevator_id(3, &config_file), [ConfigProblem::IdOutOfRange { id: 3, num_elevators: 3 }]);
+    }
+}
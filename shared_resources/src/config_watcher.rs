@@ -0,0 +1,151 @@
+//! ----- CONFIG WATCHER -----
+//! This module watches the configuration files for changes and reloads the
+//! configuration when they are modified or the program receives SIGHUP.
+//! Only settings that are safe to change while running are applied, i.e.
+//! timing, the hall request assigner, logging, the status view and the API token.
+//! Changes to ports, the number of floors or elevators, the server port, the
+//! node's id and role, the log directory, metrics, the API, the journal and the
+//! load weighing require a restart and are rejected.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crossbeam_channel::{select, unbounded, Sender};
+use tracing::{error, info, warn};
+
+use crate::cli::Cli;
+use crate::config::{self, ApiConfigFile, ConfigFile, LayeredConfig, NodeConfig, ResolvedConfig};
+use crate::logging;
+
+const POLL_PERIOD: f64 = 1.0;
+
+/// Starts watching the configuration, sending the reloaded configuration of
+/// a node of type `T` to every subscriber whenever a setting is changed.
+/// Fails if the configuration can not be loaded, since the settings that
+/// require a restart would otherwise be taken from the defaults.
+pub fn watch<T: ResolvedConfig>(cli: Cli, subscribers: Vec<Sender<T>>) -> io::Result<()> {
+    let (reload_tx, reload_rx) = unbounded::<()>();
+    #[cfg(unix)]
+    {
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+        thread::Builder::new().name("config_sighup".to_string()).spawn(move || {
+            for _ in signals.forever() {
+                if reload_tx.send(()).is_err() {
+                    return
+                }
+            }
+        })?;
+    }
+    #[cfg(not(unix))]
+    drop(reload_tx);
+
+    let mut current = LayeredConfig::load_validated::<T>(&cli)
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .config_file;
+    thread::Builder::new().name("config_watcher".to_string()).spawn(move || {
+        let mut modified = modification_times(&cli);
+        loop {
+            select! {
+                recv(reload_rx) -> _ => (),
+                default(Duration::from_secs_f64(POLL_PERIOD)) => {
+                    let now_modified = modification_times(&cli);
+                    if now_modified == modified {
+                        continue;
+                    }
+                    modified = now_modified;
+                },
+            }
+
+            let reloaded = match LayeredConfig::load_validated::<T>(&cli) {
+                Ok(layered) => layered.config_file,
+                Err(e) => {
+                    error!("Keeping the current configuration, the reloaded configuration is invalid: {}", e);
+                    continue;
+                },
+            };
+            let (applied, rejected) = apply_reloadable(&current, &reloaded);
+            for path in rejected {
+                warn!("Ignoring change to {}, a restart is required for it to take effect", path);
+            }
+            let changed = changed_settings(&current, &applied);
+            if changed.is_empty() {
+                continue;
+            }
+            current = applied;
+            logging::reload(&NodeConfig::from_file(&current));
+            info!("Reloaded configuration, changed {}", changed.join(", "));
+            let resolved = T::resolve(&current);
+            for subscriber in &subscribers {
+                subscriber.send(resolved.clone()).ok();
+            }
+        }
+    })?;
+    Ok(())
+}
+
+fn modification_times(cli: &Cli) -> Vec<Option<SystemTime>> {
+    let files: Vec<PathBuf> = config::shared_file_path(cli).into_iter()
+        .chain(cli.config.node_file.clone())
+        .collect();
+    files.iter()
+        .map(|file_path| fs::metadata(file_path).and_then(|metadata| metadata.modified()).ok())
+        .collect()
+}
+
+/// Applies the settings of `reloaded` that are safe to change while running to
+/// `current`, returning the result along with the settings that were rejected.
+fn apply_reloadable(current: &ConfigFile, reloaded: &ConfigFile) -> (ConfigFile, Vec<String>) {
+    let mut applied = reloaded.clone();
+    applied.network = current.network.clone();
+    applied.server = current.server.clone();
+    applied.elevator = current.elevator.clone();
+    applied.node.id = current.node.id;
+    applied.node.role = current.node.role;
+    applied.logging.directory = current.logging.directory.clone();
+    applied.metrics = current.metrics.clone();
+    applied.api = ApiConfigFile { token: reloaded.api.token.clone(), ..current.api.clone() };
+    applied.journal = current.journal.clone();
+    // The slave reads the load settings once at startup
+    applied.load = current.load.clone();
+    let rejected = changed_settings(&applied, reloaded);
+    (applied, rejected)
+}
+
+fn changed_settings(before: &ConfigFile, after: &ConfigFile) -> Vec<String> {
+    let (mut before_values, mut after_values) = (BTreeMap::new(), BTreeMap::new());
+    config::collect_leaves(&serde_json::to_value(before).unwrap(), String::new(), &mut before_values);
+    config::collect_leaves(&serde_json::to_value(after).unwrap(), String::new(), &mut after_values);
+    after_values.into_iter()
+        .filter(|(path, value)| before_values.get(path) != Some(value))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use crate::config::BackupConfig;
+
+    #[test]
+    fn unreadable_configuration_fails_startup() {
+        let cli = Cli::parse_from(["backup", "--config", "missing-config.json"]);
+        assert!(watch::<BackupConfig>(cli, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn load_settings_require_a_restart() {
+        let current = ConfigFile::default();
+        let mut reloaded = current.clone();
+        reloaded.load.capacity = 1000.0;
+        reloaded.timing.door_open_duration = 5.0;
+        let (applied, rejected) = apply_reloadable(&current, &reloaded);
+        assert_eq!(applied.load.capacity, current.load.capacity);
+        assert_eq!(applied.timing.door_open_duration, 5.0);
+        assert_eq!(rejected, ["load.capacity"]);
+    }
+}
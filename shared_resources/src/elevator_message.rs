@@ -1,5 +1,31 @@
 use super::request::Request;
 
+/// Whether an elevator takes part in the normal service of the building
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceMode {
+    /// Serving hall and cab requests
+    #[default]
+    Normal,
+    /// Travelling non-stop to the recall floor of an emergency recall
+    Recalling,
+    /// Waiting at the recall floor with the doors open until the recall is reset
+    Recalled,
+    /// Taken out of group control, serving its cab requests only
+    Independent,
+}
+
+impl ServiceMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ServiceMode::Normal => "normal",
+            ServiceMode::Recalling => "recalling",
+            ServiceMode::Recalled => "recalled",
+            ServiceMode::Independent => "independent",
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct ElevatorMessage {
     pub id: String,
@@ -8,4 +34,20 @@ pub struct ElevatorMessage {
     pub direction: String,
     pub cab_requests: Vec<bool>,
     pub new_hall_orders: Vec<Request>,
+    /// Hall requests the passengers have cancelled, sent until the master has
+    /// cleared them
+    #[serde(default)]
+    pub cancelled_hall_orders: Vec<Request>,
+    /// Whether each floor is served by the elevator, indexed by floor. Slaves
+    /// that do not advertise it serve every floor.
+    #[serde(default)]
+    pub served_floors: Vec<bool>,
+    /// Load of the car in kilograms
+    #[serde(default)]
+    pub load: f64,
+    /// Whether the car is full, passing hall requests while it has passengers to drop off
+    #[serde(default)]
+    pub full: bool,
+    #[serde(default)]
+    pub mode: ServiceMode,
 }
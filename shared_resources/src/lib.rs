@@ -1,5 +1,10 @@
+pub mod cli;
 pub mod request;
 pub mod config;
+pub mod config_watcher;
 pub mod call;
+pub mod command_message;
 pub mod elevator_message;
+pub mod logging;
+pub mod metrics;
 pub mod request_buffer;
@@ -0,0 +1,69 @@
+//! ----- LOGGING -----
+//! This module sets up structured logging with tracing. Every event is written
+//! as JSON to a log file per node, and is only printed to the terminal when
+//! running headless, as the terminal is otherwise used by the status view.
+//! The log level and filter directives can be changed while running.
+
+use std::fs;
+use std::io;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+use crate::cli::LogLevel;
+use crate::config::NodeConfig;
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static TERMINAL_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Builds the filter from the node's log level and comma separated
+/// `target=level` directives, e.g. `network_rust=warn,master::modules::network=debug`.
+pub fn filter(log_level: LogLevel, directives: &str) -> Result<EnvFilter, ParseError> {
+    let mut filter = log_level.as_str().to_string();
+    if !directives.is_empty() {
+        filter.push(',');
+        filter.push_str(directives);
+    }
+    EnvFilter::builder().parse(filter)
+}
+
+/// Starts logging for the binary `name` to `<directory>/<name>-<id>.log`.
+pub fn init(name: &str, node: &NodeConfig) -> io::Result<()> {
+    fs::create_dir_all(&node.logging.directory)?;
+    let file_name = format!("{}-{}.log", name, node.id);
+    let file_writer = tracing_appender::rolling::never(&node.logging.directory, file_name);
+    let terminal_writer = io::stderr.with_filter(|_| TERMINAL_ENABLED.load(Ordering::Relaxed));
+
+    let filter = filter(node.log_level, &node.logging.filter).map_err(io::Error::other)?;
+    let (filter_layer, filter_handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer().json().with_span_list(true).with_thread_names(true).with_writer(file_writer))
+        .with(fmt::layer().with_writer(terminal_writer))
+        .try_init()
+        .map_err(io::Error::other)?;
+
+    FILTER_HANDLE.set(filter_handle).ok();
+    TERMINAL_ENABLED.store(node.headless, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Applies the log level, filter directives and terminal output of a reloaded configuration.
+pub fn reload(node: &NodeConfig) {
+    TERMINAL_ENABLED.store(node.headless, Ordering::Relaxed);
+    let Some(filter_handle) = FILTER_HANDLE.get() else {
+        return;
+    };
+    match filter(node.log_level, &node.logging.filter) {
+        Ok(filter) => {
+            if let Err(e) = filter_handle.reload(filter) {
+                tracing::error!("Could not reload the log filter: {}", e);
+            }
+        },
+        Err(e) => tracing::error!("Could not reload the log filter: {}", e),
+    }
+}
@@ -0,0 +1,48 @@
+//! ----- METRICS -----
+//! This module serves the metrics collected by a node in the Prometheus text
+//! format on `http://<address>:<port>/metrics`. Metrics are registered in the
+//! default registry by the modules collecting them.
+
+use std::thread;
+
+use prometheus::{Encoder, TextEncoder};
+use tiny_http::{Header, Response, Server};
+use tracing::{info, warn};
+
+use crate::config::MetricsConfig;
+
+/// Starts serving the metrics of this node, unless disabled in the configuration.
+/// Failing to start the server is logged, but does not stop the node.
+pub fn serve(config: &MetricsConfig) {
+    if !config.enabled {
+        return
+    }
+    let server = match Server::http((config.address.as_str(), config.port)) {
+        Ok(server) => server,
+        Err(e) => {
+            warn!(address = config.address, port = config.port, "Could not start the metrics server: {}", e);
+            return
+        },
+    };
+    info!(address = config.address, port = config.port, "Serving metrics");
+    let spawned = thread::Builder::new().name("metrics".to_string()).spawn(move || {
+        let content_type = Header::from_bytes("Content-Type", TextEncoder::new().format_type()).unwrap();
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                let mut body = Vec::new();
+                match TextEncoder::new().encode(&prometheus::gather(), &mut body) {
+                    Ok(()) => Response::from_data(body).with_header(content_type.clone()),
+                    Err(e) => Response::from_string(e.to_string()).with_status_code(500),
+                }
+            } else {
+                Response::from_string("Not found").with_status_code(404)
+            };
+            if let Err(e) = request.respond(response) {
+                warn!("Could not respond to metrics request: {}", e);
+            }
+        }
+    });
+    if let Err(e) = spawned {
+        warn!("Could not start the metrics server: {}", e);
+    }
+}
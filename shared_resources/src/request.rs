@@ -1,18 +1,274 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
 use driver_rust::elevio::poll;
+use tracing::{field, info_span, Span};
 
 use super::call::Call;
 
+/// Target of the events logged at each stage in the lifecycle of a hall request
+pub const LIFECYCLE_TARGET: &str = "request_lifecycle";
+
+/// Identifies a single hall request from the button press until it is served,
+/// serialized as 16 hexadecimal digits like in the logs
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(into = "String", try_from = "String")]
+pub struct TraceId(u64);
+
+impl TraceId {
+    /// Generates a new trace id, unique with high probability across all nodes
+    pub fn new() -> Self {
+        static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(nanos);
+        hasher.write_u64(SEQUENCE.fetch_add(1, Ordering::Relaxed));
+        TraceId(hasher.finish())
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        TraceId::new()
+    }
+}
+
+impl From<TraceId> for String {
+    fn from(trace_id: TraceId) -> Self {
+        trace_id.to_string()
+    }
+}
+
+impl TryFrom<String> for TraceId {
+    type Error = std::num::ParseIntError;
+
+    fn try_from(trace_id: String) -> Result<Self, Self::Error> {
+        trace_id.parse()
+    }
+}
+
+impl FromStr for TraceId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(trace_id: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(trace_id, 16).map(TraceId)
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Stages in the lifecycle of a hall request, in the order they normally happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// The hall button was pressed at a slave
+    Placed,
+    /// The master received the request from the slave
+    Received,
+    /// The backup stored the request
+    Stored,
+    /// The master received the backup's confirmation of the request
+    Acknowledged,
+    /// The master assigned the request to an elevator
+    Assigned,
+    /// The slave received the confirmed request from the master
+    Confirmed,
+    /// The elevator opened its doors to serve the request
+    Served,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Placed => "placed",
+            Stage::Received => "received",
+            Stage::Stored => "stored",
+            Stage::Acknowledged => "acknowledged",
+            Stage::Assigned => "assigned",
+            Stage::Confirmed => "confirmed",
+            Stage::Served => "served",
+        }
+    }
+
+    pub fn parse(stage: &str) -> Option<Self> {
+        Stage::iter().find(|candidate| candidate.as_str() == stage)
+    }
+
+    pub fn iter() -> impl Iterator<Item = Stage> {
+        [
+            Stage::Placed,
+            Stage::Received,
+            Stage::Stored,
+            Stage::Acknowledged,
+            Stage::Assigned,
+            Stage::Confirmed,
+            Stage::Served,
+        ].iter().copied()
+    }
+}
+
+/// A request for an elevator at `floor`. Destination calls also carry the
+/// floor the passenger is going to, which is `None` for every other call.
+/// Hall requests of a higher `priority` are served first, normal requests
+/// have priority 0.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Request {
     pub floor: u8,
-    pub call: Call
+    pub call: Call,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<TraceId>,
+    #[serde(default, skip_serializing_if = "is_normal_priority")]
+    pub priority: u8,
+}
+
+fn is_normal_priority(priority: &u8) -> bool {
+    *priority == 0
 }
 
 impl Request {
-    pub fn from_elev(call_button: poll::CallButton) -> Self {
+    pub fn new(floor: u8, call: Call) -> Self {
         Request {
-            floor: call_button.floor,
-            call: Call::from_elev_constant(call_button.call).unwrap(),
+            floor,
+            call,
+            destination: None,
+            trace_id: None,
+            priority: 0,
         }
     }
+
+    /// Destination call from `floor` to `destination`
+    pub fn destination(floor: u8, destination: u8) -> Self {
+        Request {
+            destination: Some(destination),
+            ..Request::new(floor, Call::Destination)
+        }
+    }
+
+    /// Checks that this is a hall request the master can place or cancel in a
+    /// building with `num_floors` floors, explaining what is wrong otherwise
+    pub fn validate(&self, num_floors: u8) -> Result<(), String> {
+        if self.floor >= num_floors {
+            return Err(format!("floor must be below {}", num_floors))
+        }
+        match (self.call, self.destination) {
+            (Call::Cab, _) => Err(String::from("only hall requests can be placed or cancelled")),
+            (Call::Destination, None) => Err(String::from("destination calls must have a destination")),
+            (Call::Destination, Some(destination)) if destination >= num_floors =>
+                Err(format!("destination must be below {}", num_floors)),
+            (Call::Destination, Some(destination)) if destination == self.floor =>
+                Err(String::from("destination must differ from floor")),
+            (Call::Destination, Some(_)) => Ok(()),
+            (_, Some(_)) => Err(String::from("only destination calls have a destination")),
+            (_, None) => Ok(()),
+        }
+    }
+
+    /// Number of columns in each floor of a hall request table, where the up
+    /// and down calls are followed by the destination calls to each floor
+    pub fn hall_columns(num_floors: u8) -> usize {
+        Call::num_hall_calls() as usize + num_floors as usize
+    }
+
+    /// Column of this hall request in the hall request table of its floor
+    pub fn column(&self) -> usize {
+        match (self.call, self.destination) {
+            (Call::Destination, Some(destination)) => Call::num_hall_calls() as usize + destination as usize,
+            (call, _) => call as usize,
+        }
+    }
+
+    /// The hall request at `column` of the hall request table of `floor`
+    pub fn from_column(floor: u8, column: usize, trace_id: Option<TraceId>) -> Self {
+        let request = match column {
+            0 => Request::new(floor, Call::HallUp),
+            1 => Request::new(floor, Call::HallDown),
+            _ => Request::destination(floor, (column - Call::num_hall_calls() as usize) as u8),
+        };
+        Request { trace_id, ..request }
+    }
+
+    /// The up or down hall call with the same direction of travel, which is
+    /// where the elevator picks up the passenger of a destination call
+    pub fn hall_call(&self) -> Call {
+        match (self.call, self.destination) {
+            (Call::Destination, Some(destination)) if destination < self.floor => Call::HallDown,
+            (Call::Destination, _) => Call::HallUp,
+            (call, _) => call,
+        }
+    }
+
+    pub fn from_elev(call_button: poll::CallButton) -> Self {
+        Request::new(
+            call_button.floor,
+            Call::from_elev_constant(call_button.call).unwrap(),
+        )
+    }
+
+    /// Span grouping the events in the lifecycle of this request
+    pub fn span(&self) -> Span {
+        let span = info_span!("request", floor = self.floor, call = ?self.call, destination = field::Empty, priority = field::Empty, trace_id = field::Empty);
+        if let Some(destination) = self.destination {
+            span.record("destination", destination);
+        }
+        if self.priority > 0 {
+            span.record("priority", self.priority);
+        }
+        if let Some(trace_id) = self.trace_id {
+            span.record("trace_id", field::display(trace_id));
+        }
+        span
+    }
+
+    /// Logs that this request has reached `stage` of its lifecycle
+    pub fn log_stage(&self, stage: Stage) {
+        self.span().in_scope(|| {
+            tracing::info!(target: LIFECYCLE_TARGET, stage = stage.as_str(), "Hall request {}", stage.as_str())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUM_FLOORS: u8 = 4;
+
+    #[test]
+    fn hall_calls_inside_the_building_are_valid() {
+        assert!(Request::new(0, Call::HallUp).validate(NUM_FLOORS).is_ok());
+        assert!(Request::new(3, Call::HallDown).validate(NUM_FLOORS).is_ok());
+        assert!(Request::destination(1, 3).validate(NUM_FLOORS).is_ok());
+    }
+
+    #[test]
+    fn cab_requests_are_rejected() {
+        assert!(Request::new(1, Call::Cab).validate(NUM_FLOORS).is_err());
+    }
+
+    #[test]
+    fn floors_outside_the_building_are_rejected() {
+        assert!(Request::new(NUM_FLOORS, Call::HallUp).validate(NUM_FLOORS).is_err());
+        assert!(Request::destination(NUM_FLOORS, 0).validate(NUM_FLOORS).is_err());
+        assert!(Request::destination(0, NUM_FLOORS).validate(NUM_FLOORS).is_err());
+    }
+
+    #[test]
+    fn destinations_must_match_the_call() {
+        assert!(Request::new(1, Call::Destination).validate(NUM_FLOORS).is_err());
+        assert!(Request::destination(2, 2).validate(NUM_FLOORS).is_err());
+        let up_with_destination = Request { destination: Some(3), ..Request::new(1, Call::HallUp) };
+        assert!(up_with_destination.validate(NUM_FLOORS).is_err());
+    }
 }
@@ -1,23 +1,46 @@
+use std::sync::LazyLock;
 use std::time::{Instant, Duration};
 
+use prometheus::{register_int_counter_vec, register_int_gauge_vec, IntCounterVec, IntGaugeVec};
+use tracing::debug;
+
 use crate::request::Request;
 
+static BUFFER_LENGTH: LazyLock<IntGaugeVec> = LazyLock::new(|| register_int_gauge_vec!(
+    "request_buffer_length",
+    "Number of requests waiting for confirmation",
+    &["buffer"]
+).unwrap());
+
+static BUFFER_TIMEOUTS: LazyLock<IntCounterVec> = LazyLock::new(|| register_int_counter_vec!(
+    "request_buffer_timeouts_total",
+    "Number of requests that timed out before being confirmed",
+    &["buffer"]
+).unwrap());
+
 #[derive(Debug, Clone)]
 pub struct RequestBuffer {
+    name: &'static str,
     new_requests: Vec<Request>,
     new_request_timers: Vec<Instant>,
-    timeout: u64,
+    timeout: Duration,
 }
 
 impl RequestBuffer {
-    pub fn new(timeout: u64) -> Self {
+    /// Creates an empty buffer, where `name` labels the metrics of the buffer
+    pub fn new(name: &'static str, timeout: Duration) -> Self {
         RequestBuffer { 
+            name,
             new_requests: Vec::new(), 
             new_request_timers: Vec::new(),
-            timeout: timeout,
+            timeout,
         }
     }
 
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
     pub fn get_new_requests(&self) -> Vec<Request> {
         self.new_requests.clone()
     }
@@ -25,25 +48,98 @@ impl RequestBuffer {
     pub fn insert_new_request(&mut self, request: Request) {
         self.new_requests.push(request);
         self.new_request_timers.push(Instant::now());
+        self.update_length_metric();
+    }
+
+    /// Forgets every buffered request without resending it
+    pub fn clear(&mut self) {
+        self.new_requests.clear();
+        self.new_request_timers.clear();
+        self.update_length_metric();
     }
 
-    pub fn remove_confirmed_requests(&mut self, all_hall_requests: &Vec<Vec<bool>>) {
+    /// Forgets the buffered requests for the same floor and column as `request`
+    pub fn remove_request(&mut self, request: &Request) {
         for index in (0..self.new_requests.len()).rev() {
-            let floor = self.new_requests[index].floor;
-            let call = self.new_requests[index].call;
-            if all_hall_requests[floor as usize][call as usize] {
+            if self.new_requests[index].floor == request.floor && self.new_requests[index].column() == request.column() {
                 self.new_requests.remove(index);
                 self.new_request_timers.remove(index);
             }
         }
+        self.update_length_metric();
+    }
+
+    /// Removes the requests that are set in `all_hall_requests`, returning the removed requests
+    pub fn remove_confirmed_requests(&mut self, all_hall_requests: &[Vec<bool>]) -> Vec<Request> {
+        let mut confirmed_requests = Vec::new();
+        for index in (0..self.new_requests.len()).rev() {
+            let floor = self.new_requests[index].floor;
+            let column = self.new_requests[index].column();
+            if all_hall_requests[floor as usize].get(column).copied().unwrap_or(false) {
+                self.new_requests[index].span().in_scope(|| debug!("Buffered request confirmed"));
+                confirmed_requests.push(self.new_requests.remove(index));
+                self.new_request_timers.remove(index);
+            }
+        }
+        self.update_length_metric();
+        confirmed_requests
     }
 
     pub fn remove_timed_out_orders(&mut self) {
         for index in (0..self.new_requests.len()).rev() {
-            if self.new_request_timers[index].elapsed() > Duration::from_secs(self.timeout) {
+            if self.new_request_timers[index].elapsed() > self.timeout {
+                self.new_requests[index].span().in_scope(|| debug!("Buffered request timed out"));
+                BUFFER_TIMEOUTS.with_label_values(&[self.name]).inc();
                 self.new_requests.remove(index);
                 self.new_request_timers.remove(index);
             }
         }
+        self.update_length_metric();
+    }
+
+    fn update_length_metric(&self) {
+        BUFFER_LENGTH.with_label_values(&[self.name]).set(self.new_requests.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call::Call;
+
+    fn hall_requests(set: &[(usize, usize)]) -> Vec<Vec<bool>> {
+        let mut hall_requests = vec![vec![false; 2]; 4];
+        for &(floor, call) in set {
+            hall_requests[floor][call] = true;
+        }
+        hall_requests
+    }
+
+    #[test]
+    fn confirmed_requests_are_removed_and_returned() {
+        let mut buffer = RequestBuffer::new("test_confirmed", Duration::from_secs(60));
+        buffer.insert_new_request(Request::new(1, Call::HallUp));
+        buffer.insert_new_request(Request::new(2, Call::HallDown));
+        buffer.insert_new_request(Request::new(3, Call::HallDown));
+
+        let confirmed = buffer.remove_confirmed_requests(&hall_requests(&[(2, 1), (1, 1)]));
+
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(confirmed[0].floor, 2);
+        let remaining: Vec<u8> = buffer.get_new_requests().iter().map(|request| request.floor).collect();
+        assert_eq!(remaining, vec![1, 3]);
+    }
+
+    #[test]
+    fn requests_are_kept_until_they_time_out() {
+        let mut buffer = RequestBuffer::new("test_timeout", Duration::from_secs(60));
+        buffer.insert_new_request(Request::new(0, Call::HallUp));
+        buffer.remove_timed_out_orders();
+        assert_eq!(buffer.get_new_requests().len(), 1);
+
+        buffer.set_timeout(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(1));
+        buffer.remove_timed_out_orders();
+        assert!(buffer.get_new_requests().is_empty());
     }
 }
@@ -1,2 +1,2 @@
-mod utilities;
+pub mod utilities;
 pub mod modules;
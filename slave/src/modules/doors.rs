@@ -1,20 +1,27 @@
-/// ----- DOORS MODULE -----
-/// This module is responsible for managing the door open light
-/// and keeping track of how long the door has been open for, to
-/// generate the doorClosing event for the state machine.
+//! ----- DOORS MODULE -----
+//! This module is responsible for managing the door open light
+//! and keeping track of how long the door has been open for, to
+//! generate the doorClosing event for the state machine.
+//! The doors are kept open while obstructed or held open by the state machine.
 
 use std::time::Duration;
 
 use crossbeam_channel::{select, Sender, Receiver};
 
+use shared_resources::config::SlaveConfig;
+
 pub fn main(
+    config: SlaveConfig,
+    config_rx: Receiver<SlaveConfig>,
     obstruction_rx: Receiver<bool>,
     doors_activate_rx: Receiver<bool>,
+    doors_hold_rx: Receiver<bool>,
     doors_closing_tx: Sender<bool>, 
     door_light_tx: Sender<bool>
 ) {
-    const TIMER_DURATION: f64 = 3.0;
+    let mut timer_duration = Duration::from_secs_f64(config.timing.door_open_duration);
     let mut active: bool = false;
+    let mut held = false;
 
     loop {
         select! {
@@ -24,8 +31,14 @@ pub fn main(
             recv(doors_activate_rx) -> _ => {
                 door_light_tx.send(true).unwrap();
             },
-            default(Duration::from_secs_f64(TIMER_DURATION)) => {
-                if !active {
+            recv(doors_hold_rx) -> msg => {
+                held = msg.unwrap();
+            },
+            recv(config_rx) -> msg => {
+                timer_duration = Duration::from_secs_f64(msg.unwrap().timing.door_open_duration);
+            },
+            default(timer_duration) => {
+                if !active && !held {
                     doors_closing_tx.send(true).unwrap();
                     door_light_tx.send(false).unwrap();
                 }
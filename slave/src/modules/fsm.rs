@@ -1,59 +1,174 @@
-/// ----- FSM MODULE -----
-/// This module is the finite state machine controlling the elevator.
-/// It receives events from other modules and switches states based
-/// on these.
+//! ----- FSM MODULE -----
+//! This module is the finite state machine controlling the elevator.
+//! It receives events from other modules and switches states based
+//! on these.
+
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::{select, Receiver, Sender, unbounded};
+use prometheus::{register_histogram_vec, HistogramVec};
+use tracing::{info, warn};
 
 use shared_resources::call::Call;
-use shared_resources::request::Request;
+use shared_resources::config::{LoadConfig, LoadSensor};
+use shared_resources::elevator_message::ServiceMode;
+use shared_resources::request::{Request, Stage, TraceId};
 
 use crate::utilities::direction::Direction;
 use crate::utilities::elevator_status::{ElevatorStatus, Behaviour};
 use crate::utilities::master_message::MasterMessage;
 
+static STATE_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| register_histogram_vec!(
+    "fsm_state_duration_seconds",
+    "Time spent in each state of the elevator before switching state",
+    &["state"],
+    vec![0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0]
+).unwrap());
+
+#[allow(clippy::too_many_arguments)]
 pub fn main(
     num_floors: u8,
+    // Whether each floor is served by this elevator, cab requests to other floors are rejected
+    served_floors: Vec<bool>,
+    // Time within which the cab buttons of the bottom and top floors must both be pressed
+    // to switch independent service, the button combination is off without it
+    independent_combination_window: Option<Duration>,
+    load_config: LoadConfig,
     backup_data: ElevatorStatus,
     floor_sensor_rx: Receiver<u8>,
     floor_indicator_tx: Sender<u8>,
     button_light_tx: Sender<(Request,bool)>,
     doors_closing_rx: Receiver<bool>,
     doors_activate_tx: Sender<bool>,
+    // Keeps the doors open at the recall floor until the recall is reset
+    doors_hold_tx: Sender<bool>,
     cab_button_rx: Receiver<u8>,
+    // Only the `file` load sensor sends readings, the estimated load is updated here
+    load_rx: Receiver<f64>,
     motor_direction_tx: Sender<Direction>,
     master_hall_requests_rx: Receiver<MasterMessage>,
-    elevator_status_tx: Sender<ElevatorStatus>,
+    // Every receiver gets each status, the network and the debug view both need them
+    elevator_status_txs: Vec<Sender<ElevatorStatus>>,
 ) {
     let (new_request_tx, new_request_rx) = unbounded::<bool>();
 
     let mut elevator = backup_data;
+    let mut state_entered = Instant::now();
+    let mut trace_ids: Vec<Vec<Option<TraceId>>> = Vec::new();
+    let mut parking_floor: Option<u8> = None;
+    // Whether the elevator is moving to its parking floor rather than serving requests
+    let mut parking = false;
+    // Whether the parking trip was abandoned for a request, since the last floor
+    let mut abandoned_parking = false;
+    // Floor to travel to non-stop while recalled, not known after a restart until the master tells
+    let mut recall_floor: Option<u8> = None;
+    let mut operator_independent = false;
+    // The first cab button of the combination switching independent service, when it was
+    // pressed and whether it added a cab request
+    let mut combination_start: Option<(u8, Instant, bool)> = None;
 
     if elevator.behaviour == Behaviour::Moving {
         motor_direction_tx.send(elevator.direction).unwrap();
     } else if elevator.behaviour == Behaviour::DoorOpen {
         doors_activate_tx.send(true).unwrap();
     }
+    if elevator.mode == ServiceMode::Recalled {
+        doors_hold_tx.send(true).unwrap();
+    }
 
     loop {
+        let previous_behaviour = elevator.behaviour.clone();
         select! {
             // channels for receiving requests from other modules => generates the new_request event
             recv(cab_button_rx) -> msg => {
                 let destination = msg.unwrap();
-                elevator.requests.add_request(destination, Call::Cab);
-                new_request_tx.send(true).unwrap();
-                button_light_tx.send((Request{ floor: destination, call: Call::Cab }, true)).unwrap();
+                if matches!(elevator.mode, ServiceMode::Recalling | ServiceMode::Recalled) {
+                    info!(floor = destination, "Cab request ignored, the elevator is recalled");
+                    continue;
+                }
+                let ends = [0, num_floors - 1];
+                let combination = combination_start.take().filter(|&(first, pressed_at, _)| {
+                    first != destination && ends.contains(&destination)
+                        && independent_combination_window.is_some_and(|window| pressed_at.elapsed() < window)
+                });
+                if let Some((first, _, added)) = combination {
+                    // The buttons were pressed to switch the mode, not to travel
+                    if added {
+                        elevator.requests.clear_request(Call::Cab, first);
+                        button_light_tx.send((Request::new(first, Call::Cab), false)).unwrap();
+                    }
+                    elevator.independent_switch = !elevator.independent_switch;
+                    info!(independent = elevator.independent_switch, "Independent service switched at the car");
+                } else {
+                    if !served_floors.get(destination as usize).copied().unwrap_or(false) {
+                        warn!(floor = destination, "Cab request rejected, the elevator does not serve the floor");
+                        continue;
+                    }
+                    if ends.contains(&destination) && independent_combination_window.is_some() {
+                        let added = !elevator.requests.cab_request_at_floor(destination);
+                        combination_start = Some((destination, Instant::now(), added));
+                    }
+                    elevator.requests.add_request(destination, Call::Cab);
+                    new_request_tx.send(true).unwrap();
+                    button_light_tx.send((Request::new(destination, Call::Cab), true)).unwrap();
+                }
+            },
+            recv(load_rx) -> msg => {
+                elevator.load = msg.unwrap();
             },
             recv(master_hall_requests_rx) -> msg => {
                 let message = msg.unwrap();
-                elevator.requests.update_hall_requests(message.our_hall_requests);
-                if elevator.requests.has_unserved_requests() {
-                    new_request_tx.send(true).unwrap();
+                match (message.recall_floor, elevator.mode) {
+                    (Some(floor), ServiceMode::Normal | ServiceMode::Independent) => {
+                        warn!(floor, "Recalled, cancelling all requests");
+                        for destination in 0..num_floors {
+                            button_light_tx.send((Request::new(destination, Call::Cab), false)).unwrap();
+                        }
+                        elevator.clear_requests(num_floors);
+                        elevator.mode = ServiceMode::Recalling;
+                        parking = false;
+                        abandoned_parking = false;
+                    },
+                    // The recall floor was moved, the doors close and the elevator goes there instead
+                    (Some(floor), ServiceMode::Recalled) if floor != elevator.floor => {
+                        warn!(floor, "Recall floor changed");
+                        elevator.mode = ServiceMode::Recalling;
+                        doors_hold_tx.send(false).unwrap();
+                    },
+                    (None, ServiceMode::Recalling | ServiceMode::Recalled) => {
+                        info!("Recall reset, back in normal service");
+                        elevator.mode = ServiceMode::Normal;
+                        doors_hold_tx.send(false).unwrap();
+                    },
+                    _ => {},
+                }
+                recall_floor = message.recall_floor;
+                operator_independent = message.independent;
+                trace_ids = message.trace_ids;
+                // Recalled elevators and elevators in independent service are neither given hall requests nor parked
+                if elevator.mode != ServiceMode::Normal {
+                    if elevator.mode == ServiceMode::Recalling && elevator.behaviour == Behaviour::Idle {
+                        continue_recall(&mut elevator, recall_floor, &motor_direction_tx, &doors_activate_tx, &doors_hold_tx);
+                    }
+                } else if !operator_independent {
+                    elevator.requests.update_hall_requests(message.our_hall_requests);
+                    parking_floor = message.parking_floor;
+                    if elevator.requests.has_unserved_requests() {
+                        new_request_tx.send(true).unwrap();
+                    } else if elevator.behaviour == Behaviour::Idle {
+                        if let Some(direction) = direction_to(&elevator, parking_floor) {
+                            elevator.direction = direction;
+                            motor_direction_tx.send(direction).unwrap();
+                            elevator.behaviour = Behaviour::Moving;
+                            parking = true;
+                        }
+                    }
                 }
                 for floor in 0..num_floors {
                     for call in Call::iter_hall() {
                         button_light_tx.send((
-                            Request{ floor: floor, call: call }, 
+                            Request::new(floor, call), 
                             message.all_hall_requests[floor as usize][call as usize],
                         )).unwrap();
                     }
@@ -61,20 +176,20 @@ pub fn main(
             },
             // channels for events in the state machine
             recv(new_request_rx) -> _ => {
+                // A parking elevator can not stop between floors, and serves the request from the next floor
+                abandoned_parking |= parking;
+                parking = false;
                 elevator.behaviour = match elevator.behaviour {
                     Behaviour::Idle => {
                         elevator.update_direction();
                         if elevator.requests_at_this_floor() {
                             doors_activate_tx.send(true).unwrap();
-                            elevator.serve_requests_here();
-                            button_light_tx.send((Request {
-                                floor: elevator.floor,
-                                call: Call::Cab
-                            }, false)).unwrap();
-                            button_light_tx.send((Request { 
-                                floor: elevator.floor, 
-                                call: elevator.direction.to_call().unwrap()
-                            }, false)).unwrap();
+                            log_served_hall_request(&elevator, &trace_ids);
+                            for destination in elevator.serve_requests_here() {
+                                button_light_tx.send((Request::new(destination, Call::Cab), true)).unwrap();
+                            }
+                            button_light_tx.send((Request::new(elevator.floor, Call::Cab), false)).unwrap();
+                            button_light_tx.send((Request::new(elevator.floor, elevator.direction.to_call().unwrap()), false)).unwrap();
                             Behaviour::DoorOpen
                         } else if elevator.requests.has_unserved_requests() {
                             motor_direction_tx.send(elevator.direction).unwrap();
@@ -87,20 +202,40 @@ pub fn main(
             recv(floor_sensor_rx) -> msg => {
                 elevator.floor = msg.unwrap();
                 floor_indicator_tx.send(elevator.floor).unwrap();
-                if elevator.should_stop() {
+                let abandoned = std::mem::take(&mut abandoned_parking);
+                if elevator.mode == ServiceMode::Recalling {
+                    if elevator.behaviour == Behaviour::Moving {
+                        continue_recall(&mut elevator, recall_floor, &motor_direction_tx, &doors_activate_tx, &doors_hold_tx);
+                    }
+                } else if parking {
+                    match direction_to(&elevator, parking_floor) {
+                        Some(direction) => if direction != elevator.direction {
+                            elevator.direction = direction;
+                            motor_direction_tx.send(direction).unwrap();
+                        },
+                        // Parked, or the parking floor was taken away, the doors stay closed
+                        None => {
+                            motor_direction_tx.send(Direction::Stop).unwrap();
+                            elevator.behaviour = Behaviour::Idle;
+                            parking = false;
+                        },
+                    }
+                } else if abandoned && elevator.should_stop() && !elevator.requests_at_this_floor() {
+                    // The request is behind the elevator, which turns around without opening the doors
+                    motor_direction_tx.send(Direction::Stop).unwrap();
+                    elevator.behaviour = Behaviour::Idle;
+                    new_request_tx.send(true).unwrap();
+                } else if elevator.should_stop() {
                     elevator.behaviour = match elevator.behaviour {
                         Behaviour::Moving => {
                             motor_direction_tx.send(Direction::Stop).unwrap();
                             doors_activate_tx.send(true).unwrap();
-                            elevator.serve_requests_here();
-                            button_light_tx.send((Request { 
-                                floor: elevator.floor, 
-                                call: Call::Cab 
-                            }, false)).unwrap();
-                            button_light_tx.send((Request { 
-                                floor: elevator.floor, 
-                                call: elevator.direction.to_call().unwrap()
-                            }, false)).unwrap();
+                            log_served_hall_request(&elevator, &trace_ids);
+                            for destination in elevator.serve_requests_here() {
+                                button_light_tx.send((Request::new(destination, Call::Cab), true)).unwrap();
+                            }
+                            button_light_tx.send((Request::new(elevator.floor, Call::Cab), false)).unwrap();
+                            button_light_tx.send((Request::new(elevator.floor, elevator.direction.to_call().unwrap()), false)).unwrap();
                             Behaviour::DoorOpen
                         },
                         _ => elevator.behaviour,
@@ -108,33 +243,131 @@ pub fn main(
                 }
             },
             recv(doors_closing_rx) -> _ => {
-                elevator.behaviour = match elevator.behaviour {
-                    Behaviour::DoorOpen => {
-                        elevator.update_direction();
-                        if elevator.should_stop() && elevator.requests_at_this_floor() {
-                            doors_activate_tx.send(true).unwrap();
-                            elevator.serve_requests_here();
-                            button_light_tx.send((Request {
-                                floor: elevator.floor,
-                                call: Call::Cab
-                            }, false)).unwrap();
-                            button_light_tx.send((Request { 
-                                floor: elevator.floor, 
-                                call: elevator.direction.to_call().unwrap()
-                            }, false)).unwrap();
-                            Behaviour::DoorOpen
-                        } else if elevator.requests.has_unserved_requests() {
-                            motor_direction_tx.send(elevator.direction).unwrap();
-                            Behaviour::Moving
-                        } else {
-                            Behaviour::Idle
-                        }
-                    },
-                    Behaviour::Idle | Behaviour::Moving => elevator.behaviour,
+                if matches!(elevator.mode, ServiceMode::Recalling | ServiceMode::Recalled) {
+                    if elevator.behaviour == Behaviour::DoorOpen {
+                        continue_recall(&mut elevator, recall_floor, &motor_direction_tx, &doors_activate_tx, &doors_hold_tx);
+                    }
+                } else {
+                    elevator.behaviour = match elevator.behaviour {
+                        Behaviour::DoorOpen => {
+                            elevator.update_direction();
+                            if elevator.should_stop() && elevator.requests_at_this_floor() {
+                                doors_activate_tx.send(true).unwrap();
+                                log_served_hall_request(&elevator, &trace_ids);
+                                for destination in elevator.serve_requests_here() {
+                                    button_light_tx.send((Request::new(destination, Call::Cab), true)).unwrap();
+                                }
+                                button_light_tx.send((Request::new(elevator.floor, Call::Cab), false)).unwrap();
+                                button_light_tx.send((Request::new(elevator.floor, elevator.direction.to_call().unwrap()), false)).unwrap();
+                                Behaviour::DoorOpen
+                            } else if elevator.requests.has_unserved_requests() {
+                                motor_direction_tx.send(elevator.direction).unwrap();
+                                Behaviour::Moving
+                            } else {
+                                Behaviour::Idle
+                            }
+                        },
+                        Behaviour::Idle | Behaviour::Moving => elevator.behaviour,
+                    }
                 }
             },
             
         }
-        elevator_status_tx.send(elevator.clone()).unwrap();
+        // A recall overrides independent service, which is taken up again once the recall is reset
+        let independent = elevator.independent_switch || operator_independent;
+        if elevator.mode == ServiceMode::Normal && independent {
+            info!("Independent service, leaving the hall requests to the other elevators");
+            elevator.mode = ServiceMode::Independent;
+            elevator.requests.update_hall_requests(vec![vec![false; Request::hall_columns(num_floors)]; num_floors as usize]);
+            abandoned_parking |= parking;
+            parking = false;
+        } else if elevator.mode == ServiceMode::Independent && !independent {
+            info!("Back under group control");
+            elevator.mode = ServiceMode::Normal;
+        }
+        if load_config.sensor == LoadSensor::Estimated {
+            let passengers = elevator.requests.get_cab_requests().iter().filter(|&&active| active).count();
+            elevator.load = passengers as f64 * load_config.passenger_weight;
+        }
+        let full = elevator.load >= load_config.full_share * load_config.capacity;
+        if full != elevator.full {
+            elevator.full = full;
+            info!(load = elevator.load, full, "Car load changed");
+        }
+        if elevator.behaviour != previous_behaviour {
+            STATE_DURATION
+                .with_label_values(&[previous_behaviour.as_string()])
+                .observe(state_entered.elapsed().as_secs_f64());
+            state_entered = Instant::now();
+        }
+        for elevator_status_tx in &elevator_status_txs {
+            elevator_status_tx.send(elevator.clone()).unwrap();
+        }
+    }
+}
+
+/// Direction to move in to reach the floor, if the elevator is not there already
+fn direction_to(elevator: &ElevatorStatus, floor: Option<u8>) -> Option<Direction> {
+    match floor? {
+        floor if floor > elevator.floor => Some(Direction::Up),
+        floor if floor < elevator.floor => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+/// Moves the elevator on towards the recall floor without stopping, or opens and
+/// holds the doors once there. Without a recall floor the elevator waits, stopped.
+fn continue_recall(
+    elevator: &mut ElevatorStatus,
+    recall_floor: Option<u8>,
+    motor_direction_tx: &Sender<Direction>,
+    doors_activate_tx: &Sender<bool>,
+    doors_hold_tx: &Sender<bool>,
+) {
+    let Some(floor) = recall_floor else {
+        if elevator.behaviour == Behaviour::Moving {
+            motor_direction_tx.send(Direction::Stop).unwrap();
+            elevator.behaviour = Behaviour::Idle;
+        }
+        return
+    };
+    match direction_to(elevator, Some(floor)) {
+        Some(direction) => {
+            if elevator.behaviour != Behaviour::Moving || direction != elevator.direction {
+                motor_direction_tx.send(direction).unwrap();
+            }
+            elevator.direction = direction;
+            elevator.behaviour = Behaviour::Moving;
+            elevator.mode = ServiceMode::Recalling;
+        },
+        None => {
+            if elevator.mode != ServiceMode::Recalled {
+                info!(floor, "Arrived at the recall floor");
+            }
+            motor_direction_tx.send(Direction::Stop).unwrap();
+            doors_activate_tx.send(true).unwrap();
+            doors_hold_tx.send(true).unwrap();
+            elevator.behaviour = Behaviour::DoorOpen;
+            elevator.mode = ServiceMode::Recalled;
+        },
+    }
+}
+
+/// Logs the hall requests served by opening the doors at the current floor,
+/// including the destination calls of the passengers picked up, if any
+fn log_served_hall_request(elevator: &ElevatorStatus, trace_ids: &[Vec<Option<TraceId>>]) {
+    let Some(call) = elevator.direction.to_call() else {
+        return
+    };
+    let mut served = Vec::new();
+    if elevator.requests.get_requests_at_floor(elevator.floor)[call as usize] {
+        served.push(Request::new(elevator.floor, call));
+    }
+    for destination in elevator.requests.destinations_at_floor(elevator.floor, elevator.direction) {
+        served.push(Request::destination(elevator.floor, destination));
+    }
+    for mut request in served {
+        request.trace_id = trace_ids.get(elevator.floor as usize).and_then(|calls| calls.get(request.column()).copied().flatten());
+        request.log_stage(Stage::Served);
     }
 }
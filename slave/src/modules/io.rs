@@ -1,50 +1,63 @@
-/// ----- I/O MODULE -----
-/// This module is responsible for polling the sensors on the elevator,
-/// returning channels for other modules to listen to, as well as channels
-/// for sending commands to the elevator driver. 
+//! ----- I/O MODULE -----
+//! This module is responsible for polling the sensors on the elevator,
+//! returning channels for other modules to listen to, as well as channels
+//! for sending commands to the elevator driver. 
 
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use std::thread;
 
-use crossbeam_channel::{unbounded, Sender, Receiver};
+use crossbeam_channel::{never, unbounded, Sender, Receiver};
 use driver_rust::elevio::{poll, elev};
+use tracing::warn;
 
 use shared_resources::config;
 use shared_resources::call::Call;
-use shared_resources::request::Request;
+use shared_resources::request::{Request, TraceId};
 
 use crate::utilities::direction::Direction;
 
+/// The channels to and from the elevator driver
+pub struct ElevatorIo {
+    pub cab_button_rx: Receiver<u8>,
+    pub hall_button_rx: Receiver<Request>,
+    /// Hall buttons released, with how long they were held
+    pub hall_button_release_rx: Receiver<(Request, Duration)>,
+    pub floor_sensor_rx: Receiver<u8>,
+    pub stop_button_rx: Receiver<bool>,
+    pub obstruction_rx: Receiver<bool>,
+    /// Load of the car in kilograms, only for the `file` load sensor
+    pub load_rx: Receiver<f64>,
+    pub stop_button_light_tx: Sender<bool>,
+    pub button_light_tx: Sender<(Request, bool)>,
+    pub motor_direction_tx: Sender<Direction>,
+    pub door_light_tx: Sender<bool>,
+    pub floor_indicator_tx: Sender<u8>,
+}
+
 pub fn init(
     server_config: config::ServerConfig,
     elevator_settings: config::ElevatorConfig,
-) -> std::io::Result<(
-    Receiver<u8>, 
-    Receiver<Request>, 
-    Receiver<u8>,
-    Receiver<bool>,
-    Receiver<bool>,
-    Sender<bool>,
-    Sender<(Request,bool)>,
-    Sender<Direction>,
-    Sender<bool>,
-    Sender<u8>
-)> { 
+    load_config: config::LoadConfig,
+) -> std::io::Result<ElevatorIo> { 
     let serveraddr = "localhost:".to_owned() + &server_config.port.to_string();
     let elevator = elev::Elevator::init(serveraddr.as_str(), elevator_settings.num_floors).unwrap();
 
     let poll_period = Duration::from_millis(25);
     let (cab_button_tx, cab_button_rx) = unbounded();
     let (hall_button_tx, hall_button_rx) = unbounded();
+    let (hall_button_release_tx, hall_button_release_rx) = unbounded();
     {
         let (call_button_tx, call_button_rx) = unbounded();
         let elevator = elevator.clone();
-        thread::Builder::new().name("pull_call_buttons".to_string()).spawn(move || poll::call_buttons(elevator, call_button_tx, poll_period))?;
+        let num_floors = elevator_settings.num_floors;
+        thread::Builder::new().name("pull_call_buttons".to_string())
+            .spawn(move || poll_call_buttons(elevator, num_floors, call_button_tx, hall_button_release_tx, poll_period))?;
         thread::Builder::new().name("call_buttons".to_string()).spawn(move || { loop {
             let button_call = Request::from_elev(call_button_rx.recv().unwrap());
             match button_call.call {
                 Call::Cab => cab_button_tx.send(button_call.floor).unwrap(),
-                _ => hall_button_tx.send(button_call).unwrap(),
+                _ => hall_button_tx.send(Request { trace_id: Some(TraceId::new()), ..button_call }).unwrap(),
             }
         }})?;
     }
@@ -61,6 +74,16 @@ pub fn init(
         thread::Builder::new().name("stop_button".to_string()).spawn(move || poll::stop_button(elevator, stop_button_tx, poll_period))?;
     }
 
+    let load_rx = match load_config.sensor {
+        config::LoadSensor::File => {
+            let (load_tx, load_rx) = unbounded();
+            let file = load_config.file;
+            thread::Builder::new().name("load_sensor".to_string()).spawn(move || poll_load(file, load_tx, poll_period))?;
+            load_rx
+        },
+        config::LoadSensor::None | config::LoadSensor::Estimated => never(),
+    };
+
     let (stop_button_light_tx, stop_button_light_rx) = unbounded();
     {
         let elevator = elevator.clone();
@@ -81,7 +104,9 @@ pub fn init(
         let elevator = elevator.clone();
         thread::Builder::new().name("button_light".to_string()).spawn(move || { loop {
             let (request, on): (Request, bool) = button_light_rx.recv().unwrap();
-            elevator.call_button_light(request.floor, request.call.as_elev_constant(), on);
+            if let Some(button) = request.call.as_elev_constant() {
+                elevator.call_button_light(request.floor, button, on);
+            }
         }})?;
     }
 
@@ -117,14 +142,85 @@ pub fn init(
         motor_direction_tx.send(Direction::Down).unwrap();
     }
     
-    Ok((cab_button_rx, 
-     hall_button_rx, 
-     floor_sensor_rx, 
-     stop_button_rx, 
-     obstruction_rx,
-     stop_button_light_tx,
-     button_light_tx,
-     motor_direction_tx,
-     door_light_tx,
-     floor_indicator_tx))
+    Ok(ElevatorIo {
+        cab_button_rx,
+        hall_button_rx,
+        hall_button_release_rx,
+        floor_sensor_rx,
+        stop_button_rx,
+        obstruction_rx,
+        load_rx,
+        stop_button_light_tx,
+        button_light_tx,
+        motor_direction_tx,
+        door_light_tx,
+        floor_indicator_tx,
+    })
+}
+
+/// Polls the call buttons like the driver does, sending each button when it is
+/// pressed, and sends each hall button when it is released along with how long
+/// it was held. The driver only reports when buttons are pressed, so the call
+/// buttons are polled here instead.
+fn poll_call_buttons(
+    elevator: elev::Elevator,
+    num_floors: u8,
+    call_button_tx: Sender<poll::CallButton>,
+    hall_button_release_tx: Sender<(Request, Duration)>,
+    period: Duration,
+) {
+    let mut pressed_since: Vec<[Option<Instant>; 3]> = vec![[None; 3]; num_floors as usize];
+    loop {
+        for floor in 0..num_floors {
+            for call in [elev::HALL_UP, elev::HALL_DOWN, elev::CAB] {
+                let pressed = elevator.call_button(floor, call);
+                let since = &mut pressed_since[floor as usize][call as usize];
+                match (pressed, *since) {
+                    (true, None) => {
+                        *since = Some(Instant::now());
+                        call_button_tx.send(poll::CallButton { floor, call }).unwrap();
+                    },
+                    (false, Some(start)) => {
+                        *since = None;
+                        let request = Request::from_elev(poll::CallButton { floor, call });
+                        if !matches!(request.call, Call::Cab) {
+                            hall_button_release_tx.send((request, start.elapsed())).unwrap();
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+        thread::sleep(period);
+    }
+}
+
+/// Polls the file the load sensor writes the load of the car to, as the driver
+/// has no load sensor, sending the load whenever it changes. Until the file can
+/// be read the car is reported empty, and a file that can not be read is only
+/// logged once.
+fn poll_load(file: PathBuf, load_tx: Sender<f64>, period: Duration) {
+    let mut load = None;
+    let mut failing = false;
+    loop {
+        let reading = std::fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| contents.trim().parse::<f64>().map_err(|e| e.to_string()));
+        match reading {
+            Ok(reading) if reading.is_finite() && reading >= 0.0 => {
+                failing = false;
+                if load != Some(reading) {
+                    load = Some(reading);
+                    load_tx.send(reading).unwrap();
+                }
+            },
+            Ok(reading) => if !std::mem::replace(&mut failing, true) {
+                warn!(file = %file.display(), "The load sensor reported an invalid load of {} kilograms", reading);
+            },
+            Err(e) => if !std::mem::replace(&mut failing, true) {
+                warn!(file = %file.display(), "Could not read the load sensor: {}", e);
+            },
+        }
+        thread::sleep(period);
+    }
 }
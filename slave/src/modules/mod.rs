@@ -2,8 +2,17 @@ use std::thread;
 use std::process::Command;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crossbeam_channel::{select, unbounded};
+use network_rust::udpnet;
+
+use shared_resources::cli::Cli;
+use shared_resources::config::SlaveConfig;
+use shared_resources::config_watcher;
+use shared_resources::logging;
+use shared_resources::metrics;
+use tracing::info;
 
 use crate::utilities::debug::Debug;
 
@@ -14,79 +23,124 @@ mod network;
 mod process_pair;
 
 pub fn run() -> std::io::Result<()> {
-    let config = shared_resources::config::SlaveConfig::get();
-    println!("elevnum: {}, serverport: {}", config.elevnum, config.server.port);
+    let cli = Cli::parse_for("slave", "Operates a single elevator and executes the orders given by the master");
+    let config = SlaveConfig::get(&cli);
+    logging::init("slave", &config.node)?;
+    info!(elevnum = config.elevnum, server_port = config.server.port, "Slave starting");
+    if let Some(path) = &cli.record {
+        udpnet::recording::record_to(path)?;
+    }
+    if let Some(path) = &cli.replay {
+        udpnet::recording::replay_from(path)?;
+    }
 
     let program_dir = PathBuf::from("./.");
     let program_path: String = fs::canonicalize(&program_dir).unwrap().into_os_string().into_string().unwrap();
     let pp_update_port = config.network.pp_update_port;
-    let handle = thread::spawn(move || process_pair::process_pair(config.elevator.num_floors, pp_update_port));
+    let num_floors = config.elevator.num_floors;
+    let node = config.node.clone();
+    let handle = thread::spawn(move || process_pair::process_pair(&node, num_floors, pp_update_port));
     let backup_data = handle.join().unwrap();
    
-    process_pair::spawn_process_pair(program_path, config.elevnum, config.server.port);
+    if cli.spawns_process_pair() {
+        process_pair::spawn_process_pair(program_path, cli.process_pair_args(), config.node.headless);
+    }
+    metrics::serve(&config.metrics);
+
+    let (doors_config_tx, doors_config_rx) = unbounded();
+    let (network_config_tx, network_config_rx) = unbounded();
+    let (config_tx, config_rx) = unbounded();
+    config_watcher::watch(cli, vec![doors_config_tx, network_config_tx, config_tx])?;
 
     let (doors_activate_tx, doors_activate_rx) = unbounded();
     let (doors_closing_tx, doors_closing_rx) = unbounded();
+    let (doors_hold_tx, doors_hold_rx) = unbounded();
     let (master_hall_requests_tx, master_hall_requests_rx) = unbounded();
-    let (elevator_status_tx, elevator_status_rx) = unbounded();
+    let (network_status_tx, network_status_rx) = unbounded();
+    let (debug_status_tx, debug_status_rx) = unbounded();
 
     let config1 = config.clone();
-    let (
-        cab_button_rx, 
-        hall_button_rx, 
-        floor_sensor_rx, 
-        stop_button_rx, 
+    let io::ElevatorIo {
+        cab_button_rx,
+        hall_button_rx,
+        hall_button_release_rx,
+        floor_sensor_rx,
+        stop_button_rx,
         obstruction_rx,
+        load_rx,
         stop_button_light_tx,
         button_light_tx,
         motor_direction_tx,
         door_light_tx,
         floor_indicator_tx,
-    ) = io::init(
+    } = io::init(
         config1.server,
         config1.elevator.clone(),
+        config1.load.clone(),
     )?;
 
-    thread::Builder::new().name("doors".to_string()).spawn(move || doors::main(
-        obstruction_rx,
-        doors_activate_rx,
-        doors_closing_tx,
-        door_light_tx
-    ))?;
+    {
+        let config = config.clone();
+        thread::Builder::new().name("doors".to_string()).spawn(move || doors::main(
+            config,
+            doors_config_rx,
+            obstruction_rx,
+            doors_activate_rx,
+            doors_hold_rx,
+            doors_closing_tx,
+            door_light_tx
+        ))?;
+    }
 
-    let num_floors = config.elevator.num_floors;
+
+    let served_floors = config.served_floors.clone();
+    let independent_combination_window = config.independent_combination_window.map(Duration::from_secs_f64);
+    let load_config = config.load.clone();
     thread::Builder::new().name("fsm".to_string()).spawn(move || fsm::main(
         num_floors,
+        served_floors,
+        independent_combination_window,
+        load_config,
         backup_data,
         floor_sensor_rx,
         floor_indicator_tx,
         button_light_tx,
         doors_closing_rx,
         doors_activate_tx,
+        doors_hold_tx,
         cab_button_rx,
+        load_rx,
         motor_direction_tx,
         master_hall_requests_rx,
-        elevator_status_tx,
+        vec![network_status_tx, debug_status_tx],
     ))?;
 
     {
         let config = config.clone();
-        let elevator_status_rx = elevator_status_rx.clone();
         thread::Builder::new().name("network".to_string()).spawn(move || network::main(
             config,
+            network_config_rx,
             hall_button_rx,
+            hall_button_release_rx,
             master_hall_requests_tx,
-            elevator_status_rx,
+            network_status_rx,
         ))?;
     }
 
     let mut debug = Debug::new(num_floors);
     let mut packetloss_active = false;
-    
+    let mut headless = config.node.headless;
+
     loop {
         select! {
-            recv(elevator_status_rx) -> msg => {
-                debug.printstatus(&msg.unwrap()).unwrap();
+            recv(debug_status_rx) -> msg => {
+                let elevator_status = msg.unwrap();
+                if !headless {
+                    debug.printstatus(&elevator_status).unwrap();
+                }
+            },
+            recv(config_rx) -> msg => {
+                headless = msg.unwrap().node.headless;
             },
             recv(stop_button_rx) -> msg => {
                 if msg.unwrap() && cfg!(debug_assertions) {
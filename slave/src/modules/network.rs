@@ -1,19 +1,20 @@
-/// ----- NETWORK MODULE -----
-/// This module is responsible for collecting hall requests from the io module,
-/// states from the FSM module, and sending these to the master node. It also
-/// parses messages from the master node and delivers this elevator's orders
-/// as decided by master to the fsm module for execution.
+//! ----- NETWORK MODULE -----
+//! This module is responsible for collecting hall requests from the io module,
+//! states from the FSM module, and sending these to the master node. It also
+//! parses messages from the master node and delivers this elevator's orders
+//! as decided by master to the fsm module for execution.
 
 use std::thread::spawn;
-use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
-use crossbeam_channel::{Sender, Receiver, unbounded, select};
+use crossbeam_channel::{Sender, Receiver, unbounded, select, tick};
 use network_rust::udpnet;
+use tracing::{debug, info, warn};
 
+use shared_resources::command_message::CommandMessage;
 use shared_resources::config::SlaveConfig;
-use shared_resources::request::Request;
-use shared_resources::elevator_message::ElevatorMessage;
+use shared_resources::request::{Request, Stage};
+use shared_resources::elevator_message::{ElevatorMessage, ServiceMode};
 use shared_resources::request_buffer::RequestBuffer;
 
 use crate::utilities::elevator_status::ElevatorStatus;
@@ -21,12 +22,15 @@ use crate::utilities::master_message::MasterMessage;
 
 pub fn main(
     config: SlaveConfig,
+    config_rx: Receiver<SlaveConfig>,
     hall_button_rx: Receiver<Request>,
+    hall_button_release_rx: Receiver<(Request, Duration)>,
     master_hall_requests_tx: Sender<MasterMessage>,
     elevator_status_rx: Receiver<ElevatorStatus>,
 ) {
-    const TIMEOUT_BUFFERED_HALL_REQUESTS: u64 = 5;
-    let timer = Duration::from_millis(100);
+    // A ticker rather than a select timeout, as the master sends more often than
+    // every 100 ms, which would keep a timeout from ever running out
+    let timer = tick(Duration::from_millis(100));
     let (elevator_message_tx, elevator_message_rx) = unbounded::<ElevatorMessage>();
     {
         let elevator_message_rx = elevator_message_rx.clone();
@@ -38,13 +42,12 @@ pub fn main(
     }
     let (pp_update_tx, pp_update_rx) = unbounded::<ElevatorStatus>();
     spawn(move || {
-        println!("{:#?}", config.network.pp_update_port);
         if udpnet::bcast::tx(config.network.pp_update_port, pp_update_rx, true).is_err() {
             panic!("Could not establish sending connection to process pair backup. Port {} already in use?", config.network.pp_update_port);
         }
     });
     
-    let (command_tx, command_rx) = unbounded::<HashMap<String, Vec<Vec<bool>>>>();
+    let (command_tx, command_rx) = unbounded::<CommandMessage>();
     spawn(move || {
         if udpnet::bcast::rx(config.network.command_port, command_tx).is_err() {
             panic!("Could not establish receiving connection with master. Port {} already in use?", config.network.command_port);
@@ -53,15 +56,25 @@ pub fn main(
 
     let num_floors = config.elevator.num_floors;
 
-    let mut hall_request_buffer = RequestBuffer::new(TIMEOUT_BUFFERED_HALL_REQUESTS);
+    let mut hall_request_buffer = RequestBuffer::new("slave_hall_requests", Duration::from_secs_f64(config.timing.request_buffer_timeout));
+    // Hall requests cancelled by holding their hall button down long enough, sent to the master until it has cleared them
+    let mut cancelled_hall_request_buffer = RequestBuffer::new("slave_cancelled_hall_requests", Duration::from_secs_f64(config.timing.request_buffer_timeout));
     let mut elevator_behaviour = ElevatorStatus::new(num_floors);
     
-    const MASTER_TIMEOUT: u64 = 3;
+    let mut master_timeout = Duration::from_secs_f64(config.timing.master_timeout);
+    let mut priority = config.priority.clone();
+    let mut cancel = config.cancel.clone();
     let mut last_seen_master = Instant::now();
     let mut master_connected = false;
     let mut last_master_message = MasterMessage { 
         our_hall_requests: Vec::new(), 
-        all_hall_requests: Vec::new() 
+        all_hall_requests: Vec::new(),
+        withdrawn_hall_requests: Vec::new(),
+        trace_ids: Vec::new(),
+        arrivals: Vec::new(),
+        parking_floor: None,
+        recall_floor: None,
+        independent: false,
     };
 
     loop {
@@ -73,32 +86,90 @@ pub fn main(
                     num_floors, 
                     config.elevnum.to_string().clone()
                 );
-                hall_request_buffer.remove_confirmed_requests(&master_message.all_hall_requests);
+                for request in hall_request_buffer.remove_confirmed_requests(&master_message.all_hall_requests) {
+                    request.log_stage(Stage::Confirmed);
+                    // What a hall display would show to the passenger who pressed the button
+                    if let Some(arrival) = master_message.arrival(&request) {
+                        request.span().in_scope(|| info!(
+                            elevator = arrival.elevator,
+                            seconds = arrival.seconds,
+                            "Car {} in ~{:.0} s", arrival.elevator, arrival.seconds
+                        ));
+                    }
+                }
+                // The request may not be active on the master yet, so only its acknowledgement confirms the cancellation
+                for request in cancelled_hall_request_buffer.remove_confirmed_requests(&master_message.withdrawn_hall_requests) {
+                    request.span().in_scope(|| debug!("Hall request cancellation confirmed"));
+                }
                 master_hall_requests_tx.send(master_message.clone()).unwrap();
                 last_master_message = master_message;
                 last_seen_master = Instant::now();
+                if !master_connected {
+                    info!("Connected to master");
+                }
                 master_connected = true;
             },
             recv(hall_button_rx) -> hall_request => {
-                hall_request_buffer.insert_new_request(hall_request.unwrap());
+                let mut hall_request = hall_request.unwrap();
+                if matches!(elevator_behaviour.mode, ServiceMode::Recalling | ServiceMode::Recalled) {
+                    hall_request.span().in_scope(|| info!("Hall request ignored, the elevator is recalled"));
+                    continue;
+                }
+                hall_request.priority = priority.level(&hall_request);
+                hall_request.log_stage(Stage::Placed);
+                hall_request_buffer.insert_new_request(hall_request);
+            },
+            recv(hall_button_release_rx) -> msg => {
+                let (mut hall_request, held) = msg.unwrap();
+                if cancel.long_press.is_none_or(|long_press| held.as_secs_f64() < long_press) {
+                    continue;
+                }
+                hall_request.trace_id = last_master_message.trace_ids.get(hall_request.floor as usize)
+                    .and_then(|calls| calls.get(hall_request.column()))
+                    .copied()
+                    .flatten();
+                hall_request.span().in_scope(|| info!(held = held.as_secs_f64(), "Hall request cancelled by a long press"));
+                // The press that started the long press must not place the request again
+                hall_request_buffer.remove_request(&hall_request);
+                cancelled_hall_request_buffer.insert_new_request(hall_request);
             },
             recv(elevator_status_rx) -> elevator_behaviour_msg => {
                 elevator_behaviour = elevator_behaviour_msg.unwrap();
             },
-            default(timer) => {
+            recv(config_rx) -> msg => {
+                let config = msg.unwrap();
+                hall_request_buffer.set_timeout(Duration::from_secs_f64(config.timing.request_buffer_timeout));
+                cancelled_hall_request_buffer.set_timeout(Duration::from_secs_f64(config.timing.request_buffer_timeout));
+                master_timeout = Duration::from_secs_f64(config.timing.master_timeout);
+                priority = config.priority;
+                cancel = config.cancel;
+            },
+            recv(timer) -> _ => {
                 hall_request_buffer.remove_timed_out_orders();
+                cancelled_hall_request_buffer.remove_timed_out_orders();
                 let message = generate_elevator_message(
                     config.elevnum.to_string().clone(),
                     elevator_behaviour.clone(),
-                    &hall_request_buffer
+                    &hall_request_buffer,
+                    &cancelled_hall_request_buffer,
+                    &config.served_floors,
                 );
                 elevator_message_tx.send(message).unwrap();
                 pp_update_tx.send(elevator_behaviour.clone()).unwrap();
-                if master_connected && last_seen_master.elapsed() > Duration::from_secs(MASTER_TIMEOUT) {
+                if master_connected && last_seen_master.elapsed() > master_timeout {
                     master_connected = false;
+                    warn!("Lost connection to master, serving all hall requests at the floors served");
+                    // A recall lasts until the master resets it
                     master_hall_requests_tx.send(MasterMessage {
-                        our_hall_requests: last_master_message.all_hall_requests.clone(),
+                        our_hall_requests: last_master_message.reachable_hall_requests(&config.served_floors),
                         all_hall_requests: last_master_message.all_hall_requests.clone(),
+                        withdrawn_hall_requests: last_master_message.withdrawn_hall_requests.clone(),
+                        trace_ids: last_master_message.trace_ids.clone(),
+                        // Without the master the elevators no longer know each other's plans
+                        arrivals: Vec::new(),
+                        parking_floor: None,
+                        recall_floor: last_master_message.recall_floor,
+                        independent: last_master_message.independent,
                     }).unwrap();
                 }
             },
@@ -109,14 +180,21 @@ pub fn main(
 pub fn generate_elevator_message(
     id: String, 
     elevator_behaviour: ElevatorStatus, 
-    request_buffer: &RequestBuffer
+    request_buffer: &RequestBuffer,
+    cancelled_request_buffer: &RequestBuffer,
+    served_floors: &[bool],
 ) -> ElevatorMessage {
     ElevatorMessage {
-        id: id,
+        id,
         behaviour: elevator_behaviour.behaviour.as_string(),
         floor: elevator_behaviour.floor,
         direction: elevator_behaviour.direction.as_string().unwrap(),
         cab_requests: elevator_behaviour.requests.get_cab_requests(),
         new_hall_orders: request_buffer.get_new_requests(),
+        cancelled_hall_orders: cancelled_request_buffer.get_new_requests(),
+        served_floors: served_floors.to_vec(),
+        load: elevator_behaviour.load,
+        full: elevator_behaviour.full,
+        mode: elevator_behaviour.mode,
     }
 }
@@ -1,22 +1,28 @@
+use std::env;
 use std::process;
 use std::thread;
 use std::time::Duration;
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use crossbeam_channel::{select, unbounded};
+use tracing::info;
 use network_rust::udpnet::{self, bcast::BcError};
+use shared_resources::cli::{self, Role};
+use shared_resources::config::NodeConfig;
 
 use crate::utilities::elevator_status::ElevatorStatus;
 
-pub fn process_pair(num_floors: u8, pp_update_port: u16) -> ElevatorStatus {
-    println!("SLAVE process pair running on port: {:#?}\n------------------------", pp_update_port);
+pub fn process_pair(node: &NodeConfig, num_floors: u8, pp_update_port: u16) -> ElevatorStatus {
     let mut backup_data: ElevatorStatus = ElevatorStatus::new(num_floors);
+    if node.role == Role::Primary {
+        return backup_data
+    }
+    info!(port = pp_update_port, "Slave process pair running, waiting for the primary to go silent");
 
     let (pp_update_tx, pp_update_rx) = unbounded::<ElevatorStatus>();
     thread::Builder::new().name("process_pair_recieve_from_slave".to_string()).spawn(move || {
-        match udpnet::bcast::rx(pp_update_port, pp_update_tx) {
-            Err(BcError::IOError(_e)) => process::exit(1),
-            _ => (),
+        if let Err(BcError::IOError(_e)) = udpnet::bcast::rx(pp_update_port, pp_update_tx) {
+            process::exit(1);
         }
     }).ok();
 
@@ -26,6 +32,7 @@ pub fn process_pair(num_floors: u8, pp_update_port: u16) -> ElevatorStatus {
                 backup_data = data.clone().unwrap();
             },
             default(Duration::from_secs(2)) => {
+                info!("Primary went silent, taking over");
                 return backup_data
             }
         }
@@ -34,37 +41,34 @@ pub fn process_pair(num_floors: u8, pp_update_port: u16) -> ElevatorStatus {
 
 pub fn spawn_process_pair(
     program_path: String, 
-    elevnum: u8,
-    server_port: u16,
+    args: Vec<String>,
+    headless: bool,
 ) {
-    if cfg!(target_os = "linux") {
+    if headless {
+        // The process pair is meant to outlive this program, so it is never waited on
+        #[allow(clippy::zombie_processes)]
+        let _process_pair = Command::new(env::current_exe().expect("failed to locate executable"))
+            .args(args)
+            .current_dir(program_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start backup");
+    } else if cfg!(target_os = "linux") {
+        // The arguments are passed on as they are, without a shell to split them
         Command::new("gnome-terminal")
-            .arg("--")
-            .arg("/bin/sh")
-            .arg("-c")
-            .arg("cd ".to_owned()
-                + &program_path
-                + " && "
-                + "cargo run"
-                + " --"
-                + " --num "
-                + &elevnum.to_string()
-                + " --serverport "
-                + &server_port.to_string())
+            .arg(format!("--working-directory={}", program_path))
+            .args(["--", "cargo", "run", "--"])
+            .args(&args)
             .output()
             .expect("failed to start backup");
     } else if cfg!(target_os = "macos") {
+        let args: Vec<String> = args.iter().map(|arg| cli::shell_quote(arg)).collect();
+        let command = format!("cd {} && cargo run -- {}", cli::shell_quote(&program_path), args.join(" "));
         Command::new("osascript")
             .arg("-e")
-            .arg("tell app \"Terminal\" to do script \"cd ".to_owned() 
-                + &program_path
-                + " && "
-                + "cargo run"
-                + " --"
-                + " --num "
-                + &elevnum.to_string()
-                + " --serverport "
-                + &server_port.to_string() + "\"")
+            .arg(format!("tell app \"Terminal\" to do script \"{}\"", command.replace('\\', "\\\\").replace('"', "\\\"")))
             .output()
             .expect("failed to start backup");
     }
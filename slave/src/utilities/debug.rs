@@ -15,7 +15,7 @@ impl Debug {
     pub fn new(num_floors: u8) -> Self {
         Debug { 
             stdout: stdout(),
-            num_floors: num_floors,
+            num_floors,
         }
     }
 
@@ -1,4 +1,5 @@
 use shared_resources::call::Call;
+use shared_resources::elevator_message::ServiceMode;
 
 use crate::utilities::direction::Direction;
 use crate::utilities::request_collection::RequestCollection;
@@ -26,6 +27,17 @@ pub struct ElevatorStatus {
     pub behaviour: Behaviour,
     pub floor: u8,  
     pub direction: Direction,
+    /// Load of the car in kilograms
+    #[serde(default)]
+    pub load: f64,
+    /// Whether the car is loaded to its full share of the capacity
+    #[serde(default)]
+    pub full: bool,
+    #[serde(default)]
+    pub mode: ServiceMode,
+    /// Whether independent service was switched on at the car
+    #[serde(default)]
+    pub independent_switch: bool,
 }
 
 impl ElevatorStatus {
@@ -35,19 +47,45 @@ impl ElevatorStatus {
             behaviour: Behaviour::Moving, 
             floor: 0, 
             direction: Direction::Down,
+            load: 0.0,
+            full: false,
+            mode: ServiceMode::Normal,
+            independent_switch: false,
         }
     }
     
-    pub fn serve_requests_here(&mut self) {
+    /// Serves the requests at the current floor in the current direction,
+    /// returning the destinations of the passengers picked up, which are added
+    /// as cab requests
+    pub fn serve_requests_here(&mut self) -> Vec<u8> {
         self.requests.clear_request(Call::Cab, self.floor);
         self.requests.clear_request(self.direction.to_call().unwrap(), self.floor);
+        self.requests.pick_up_passengers(self.floor, self.direction)
     }
     
+    /// Whether to stop at the current floor. A full car passes the hall
+    /// requests on its way while it has passengers to drop off.
     pub fn should_stop(&self) -> bool {
+        if self.bypassing_hall_requests() {
+            return self.requests.cab_request_at_floor(self.floor)
+                || !self.requests.further_requests_in_direction(self.floor, self.direction)
+        }
         self.requests.should_stop(self.floor, self.direction)
     }
 
+    /// Drops every request, as the elevator is taken out of normal service
+    pub fn clear_requests(&mut self, num_floors: u8) {
+        self.requests = RequestCollection::new(num_floors);
+    }
+
+    pub fn bypassing_hall_requests(&self) -> bool {
+        self.full && self.requests.get_cab_requests().contains(&true)
+    }
+
     pub fn requests_at_this_floor(&self) -> bool {
+        if self.bypassing_hall_requests() {
+            return self.requests.cab_request_at_floor(self.floor)
+        }
         for request in self.requests.get_requests_at_floor(self.floor) {
             if request {
                 return true
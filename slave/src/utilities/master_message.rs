@@ -1,33 +1,95 @@
-use std::collections::HashMap;
-
-use shared_resources::call::Call;
+use shared_resources::command_message::{Arrival, CommandMessage};
+use shared_resources::request::{Request, TraceId};
 
 #[derive(Clone)]
 pub struct MasterMessage {
+    /// Hall requests indexed by floor and column as given by `Request::column`
     pub our_hall_requests: Vec<Vec<bool>>,
+    /// Every active hall request, whether it is assigned to an elevator or not
     pub all_hall_requests: Vec<Vec<bool>>,
+    /// Hall requests whose cancellation the master has acknowledged
+    pub withdrawn_hall_requests: Vec<Vec<bool>>,
+    pub trace_ids: Vec<Vec<Option<TraceId>>>,
+    /// Predicted arrival at each assigned hall request, indexed by floor and column
+    pub arrivals: Vec<Vec<Option<Arrival>>>,
+    /// Floor to park at while there are no requests to serve
+    pub parking_floor: Option<u8>,
+    /// Floor to travel to non-stop, while an emergency recall is active
+    pub recall_floor: Option<u8>,
+    /// Whether an operator has put the elevator in independent service
+    pub independent: bool,
 }
 
 impl MasterMessage {
-    pub fn parse(message: HashMap<String, Vec<Vec<bool>>>, num_floors: u8, id: String) -> Self {
-        let mut all_hall_requests = vec![vec![false; Call::num_hall_calls() as usize]; num_floors as usize];
-        for (_, requests) in &message {
-            for floor in 0..num_floors {
-                for btn in Call::iter_hall() {
-                    if requests[floor as usize][btn as usize] {
-                        all_hall_requests[floor as usize][btn as usize] = true;
-                    }
+    pub fn parse(message: CommandMessage, num_floors: u8, id: String) -> Self {
+        let hall_columns = Request::hall_columns(num_floors);
+        let mut all_hall_requests = message.hall_requests;
+        all_hall_requests.resize(num_floors as usize, Vec::new());
+        for calls in all_hall_requests.iter_mut() {
+            calls.resize(hall_columns, false);
+        }
+        for requests in message.assigned_hall_requests.values() {
+            for (all_calls, calls) in all_hall_requests.iter_mut().zip(requests) {
+                for (all_active, &active) in all_calls.iter_mut().zip(calls) {
+                    *all_active |= active;
                 }
             }
         }
-        let empty_vec = vec![vec![false; Call::num_hall_calls() as usize]; num_floors as usize];
-        let our_hall_requests = match message.get(&id) {
-            Some(hr) => hr,
-            None => &empty_vec,
-        };
+        let mut withdrawn_hall_requests = vec![vec![false; hall_columns]; num_floors as usize];
+        for request in &message.withdrawn_hall_requests {
+            if let Some(withdrawn) = withdrawn_hall_requests.get_mut(request.floor as usize).and_then(|calls| calls.get_mut(request.column())) {
+                *withdrawn = true;
+            }
+        }
+        let mut our_hall_requests = message.assigned_hall_requests.get(&id)
+            .cloned()
+            .unwrap_or_else(|| vec![vec![false; hall_columns]; num_floors as usize]);
+        for calls in our_hall_requests.iter_mut() {
+            calls.resize(hall_columns, false);
+        }
+        let mut trace_ids = message.trace_ids;
+        trace_ids.resize(num_floors as usize, Vec::new());
+        for calls in trace_ids.iter_mut() {
+            calls.resize(hall_columns, None);
+        }
+        let mut arrivals = message.arrivals;
+        arrivals.resize(num_floors as usize, Vec::new());
+        for calls in arrivals.iter_mut() {
+            calls.resize(hall_columns, None);
+        }
+        let parking_floor = message.parking_floors.get(&id).copied().filter(|&floor| floor < num_floors);
+        let recall_floor = message.recall_floor.filter(|&floor| floor < num_floors);
+        let independent = message.independent.contains(&id);
         MasterMessage { 
-            our_hall_requests: our_hall_requests.clone(), 
-            all_hall_requests: all_hall_requests,
+            our_hall_requests, 
+            all_hall_requests,
+            withdrawn_hall_requests,
+            trace_ids,
+            arrivals,
+            parking_floor,
+            recall_floor,
+            independent,
         }
     }
+
+    /// Predicted arrival of the elevator assigned the hall request
+    pub fn arrival(&self, request: &Request) -> Option<&Arrival> {
+        self.arrivals.get(request.floor as usize)
+            .and_then(|calls| calls.get(request.column()))
+            .and_then(Option::as_ref)
+    }
+
+    /// All hall requests the elevator can serve, those at the floors it
+    /// serves whose destination, if any, it serves as well
+    pub fn reachable_hall_requests(&self, served_floors: &[bool]) -> Vec<Vec<bool>> {
+        let serves = |floor: u8| served_floors.get(floor as usize).copied().unwrap_or(false);
+        self.all_hall_requests.iter().enumerate()
+            .map(|(floor, calls)| calls.iter().enumerate()
+                .map(|(column, &active)| {
+                    let request = Request::from_column(floor as u8, column, None);
+                    active && serves(request.floor) && request.destination.is_none_or(serves)
+                })
+                .collect())
+            .collect()
+    }
 }
@@ -1,4 +1,5 @@
 use shared_resources::call::Call;
+use shared_resources::request::Request;
 
 use crate::utilities::direction::Direction;
 
@@ -6,13 +7,19 @@ use crate::utilities::direction::Direction;
 pub struct RequestCollection {
     requests: Vec<Vec<bool>>,
     num_floors: u8,
+    /// Destination calls assigned to the elevator, indexed by the floor of the
+    /// passenger and the floor they are going to. The passengers are picked up
+    /// like hall calls in their direction of travel.
+    #[serde(default)]
+    destinations: Vec<Vec<bool>>,
 }
 
 impl RequestCollection {
     pub fn new(num_floors: u8) -> Self {
         RequestCollection {
-            num_floors: num_floors,
+            num_floors,
             requests: vec![vec![false; Call::num_calls() as usize]; num_floors as usize],
+            destinations: vec![vec![false; num_floors as usize]; num_floors as usize],
         }
     }
 
@@ -24,12 +31,49 @@ impl RequestCollection {
         self.requests[floor as usize][call as usize] = false;
     }
 
+    /// Replaces the hall requests with `our_hall_requests`, indexed by floor
+    /// and column as given by `Request::column`
     pub fn update_hall_requests(&mut self, our_hall_requests: Vec<Vec<bool>>) {
+        self.destinations = vec![vec![false; self.num_floors as usize]; self.num_floors as usize];
         for floor in 0..self.num_floors {
             for btn in Call::iter_hall() {
                 self.requests[floor as usize][btn as usize] = our_hall_requests[floor as usize][btn as usize];
             }
+            let destination_calls = our_hall_requests[floor as usize].iter().skip(Call::num_hall_calls() as usize);
+            for (destination, &active) in destination_calls.enumerate().take(self.num_floors as usize) {
+                if active && destination != floor as usize {
+                    let pickup = Request::destination(floor, destination as u8).hall_call();
+                    self.requests[floor as usize][pickup as usize] = true;
+                    self.destinations[floor as usize][destination] = true;
+                }
+            }
+        }
+    }
+
+    /// Destinations of the passengers waiting at `floor` to travel in `direction`
+    pub fn destinations_at_floor(&self, floor: u8, direction: Direction) -> Vec<u8> {
+        let Some(destinations) = self.destinations.get(floor as usize) else {
+            return Vec::new()
+        };
+        destinations.iter().enumerate()
+            .filter(|&(destination, &active)| active && match direction {
+                Direction::Up => destination > floor as usize,
+                Direction::Down => destination < floor as usize,
+                Direction::Stop => false,
+            })
+            .map(|(destination, _)| destination as u8)
+            .collect()
+    }
+
+    /// Turns the destination calls of the passengers picked up at `floor` into
+    /// cab requests, returning their destinations
+    pub fn pick_up_passengers(&mut self, floor: u8, direction: Direction) -> Vec<u8> {
+        let destinations = self.destinations_at_floor(floor, direction);
+        for &destination in &destinations {
+            self.destinations[floor as usize][destination as usize] = false;
+            self.add_request(destination, Call::Cab);
         }
+        destinations
     }
 
     pub fn get_cab_requests(&self) -> Vec<bool> {